@@ -0,0 +1,36 @@
+// Imports every mesh in `assets/` into OUT_DIR as `<name>.mesh`, where
+// `add_mesh!` embeds it from
+
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+
+
+fn main() {
+    let out = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let assets = Path::new("assets");
+    println!("cargo:rerun-if-changed={}", assets.display());
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(assets)
+        .expect("assets directory")
+        .map(|entry| entry.expect("assets directory entry").path())
+        .filter(|path| {
+            path.extension().and_then(|e| e.to_str())
+                .is_some_and(|e| neutrino_assets::EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        })
+        .collect();
+    paths.sort();
+
+    let mut names = HashSet::new();
+    for path in paths {
+        let mesh = neutrino_assets::import(&path)
+            .unwrap_or_else(|e| panic!("importing {}: {:?}", path.display(), e));
+        let name = path.file_stem().expect("file name");
+        assert!(names.insert(name.to_owned()), "two meshes named {:?} in assets", name);
+        let target = out.join(name).with_extension("mesh");
+        fs::write(&target, mesh.encode())
+            .unwrap_or_else(|e| panic!("writing {}: {}", target.display(), e));
+    }
+}
@@ -1,23 +1,32 @@
-import { Shader, Buffer, Entity } from './webgl.js'
-
-
-const width = 800
-const height = 800
+import { Shader, Buffer, Mesh, Entity, DebugLines } from './webgl.js'
 
 
 const canvas = document.getElementById('canvas')
-canvas.width = width
-canvas.height = height
 const gl = canvas.getContext('webgl2')
 
+// the canvas fills the window, the engine is told whenever it changes size
+function fitCanvas() {
+    canvas.width = Math.max(1, Math.floor(window.innerWidth))
+    canvas.height = Math.max(1, Math.floor(window.innerHeight))
+    if( wasm ) {
+        wasm.instance.exports.resize(wasm.ptr, canvas.width, canvas.height)
+    }
+}
+
 
 
 const textDecoder = new TextDecoder()
+const textEncoder = new TextEncoder()
+
+// `Level` in src/log.rs, in order
+const logLevels = ['trace', 'debug', 'info', 'warn', 'error', 'off']
 let wasm
 let shaders = new Map()
 let buffers = new Map()
+let meshes = new Map()
 let entities = new Map()
-let allIds = new Set()
+let nextId = 1
+let freeIds = []
 
 
 const wasmImports = {
@@ -36,6 +45,18 @@ const wasmImports = {
             )
         },
 
+        js_log: (level, module_ptr, module_len, text_ptr, text_len) => {
+            let data
+            data = new Uint8Array(wasm.memory.buffer, module_ptr, module_len)
+            const module = textDecoder.decode(data)
+            data = new Uint8Array(wasm.memory.buffer, text_ptr, text_len)
+            const text = textDecoder.decode(data)
+            const log = [
+                console.debug, console.debug, console.info, console.warn, console.error
+            ][level]
+            log(`[${logLevels[level]} ${module}] ${text}`)
+        },
+
         js_add_shader: (
             name_ptr, name_len, vert_ptr, vert_len, frag_ptr, frag_len
         ) => {
@@ -95,39 +116,200 @@ const wasmImports = {
             buffers.set(name, buffer)
         },
 
+        // the mesh is uploaded as it is, its streams are buffers named
+        // "<mesh>.<attribute>"
+        js_add_mesh: (name_ptr, name_len, data_ptr, data_len) => {
+            let data
+            data = new Uint8Array(wasm.memory.buffer, name_ptr, name_len)
+            let name = textDecoder.decode(data)
+
+            data = new Uint8Array(wasm.memory.buffer, data_ptr, data_len)
+            const mesh = new Mesh(data)
+            mesh.load(gl)
+            meshes.set(name, mesh)
+            for( const [stream, buffer] of mesh.streams ) {
+                buffers.set(`${name}.${stream}`, buffer)
+            }
+        },
+
         js_add_entity: (ptr, len) => {
             const data = new Uint8Array(wasm.memory.buffer, ptr, len)
             let meta = JSON.parse(textDecoder.decode(data))
 
-            let id = Math.floor(Math.random() * 999998) + 1
-            while( allIds.has(id) ) {
-                id = Math.floor(Math.random() * 999998) + 1
-            }
-            allIds.add(id)
+            const id = freeIds.length > 0 ? freeIds.pop() : nextId++
 
             const entity = new Entity(
-                meta.shader, meta.count, meta.attributes, meta.uniforms
+                meta.shader, meta.count, meta.attributes, meta.uniforms,
+                meta.indices,
             )
-            entity.load(gl, shaders, buffers)
+            entity.load(gl, shaders, buffers, meshes)
             shaders.get(entity.shader).entities.push(id)
             entities.set(id, entity)
 
             return id
         },
 
-        // js_destroy_entity: (id) => {
-        //     entities[id].destroy(gl, shaders[entities[id].shader].program)
-        //     const index = shaders[entities[id].shader].entities.indexOf(id)
-        //     shaders[entities[id].shader].entities.splice(index, 1)
-        //     delete entities[id]
-        //     allIds.delete(id)
-        // },
+        js_now: () => performance.now(),
+
+        js_destroy_entity: (id) => {
+            const entity = entities.get(id)
+            const shader = shaders.get(entity.shader)
+            entity.destroy(gl)
+            shader.entities.splice(shader.entities.indexOf(id), 1)
+            entities.delete(id)
+            freeIds.push(id)
+        },
 
     },
 }
 
 
-let keys = 0
+// `Key` codes in src/engine/input.rs are indices into this list
+const keyCodes = [
+    'KeyA', 'KeyB', 'KeyC', 'KeyD', 'KeyE', 'KeyF', 'KeyG', 'KeyH', 'KeyI',
+    'KeyJ', 'KeyK', 'KeyL', 'KeyM', 'KeyN', 'KeyO', 'KeyP', 'KeyQ', 'KeyR',
+    'KeyS', 'KeyT', 'KeyU', 'KeyV', 'KeyW', 'KeyX', 'KeyY', 'KeyZ',
+    'Digit0', 'Digit1', 'Digit2', 'Digit3', 'Digit4',
+    'Digit5', 'Digit6', 'Digit7', 'Digit8', 'Digit9',
+    'ArrowUp', 'ArrowDown', 'ArrowLeft', 'ArrowRight',
+    'Space', 'Enter', 'Escape', 'Tab', 'ShiftLeft', 'ControlLeft', 'AltLeft',
+]
+
+const input = { words: null, floats: null, mouseDx: 0, mouseDy: 0 }
+let debugDraw = false
+let debugLines
+
+// views into wasm memory, they have to be created again whenever the
+// memory grows
+function createViews() {
+    const exports = wasm.instance.exports
+    const sectionSize = (name) => new Uint32Array(
+        WebAssembly.Module.customSections(wasm.module, name)[0]
+    )[0]
+
+    // the frame buffer is u32 words, uniform values are f32 bit patterns
+    const framebufferPtr = exports.get_framebuffer_pointer(wasm.ptr)
+    const framebufferSize = sectionSize('BUFFER_SIZE')
+    wasm.framebuffer = new Uint32Array(
+        wasm.memory.buffer, framebufferPtr, framebufferSize
+    )
+    wasm.framebufferFloats = new Float32Array(
+        wasm.memory.buffer, framebufferPtr, framebufferSize
+    )
+
+    wasm.debugbuffer = new Float32Array(
+        wasm.memory.buffer,
+        exports.get_debug_buffer_pointer(wasm.ptr),
+        sectionSize('DEBUG_BUFFER_SIZE')
+    )
+
+    wasm.profiler = new Uint32Array(
+        wasm.memory.buffer,
+        exports.get_profiler_pointer(wasm.ptr),
+        sectionSize('PROFILER_SIZE')
+    )
+    wasm.profilerFloats = new Float32Array(
+        wasm.profiler.buffer, wasm.profiler.byteOffset, wasm.profiler.length
+    )
+
+    // layout of `Input` in src/engine/input.rs, in u32 words
+    const inputPtr = exports.get_input_pointer(wasm.ptr)
+    input.words = new Uint32Array(wasm.memory.buffer, inputPtr, 14)
+    input.floats = new Float32Array(wasm.memory.buffer, inputPtr, 14)
+}
+
+function memoryGrew() {
+    return wasm.framebuffer.buffer !== wasm.memory.buffer
+}
+
+
+// Sessions are recorded as the seed and the input of every frame, replaying
+// one reproduces it exactly. Use from the console to attach to bug reports.
+window.neutrino = {
+    startRecording: () => wasm.instance.exports.start_recording(wasm.ptr),
+
+    stopRecording: () => {
+        const len = wasm.instance.exports.stop_recording(wasm.ptr)
+        const ptr = wasm.instance.exports.get_transfer_pointer(wasm.ptr)
+        return new Uint8Array(wasm.memory.buffer, ptr, len).slice()
+    },
+
+    playRecording: (bytes) => {
+        const ptr = wasm.instance.exports.resize_transfer(wasm.ptr, bytes.length)
+        new Uint8Array(wasm.memory.buffer, ptr, bytes.length).set(bytes)
+        if( memoryGrew() ) { createViews() }
+        return wasm.instance.exports.play_recording(wasm.ptr)
+    },
+
+    // snapshots hold the full state of the game and can be loaded any time
+    saveSnapshot: () => {
+        const len = wasm.instance.exports.save_snapshot(wasm.ptr)
+        if( memoryGrew() ) { createViews() }
+        const ptr = wasm.instance.exports.get_transfer_pointer(wasm.ptr)
+        return new Uint8Array(wasm.memory.buffer, ptr, len).slice()
+    },
+
+    loadSnapshot: (bytes) => {
+        const ptr = wasm.instance.exports.resize_transfer(wasm.ptr, bytes.length)
+        new Uint8Array(wasm.memory.buffer, ptr, bytes.length).set(bytes)
+        if( memoryGrew() ) { createViews() }
+        return wasm.instance.exports.load_snapshot(wasm.ptr)
+    },
+
+    // game time can be paused, stepped a frame at a time and slowed down
+    pause: () => wasm.instance.exports.set_paused(wasm.ptr, true),
+    resume: () => wasm.instance.exports.set_paused(wasm.ptr, false),
+    step: () => wasm.instance.exports.step(wasm.ptr),
+    setTimeScale: (scale) => wasm.instance.exports.set_time_scale(wasm.ptr, scale),
+
+    toggleProjection: () => wasm.instance.exports.toggle_projection(wasm.ptr),
+
+    // timings in milliseconds and counters of the last frames, newest first
+    profile: (count = 60) => readProfile(count),
+    setProfiling: (enabled) => wasm.instance.exports.set_profiling(wasm.ptr, enabled),
+
+    // setLogLevel('game::ship', 'trace'), an empty module sets the default
+    setLogLevel: (module, level) => {
+        const bytes = textEncoder.encode(module)
+        const ptr = wasm.instance.exports.resize_transfer(wasm.ptr, bytes.length)
+        new Uint8Array(wasm.memory.buffer, ptr, bytes.length).set(bytes)
+        if( memoryGrew() ) { createViews() }
+        return wasm.instance.exports.set_log_level(
+            wasm.ptr, bytes.length, logLevels.indexOf(level)
+        )
+    },
+}
+
+
+// `Scope` and `Counter` in src/engine/profiler.rs, in order
+const profileScopes = [
+    'frame', 'input', 'ai', 'physics', 'collision', 'events', 'renderUpload',
+]
+const profileCounters = [
+    'collisionTests', 'bodiesIntegrated', 'bufferWords', 'bufferCapacity',
+    'commands', 'droppedCommands',
+]
+
+function readProfile(count) {
+    if( memoryGrew() ) { createViews() }
+    const words = wasm.profiler
+    const floats = wasm.profilerFloats
+    const [history, recordWords, recorded] = words
+    const frames = []
+    for( let ago = 0; ago < Math.min(count, history, recorded); ago++ ) {
+        const start = 3 + ((recorded - 1 - ago) % history) * recordWords
+        const frame = { frame: words[start], times: {}, counters: {} }
+        profileScopes.forEach((name, i) => {
+            frame.times[name] = floats[start + 1 + i]
+        })
+        profileCounters.forEach((name, i) => {
+            frame.counters[name] = words[start + 1 + profileScopes.length + i]
+        })
+        frames.push(frame)
+    }
+    return frames
+}
+
 
 async function load() {
 
@@ -143,38 +325,53 @@ async function load() {
     wasm.memory = wasm.instance.exports.memory
 
     wasm.ptr = wasm.instance.exports.init()
+    fitCanvas()
+    window.addEventListener('resize', fitCanvas)
 
-    wasm.framebuffer = new Float32Array(
-        wasm.memory.buffer,
-        wasm.instance.exports.get_framebuffer_pointer(wasm.ptr),
-        new Uint32Array(
-            WebAssembly.Module.customSections(wasm.module, 'BUFFER_SIZE')[0]
-        )[0]
-    )
+    createViews()
+
+    debugLines = new DebugLines(shaders.get('debug_line'))
+    debugLines.load(gl)
 
-    const keyShifts = {
-        KeyW: 0, KeyA: 1, KeyS: 2, KeyD: 3,
-        KeyQ: 4, KeyE: 5, Space: 6,
+    const setKey = (code, down) => {
+        const key = keyCodes.indexOf(code)
+        if( key == -1 ) { return }
+        if( down ) {
+            input.words[key >> 5] |= 1 << (key & 31)
+        } else {
+            input.words[key >> 5] &= ~(1 << (key & 31))
+        }
     }
 
     window.addEventListener("keydown", (event) => {
         if (event.isComposing || event.keyCode === 229 || event.repeat) { return }
-        if( Object.keys(keyShifts).includes(event.code) ) {
-            keys |= 0b1 << keyShifts[event.code]
+        if( event.code == 'Backquote' ) {
+            debugDraw = !debugDraw
+            wasm.instance.exports.set_debug_draw(wasm.ptr, debugDraw)
         }
-        // console.log('key down!', event.code, keys.toString(2), keys)
+        setKey(event.code, true)
     })
 
     window.addEventListener("keyup", (event) => {
-        if( Object.keys(keyShifts).includes(event.code) ) {
-            keys &= ~(0b1 << keyShifts[event.code])
-        }
-        // console.log('key up!', event.code, keys.toString(2), keys)
+        setKey(event.code, false)
+    })
+
+    canvas.addEventListener("mousemove", (event) => {
+        input.floats[4] = event.offsetX
+        input.floats[5] = event.offsetY
+        input.mouseDx += event.movementX
+        input.mouseDy += event.movementY
+    })
+    canvas.addEventListener("mousedown", (event) => {
+        input.words[8] |= 1 << event.button
+    })
+    window.addEventListener("mouseup", (event) => {
+        input.words[8] &= ~(1 << event.button)
     })
 
     gl.enable(gl.CULL_FACE)
     gl.enable(gl.DEPTH_TEST)
-    gl.viewport(0, 0, gl.canvas.width, gl.canvas.height)
+    gl.enable(gl.SCISSOR_TEST)
     gl.clearColor(0, 0, 0, 1)
 
     requestAnimationFrame(render)
@@ -183,9 +380,87 @@ load()
 
 
 
-let t = 0
+const FRAME_VERSION = 2
+const FLAG_OVERFLOW = 1
+const OP_SET_UNIFORM = 1
+const OP_SET_VISIBLE = 2
+const OP_SET_VIEWPORT = 3
+const OP_SET_LAYERS = 4
+
+// rect, layer mask and view projection of every view, sent each frame
+let viewports = []
+
 let prevdt, dt
-let bufferLen, b
+let overflowWarned = false
+
+
+// mouse movement is summed up between frames, the gamepad is polled
+function updateInput() {
+    input.floats[6] = input.mouseDx
+    input.floats[7] = input.mouseDy
+    input.mouseDx = 0
+    input.mouseDy = 0
+
+    const gamepad = navigator.getGamepads ? navigator.getGamepads()[0] : null
+    let buttons = 0
+    for( let i = 0; i < 4; i++ ) {
+        input.floats[9 + i] = gamepad && i < gamepad.axes.length ? gamepad.axes[i] : 0
+    }
+    if( gamepad ) {
+        gamepad.buttons.forEach((button, i) => {
+            if( button.pressed && i < 32 ) { buttons |= 1 << i }
+        })
+    }
+    input.words[13] = buttons
+}
+
+
+function readFrame() {
+    const words = wasm.framebuffer
+    const floats = wasm.framebufferFloats
+
+    if( words[0] != FRAME_VERSION ) {
+        throw new Error(`unsupported frame buffer version ${words[0]}`)
+    }
+    if( words[3] & FLAG_OVERFLOW && !overflowWarned ) {
+        console.warn(`frame buffer full, ${words[4]} commands dropped`)
+        overflowWarned = true
+    }
+
+    viewports = []
+    let b = 5
+    for( let i = 0; i < words[2]; i++ ) {
+        const opcode = words[b] >>> 24
+        const len = words[b] & 0xffffff
+        const payload = b + 1
+        b = payload + len
+
+        switch( opcode ) {
+            case OP_SET_UNIFORM:
+                entities.get(words[payload]).addFrameUniformUpdate(
+                    words[payload + 1],
+                    words[payload + 2],
+                    floats.slice(payload + 3, b)
+                )
+                break
+            case OP_SET_VISIBLE:
+                entities.get(words[payload]).visible = words[payload + 1] == 1
+                break
+            case OP_SET_VIEWPORT:
+                viewports[words[payload]] = {
+                    mask: words[payload + 1],
+                    rect: floats.slice(payload + 2, payload + 6),
+                    viewProjection: floats.slice(payload + 6, b),
+                }
+                break
+            case OP_SET_LAYERS:
+                entities.get(words[payload]).layers = words[payload + 1]
+                break
+            default:
+                console.warn(`unknown frame buffer opcode ${opcode}`)
+        }
+    }
+}
 
 
 function render(currentdt) {
@@ -196,32 +471,49 @@ function render(currentdt) {
     dt = Math.floor(dt) % 2 == 0 ? dt : Math.floor(dt) - 1
     dt = dt / 1000
 
-    gl.clear(gl.COLOR_BUFFER_BIT | gl.DEPTH_BUFFER_BIT)
-
-    wasm.instance.exports.render(wasm.ptr, t, dt, keys)
-
-    bufferLen = wasm.framebuffer[0]
-    b = 1
-    while( b < bufferLen ) {
-        entities.get(wasm.framebuffer[b++]).addFrameUniformUpdate(
-            wasm.framebuffer[b++],
-            wasm.framebuffer[b++],
-            wasm.framebuffer.slice(b + 1, b + wasm.framebuffer[b] + 1)
-        )
-        b += wasm.framebuffer[b] + 1
-    }
+    if( memoryGrew() ) { createViews() }
+    updateInput()
+    wasm.instance.exports.render(wasm.ptr, dt)
+    if( memoryGrew() ) { createViews() }
+
+    readFrame()
+
+    // one pass per viewport, later ones are drawn over the earlier ones
+    viewports.forEach((viewport, i) => {
+        const [x, y, w, h] = viewport.rect
+        const rect = [
+            Math.round(x * canvas.width), Math.round(y * canvas.height),
+            Math.round(w * canvas.width), Math.round(h * canvas.height),
+        ]
+        gl.viewport(...rect)
+        gl.scissor(...rect)
+        gl.clear(gl.COLOR_BUFFER_BIT | gl.DEPTH_BUFFER_BIT)
+
+        for( const shader of shaders.values() ) {
+            gl.useProgram(shader.program)
+            gl.uniformMatrix4fv(
+                gl.getUniformLocation(shader.program, 'u_view_projection'),
+                false, viewport.viewProjection
+            )
+            for( const entityID of shader.entities ) {
+                const entity = entities.get(entityID)
+                if( !entity.visible || !(entity.layers & viewport.mask) ) { continue }
+                gl.bindVertexArray(entity.vao)
+                entity.updateUniforms(gl, shader.program)
+                if( entity.indexType ) {
+                    gl.drawElements(gl.TRIANGLES, entity.count, entity.indexType, 0)
+                } else {
+                    gl.drawArrays(gl.TRIANGLES, 0, entity.count)
+                }
+                gl.bindVertexArray(null)
+            }
+        }
 
-    for( const shader of shaders.values() ) {
-        gl.useProgram(shader.program)
-        for( const entityID of shader.entities ) {
-            const entity = entities.get(entityID)
-            gl.bindVertexArray(entity.vao)
-            entity.updateUniforms(gl, shader.program)
-            gl.drawArrays(gl.TRIANGLES, 0, entity.count)
-            gl.bindVertexArray(null)
+        // debug lines are drawn with the main camera
+        if( i == 0 && debugDraw ) {
+            debugLines.draw(gl, wasm.debugbuffer)
         }
-    }
+    })
 
-    t += 1
     requestAnimationFrame(render)
 }
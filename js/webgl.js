@@ -43,6 +43,8 @@ export let Buffer = function(data, drawType, size, type, normalize) {
     this.size = size
     this.type = type
     this.normalize = normalize
+    // where the first vertex starts in the webgl buffer
+    this.offset = 0
     this.buffer = null
 
     this.load = function(gl) {
@@ -58,6 +60,72 @@ export let Buffer = function(data, drawType, size, type, normalize) {
 }
 
 
+// A mesh in the binary format of `src/engine/mesh.rs`. The vertex streams
+// share one array buffer and the indices get an element array buffer, the
+// bytes are handed to webgl without being parsed.
+const MESH_VERSION = 1
+const MESH_HEADER_SIZE = 16
+
+const MESH_STREAMS = [
+    // name, flag, components, type, normalize, bytes per vertex
+    ['position', 0, 3, 'FLOAT', false, 12],
+    ['normal', 1 << 0, 3, 'FLOAT', false, 12],
+    ['uv', 1 << 1, 2, 'FLOAT', false, 8],
+    ['color', 1 << 2, 4, 'UNSIGNED_BYTE', true, 4],
+]
+const MESH_WIDE_INDICES = 1 << 3
+
+export let Mesh = function(bytes) {
+
+    const view = new DataView(bytes.buffer, bytes.byteOffset, bytes.byteLength)
+    const magic = String.fromCharCode(...bytes.subarray(0, 4))
+    const version = view.getUint16(4, true)
+    if( magic !== 'NMSH' || version !== MESH_VERSION ) {
+        throw new Error(`unsupported mesh ${magic} version ${version}`)
+    }
+    const flags = view.getUint16(6, true)
+
+    this.vertices = view.getUint32(8, true)
+    this.indices = view.getUint32(12, true)
+    this.wide = (flags & MESH_WIDE_INDICES) != 0
+    this.streams = new Map()
+    this.vertexBuffer = null
+    this.indexBuffer = null
+    this.indexType = null
+
+    let offset = MESH_HEADER_SIZE
+    const streams = []
+    for( const [name, flag, size, type, normalize, stride] of MESH_STREAMS ) {
+        if( (flags & flag) != flag ) { continue }
+        streams.push([name, size, type, normalize, offset - MESH_HEADER_SIZE])
+        offset += stride * this.vertices
+    }
+    const vertexData = bytes.subarray(MESH_HEADER_SIZE, offset)
+    const indexData = bytes.subarray(offset)
+
+    this.load = function(gl) {
+        this.vertexBuffer = gl.createBuffer()
+        gl.bindBuffer(gl.ARRAY_BUFFER, this.vertexBuffer)
+        gl.bufferData(gl.ARRAY_BUFFER, vertexData, gl.STATIC_DRAW)
+        gl.bindBuffer(gl.ARRAY_BUFFER, null)
+
+        // bound again by every entity's VAO
+        this.indexBuffer = gl.createBuffer()
+        gl.bindBuffer(gl.ELEMENT_ARRAY_BUFFER, this.indexBuffer)
+        gl.bufferData(gl.ELEMENT_ARRAY_BUFFER, indexData, gl.STATIC_DRAW)
+        gl.bindBuffer(gl.ELEMENT_ARRAY_BUFFER, null)
+        this.indexType = this.wide ? gl.UNSIGNED_INT : gl.UNSIGNED_SHORT
+
+        for( const [name, size, type, normalize, offset] of streams ) {
+            const buffer = new Buffer(null, gl.STATIC_DRAW, size, gl[type], normalize)
+            buffer.buffer = this.vertexBuffer
+            buffer.offset = offset
+            this.streams.set(name, buffer)
+        }
+    }
+}
+
+
 export let UniformBlock = function(name, variablesList) {
 
     this.name = name
@@ -98,28 +166,40 @@ export let UniformBlock = function(name, variablesList) {
         gl.bindBufferBase(gl.UNIFORM_BUFFER, this.uboIndex, this.buffer)
     }
 
-    // this.destroy = function(gl, program) {
-    //     gl.deleteBuffer(this.buffer)
-    // }
+    this.destroy = function(gl) {
+        gl.deleteBuffer(this.buffer)
+    }
 }
 
 
-export let Entity = function(shader, count, attributes, uniforms) {
+// `indices` names the mesh an entity is drawn from by index, entities
+// without one draw `count` vertices in order
+export let Entity = function(shader, count, attributes, uniforms, indices) {
 
     this.shader = shader
     this.count = count
+    this.indices = indices
+    this.indexType = null
     this.attributes = attributes
     this.uniforms = uniforms
     this.uniformBlocks = new Map()
     this.frameUniformUpdates = new Map()
+    this.visible = true
+    this.layers = 1
     this.vao = null
 
-    this.load = function(gl, shaders, buffers) {
+    this.load = function(gl, shaders, buffers, meshes) {
         const program = shaders.get(this.shader).program
 
         this.vao = gl.createVertexArray()
         gl.bindVertexArray(this.vao)
 
+        if( this.indices ) {
+            const mesh = meshes.get(this.indices)
+            gl.bindBuffer(gl.ELEMENT_ARRAY_BUFFER, mesh.indexBuffer)
+            this.indexType = mesh.indexType
+        }
+
         for( const attrib of Object.keys(this.attributes) ) {
             const buffer = buffers.get(this.attributes[attrib])
 
@@ -128,7 +208,8 @@ export let Entity = function(shader, count, attributes, uniforms) {
 
             gl.bindBuffer(gl.ARRAY_BUFFER, buffer.buffer)
             gl.vertexAttribPointer(
-                location, buffer.size, buffer.type, buffer.normalize, 0, 0,
+                location, buffer.size, buffer.type, buffer.normalize, 0,
+                buffer.offset,
             )
             gl.bindBuffer(gl.ARRAY_BUFFER, null)
         }
@@ -157,15 +238,65 @@ export let Entity = function(shader, count, attributes, uniforms) {
         }
     }
 
-    // this.destroy = function(gl, program) {
-    //     for( let [blockName, variables] of Object.entries(this.uniforms) ) {
-    //         this.uniformBlocks[blockName].destroy(gl, program)
-    //     }
-    //     for( let attrib of Object.keys(this.attributes) ) {
-    //         let location = gl.getAttribLocation(program, attrib)
-    //         gl.disableVertexAttribArray(location)
-    //     }
-    //     gl.deleteVertexArray(this.vao)
-    // }
+    this.destroy = function(gl) {
+        for( const uniformBlock of this.uniformBlocks.values() ) {
+            uniformBlock.destroy(gl)
+        }
+        this.uniformBlocks.clear()
+        this.frameUniformUpdates.clear()
+        gl.deleteVertexArray(this.vao)
+        this.vao = null
+    }
+
+}
+
+
+
+// Lines read straight out of the debug buffer, 6 floats per vertex
+export let DebugLines = function(shader) {
+
+    this.shader = shader
+    this.vao = null
+    this.buffer = null
+
+    this.load = function(gl) {
+        const program = this.shader.program
+
+        this.vao = gl.createVertexArray()
+        gl.bindVertexArray(this.vao)
+
+        this.buffer = gl.createBuffer()
+        gl.bindBuffer(gl.ARRAY_BUFFER, this.buffer)
+
+        const position = gl.getAttribLocation(program, 'a_position')
+        gl.enableVertexAttribArray(position)
+        gl.vertexAttribPointer(position, 3, gl.FLOAT, false, 24, 0)
+
+        const color = gl.getAttribLocation(program, 'a_color')
+        gl.enableVertexAttribArray(color)
+        gl.vertexAttribPointer(color, 3, gl.FLOAT, false, 24, 12)
+
+        gl.bindBuffer(gl.ARRAY_BUFFER, null)
+        gl.bindVertexArray(null)
+    }
+
+    this.draw = function(gl, debugbuffer) {
+        const length = debugbuffer[0]
+        if( length <= 17 ) { return }
+
+        gl.useProgram(this.shader.program)
+        gl.uniformMatrix4fv(
+            gl.getUniformLocation(this.shader.program, 'u_matrix'),
+            false, debugbuffer.subarray(1, 17)
+        )
 
+        gl.bindVertexArray(this.vao)
+        gl.bindBuffer(gl.ARRAY_BUFFER, this.buffer)
+        gl.bufferData(
+            gl.ARRAY_BUFFER, debugbuffer.subarray(17, length), gl.DYNAMIC_DRAW
+        )
+        gl.bindBuffer(gl.ARRAY_BUFFER, null)
+        gl.drawArrays(gl.LINES, 0, (length - 17) / 6)
+        gl.bindVertexArray(null)
+    }
 }
@@ -1,24 +1,263 @@
 
 pub mod camera;
-pub use camera::Camera;
+pub use camera::{ Camera, Projection, CameraController };
 
 pub mod arena;
 pub use arena::{ Arena, ArenaID };
 
+pub mod stack_vec;
+pub use stack_vec::StackVec;
+
 pub mod memory_buffer;
 pub use memory_buffer::MemoryBuffer;
 
+pub mod debug_buffer;
+pub use debug_buffer::DebugBuffer;
+
 pub mod entity;
 
+pub mod render;
+pub use render::{ Shader, Buffer, RenderDescriptor };
+
+pub mod mesh;
+pub use mesh::Mesh;
+
+pub mod ecs;
+
+pub mod scenegraph;
+pub use scenegraph::{ Scenegraph, NodeID };
+
+pub mod input;
+pub use input::{ Key, Input, InputMap, Action, Axis };
+
+pub mod viewport;
+pub use viewport::{ View, Views, Rect };
+
+pub mod profiler;
+pub use profiler::{ Profiler, Scope, Counter };
+
 pub mod frame;
-pub use frame::{Key, Frame};
+pub use frame::{ Frame };
+
+pub mod clock;
+pub use clock::Clock;
+
+pub mod timers;
+pub use timers::{ Timers, TimerID, Cooldown };
 
+pub mod events;
+pub use events::Events;
 
-use crate::game::{Game};
 
+#[cfg(feature = "std")]
+use crate::game::{ self, Game, Assets };
+#[cfg(feature = "std")]
+use crate::replay::{ Recording };
+#[cfg(feature = "std")]
+use crate::physics::Integrator;
 
+
+// canvas size until JS calls `resize`
+#[cfg(feature = "std")]
+const CANVAS_SIZE: f32 = 800.0;
+
+
+// Runs the game, it needs std for recordings and the buffers shared with JS
+#[cfg(feature = "std")]
 pub struct Engine {
-    pub camera: Camera,
+    // the main view and the game's extra cameras, like the minimap
+    pub views: Views,
+    pub clock: Clock,
     pub frame: Frame,
     pub game: Game,
+    // written by JS before every frame
+    pub input: Input,
+    // bytes passed to and from JS, like recordings
+    pub transfer: Vec<u8>,
+    assets: Assets,
+    seed: [u64; 2],
+    recording: Option<Recording>,
+    playback: Option<Playback>,
+}
+
+
+// A recording being played, with the player's own bindings and integrator
+// which are put back once it ends
+#[cfg(feature = "std")]
+struct Playback {
+    recording: Recording,
+    next: usize,
+    bindings: InputMap,
+    integrator: Integrator,
+}
+
+
+#[cfg(feature = "std")]
+impl Engine {
+
+    pub fn new(assets: &Assets) -> Self {
+        Self::with_seed(assets, game::SEED)
+    }
+
+    pub fn with_seed(assets: &Assets, seed: [u64; 2]) -> Self {
+        Self {
+            views: game::views(CANVAS_SIZE, CANVAS_SIZE),
+            clock: Clock::new(),
+            frame: Frame::new(),
+            game: Game::new(assets, seed),
+            input: Input::default(),
+            transfer: Vec::new(),
+            assets: *assets,
+            seed,
+            recording: None,
+            playback: None,
+        }
+    }
+
+    // the canvas is now `width` by `height` pixels
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.views.resize(width, height);
+    }
+
+    pub fn seed(&self) -> [u64; 2] {
+        self.seed
+    }
+
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    // throws away the running game and starts a new one from `seed`, with
+    // the same integrator
+    pub fn restart(&mut self, seed: [u64; 2]) {
+        let integrator = self.game.integrator();
+        self.game.world.clear();
+        self.game = Game::new(&self.assets, seed);
+        self.game.set_integrator(integrator);
+        let (width, height) = self.views.size();
+        self.views = game::views(width, height);
+        self.clock.reset();
+        self.seed = seed;
+    }
+
+
+    // swaps in a game that was built elsewhere, like a loaded snapshot.
+    // Recordings stop here, they can't be replayed from the new state.
+    pub fn replace_game(&mut self, game: Game, seed: [u64; 2]) {
+        self.game.world.clear();
+        self.game = game;
+        self.seed = seed;
+        self.recording = None;
+        self.stop_playback();
+    }
+
+
+    // restarts the game and records every frame from here on
+    pub fn start_recording(&mut self) {
+        self.restart(self.seed);
+        self.recording = Some(Recording::new(self.seed, self.game.integrator()));
+    }
+
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+
+    // restarts the game from the recording's seed and integrator, the next
+    // frames take their time and input from the recording instead of the
+    // clock and JS
+    pub fn play(&mut self, recording: Recording) {
+        self.recording = None;
+        self.stop_playback();
+        let integrator = self.game.integrator();
+        self.restart(recording.seed);
+        self.game.set_integrator(recording.integrator);
+        self.playback = Some(Playback {
+            recording, next: 0, bindings: self.frame.bindings, integrator
+        });
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    fn stop_playback(&mut self) {
+        if let Some(playback) = self.playback.take() {
+            self.frame.bindings = playback.bindings;
+            self.game.set_integrator(playback.integrator);
+        }
+    }
+
+    // the bindings the player changes, while a recording plays they only
+    // take over once it ends
+    pub fn bindings(&self) -> &InputMap {
+        match &self.playback {
+            Some(playback) => &playback.bindings,
+            None => &self.frame.bindings,
+        }
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut InputMap {
+        match &mut self.playback {
+            Some(playback) => &mut playback.bindings,
+            None => &mut self.frame.bindings,
+        }
+    }
+
+    // switches the game's integrator, like the bindings it is held back
+    // while a recording plays
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        match &mut self.playback {
+            Some(playback) => playback.integrator = integrator,
+            None => self.game.set_integrator(integrator),
+        }
+    }
+
+
+    // runs one frame, `dt` is the real time in seconds since the last one.
+    // After this both buffers are ready to be read by JS.
+    pub fn render(&mut self, dt: f32) {
+        let mut input = self.input;
+
+        let recorded = self.playback.as_mut().and_then(|playback| {
+            playback.next += 1;
+            playback.recording.frame(playback.next - 1).copied()
+        });
+        match recorded {
+            Some(frame) => {
+                self.clock.set(frame.t, frame.dt);
+                input = frame.input;
+                self.frame.bindings = frame.bindings;
+                if frame.integrator != self.game.integrator() {
+                    self.game.set_integrator(frame.integrator);
+                }
+            },
+            None => {
+                self.stop_playback();
+                self.clock.tick(dt);
+            },
+        }
+
+        let (t, dt) = (self.clock.time(), self.clock.dt());
+        if let Some(recording) = &mut self.recording {
+            recording.record(t, dt, &input, &self.frame.bindings, self.game.integrator());
+        }
+
+        let span = self.frame.profiler.begin(Scope::Frame);
+        let matrix = self.views.main().camera.view_projection_matrix();
+        self.frame.update(t, dt, &input, matrix);
+        self.frame.reserve_views(&self.views);
+        self.game.render_frame(&mut self.frame, &mut self.views);
+        self.frame.add_views(&self.views);
+        self.frame.buffer.update_length();
+        self.frame.debug.update_length();
+
+        let (buffer, profiler) = (&self.frame.buffer, &mut self.frame.profiler);
+        profiler.set(Counter::BufferWords, buffer.words().len());
+        profiler.set(Counter::BufferCapacity, buffer.capacity());
+        profiler.set(Counter::Commands, buffer.commands());
+        profiler.set(Counter::DroppedCommands, buffer.dropped());
+        profiler.end(span);
+        profiler.finish_frame(self.clock.frame());
+    }
+
 }
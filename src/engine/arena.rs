@@ -2,96 +2,412 @@ use core::ops::{ Index, IndexMut };
 
 
 
-#[derive(Default, Clone, Copy)]
-pub struct ArenaID(usize);
-
-impl From<usize> for ArenaID {
-    fn from(value: usize) -> Self { Self(value) }
+// Index of a slot plus the generation of the slot when the item was added.
+// Removing an item bumps the generation, so old IDs stop resolving.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ArenaID {
+    index: usize,
+    generation: u32,
 }
 
-impl From<ArenaID> for usize {
-    fn from(value: ArenaID) -> usize { value.0 }
+impl ArenaID {
+    pub fn index(&self) -> usize {
+        self.index
+    }
 }
 
-impl From<ArenaID> for f32 {
-    fn from(value: ArenaID) -> f32 { value.0 as f32 }
+
+
+enum Slot<T> {
+    Occupied { generation: u32, item: T },
+    Free { generation: u32, next_free: Option<usize> },
 }
 
 
 
+// Fixed capacity, stack allocated generational arena. Removed slots go on a
+// free list and are reused by later adds.
 pub struct Arena<T, const N: usize> {
     current: usize,
-    arena: [T; N],
+    len: usize,
+    free: Option<usize>,
+    slots: [Slot<T>; N],
 }
 
 
 impl<T, const N: usize> Index<ArenaID> for Arena<T, N> {
     type Output = T;
     fn index(&self, id: ArenaID) -> &Self::Output {
-        assert!(id.0 < self.current);
-        unsafe { self.arena.get_unchecked(id.0) }
+        self.get(id).expect("stale or invalid ArenaID")
     }
 }
 
 
 impl<T, const N: usize> IndexMut<ArenaID> for Arena<T, N> {
     fn index_mut(&mut self, id: ArenaID) -> &mut Self::Output {
-        assert!(id.0 < self.current);
-        unsafe { self.arena.get_unchecked_mut(id.0) }
+        self.get_mut(id).expect("stale or invalid ArenaID")
     }
-
 }
 
 
-impl<T, const N: usize> Arena<T, N> where T: Default {
+impl<T, const N: usize> Arena<T, N> {
 
     pub fn empty() -> Self {
         Self {
             current: 0,
-            arena: core::array::from_fn(|_| Default::default()),
+            len: 0,
+            free: None,
+            slots: core::array::from_fn(
+                |_| Slot::Free { generation: 0, next_free: None }
+            ),
         }
     }
 
+    // removes every item, IDs handed out before stay stale
     pub fn reset(&mut self) {
-        self.current = 0;
+        for index in 0..self.current {
+            if let Slot::Occupied { generation, .. } = self.slots[index] {
+                self.slots[index] = Slot::Free {
+                    generation: generation.wrapping_add(1), next_free: None
+                };
+            }
+        }
+        self.free = None;
+        for index in (0..self.current).rev() {
+            if let Slot::Free { next_free, .. } = &mut self.slots[index] {
+                *next_free = self.free;
+                self.free = Some(index);
+            }
+        }
+        self.len = 0;
     }
 
     pub fn add(&mut self, item: T) -> ArenaID {
-        assert!(self.current < N);
-        unsafe { *self.arena.get_unchecked_mut(self.current) = item }
-        self.current += 1;
-        ArenaID::from(self.current - 1)
+        self.try_add(item).ok().expect("arena is full")
     }
 
-    pub fn len(&self) -> usize {
+    // gives the item back if there is no room left
+    pub fn try_add(&mut self, item: T) -> Result<ArenaID, T> {
+        let index = match self.free {
+            Some(index) => index,
+            None if self.current < N => {
+                self.current += 1;
+                self.current - 1
+            },
+            None => return Err(item),
+        };
+
+        let generation = match self.slots[index] {
+            Slot::Free { generation, next_free } => {
+                self.free = next_free;
+                generation
+            },
+            Slot::Occupied { .. } => unreachable!(),
+        };
+
+        self.slots[index] = Slot::Occupied { generation, item };
+        self.len += 1;
+        Ok(ArenaID { index, generation })
+    }
+
+    pub fn remove(&mut self, id: ArenaID) -> Option<T> {
+        if !self.contains(id) { return None; }
+
+        let slot = core::mem::replace(
+            &mut self.slots[id.index],
+            Slot::Free {
+                generation: id.generation.wrapping_add(1),
+                next_free: self.free,
+            },
+        );
+        self.free = Some(id.index);
+        self.len -= 1;
+
+        match slot {
+            Slot::Occupied { item, .. } => Some(item),
+            Slot::Free { .. } => unreachable!(),
+        }
+    }
+
+    // puts the item in a specific free slot, used to rebuild a saved arena
+    // with the same layout. Gives the item back if the slot is taken.
+    pub fn insert_at(&mut self, index: usize, item: T) -> Result<ArenaID, T> {
+        if index >= N { return Err(item); }
+        self.grow_to(index + 1);
+
+        let Slot::Free { generation, next_free } = self.slots[index] else {
+            return Err(item);
+        };
+        if self.free == Some(index) {
+            self.free = next_free;
+        } else {
+            let previous = self.free_slots().find(|i| match self.slots[*i] {
+                Slot::Free { next_free, .. } => next_free == Some(index),
+                Slot::Occupied { .. } => false,
+            });
+            if let Some(Slot::Free { next_free: link, .. }) =
+                previous.map(|i| &mut self.slots[i]) {
+                *link = next_free;
+            }
+        }
+
+        self.slots[index] = Slot::Occupied { generation, item };
+        self.len += 1;
+        Ok(ArenaID { index, generation })
+    }
+
+    // the free slots in the order later adds will use them, slots past
+    // `used_slots` have never been handed out and aren't included
+    pub fn free_slots(&self) -> impl Iterator<Item = usize> + '_ {
+        core::iter::successors(self.free, |index| match self.slots[*index] {
+            Slot::Free { next_free, .. } => next_free,
+            Slot::Occupied { .. } => unreachable!(),
+        })
+    }
+
+    pub fn used_slots(&self) -> usize {
         self.current
     }
 
-    pub fn as_ptr(&mut self) -> *const T {
-        self.arena.as_ptr()
+    // relinks the free slots so they are reused in `order`, which has to
+    // hold every free slot below `used` exactly once
+    pub fn set_free_slots(&mut self, used: usize, order: &[usize]) -> bool {
+        if used > N || used < self.current { return false; }
+        self.grow_to(used);
+        if order.len() != self.current - self.len { return false; }
+        for (i, index) in order.iter().enumerate() {
+            let free = matches!(self.slots.get(*index), Some(Slot::Free { .. }));
+            if !free || *index >= self.current || order[..i].contains(index) {
+                return false;
+            }
+        }
+
+        self.free = None;
+        for index in order.iter().rev() {
+            if let Slot::Free { next_free, .. } = &mut self.slots[*index] {
+                *next_free = self.free;
+                self.free = Some(*index);
+            }
+        }
+        true
     }
 
-    pub fn slice(&self) -> &[T] {
-        &self.arena[0..self.current]
+    // hands out every slot below `used`, the new ones go on the free list
+    fn grow_to(&mut self, used: usize) {
+        while self.current < used {
+            if let Slot::Free { next_free, .. } = &mut self.slots[self.current] {
+                *next_free = self.free;
+            }
+            self.free = Some(self.current);
+            self.current += 1;
+        }
     }
 
-    pub fn slice_mut(&mut self) -> &mut [T] {
-        &mut self.arena[0..self.current]
+    pub fn contains(&self, id: ArenaID) -> bool {
+        self.get(id).is_some()
     }
 
-    // pub fn iter(&self) -> iter::Take<slice::Iter<'_, T>> {
-    //     self.arena.iter().take(self.current)
-    // }
+    pub fn get(&self, id: ArenaID) -> Option<&T> {
+        match self.slots.get(id.index) {
+            Some(Slot::Occupied { generation, item })
+                if *generation == id.generation => Some(item),
+            _ => None,
+        }
+    }
 
-    // pub fn iter_mut(&mut self) -> iter::Take<slice::IterMut<'_, T>> {
-    //     self.arena.iter_mut().take(self.current)
-    // }
+    pub fn get_mut(&mut self, id: ArenaID) -> Option<&mut T> {
+        match self.slots.get_mut(id.index) {
+            Some(Slot::Occupied { generation, item })
+                if *generation == id.generation => Some(item),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.iter_with_ids().map(|(_, item)| item)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut_with_ids().map(|(_, item)| item)
+    }
+
+    pub fn iter_with_ids(&self) -> impl Iterator<Item = (ArenaID, &T)> {
+        self.slots[0..self.current].iter().enumerate().filter_map(
+            |(index, slot)| match slot {
+                Slot::Occupied { generation, item } => Some(
+                    (ArenaID { index, generation: *generation }, item)
+                ),
+                Slot::Free { .. } => None,
+            }
+        )
+    }
+
+    pub fn iter_mut_with_ids(
+        &mut self
+    ) -> impl Iterator<Item = (ArenaID, &mut T)> {
+        self.slots[0..self.current].iter_mut().enumerate().filter_map(
+            |(index, slot)| match slot {
+                Slot::Occupied { generation, item } => Some(
+                    (ArenaID { index, generation: *generation }, item)
+                ),
+                Slot::Free { .. } => None,
+            }
+        )
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = ArenaID> + '_ {
+        self.iter_with_ids().map(|(id, _)| id)
+    }
+
+    // calls `func` once for every unordered pair of items
+    pub fn for_each_pair_mut<F>(&mut self, mut func: F)
+    where F: FnMut(&mut T, &mut T) {
+        let slots = &mut self.slots[0..self.current];
+        for i in 0..slots.len() {
+            let (left, right) = slots.split_at_mut(i + 1);
+            let Slot::Occupied { item: a, .. } = &mut left[i] else { continue };
+            for slot in right.iter_mut() {
+                if let Slot::Occupied { item: b, .. } = slot {
+                    func(a, b);
+                }
+            }
+        }
+    }
 
 }
 
 
-impl<T, const N: usize> From<[T; N]> for Arena<T, N> {
-    fn from(arena: [T; N]) -> Self {
-        Self { arena, current: N }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_get_remove() {
+        let mut arena: Arena<u32, 4> = Arena::empty();
+        let a = arena.add(1);
+        let b = arena.add(2);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena[a], 1);
+        assert_eq!(arena.get(b), Some(&2));
+
+        assert_eq!(arena.remove(a), Some(1));
+        assert_eq!(arena.remove(a), None);
+        assert_eq!(arena.get(a), None);
+        assert!(!arena.contains(a));
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn stale_id_after_slot_reuse() {
+        let mut arena: Arena<u32, 4> = Arena::empty();
+        let a = arena.add(1);
+        arena.remove(a);
+        let c = arena.add(3);
+
+        assert_eq!(c.index(), a.index());
+        assert_ne!(c, a);
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.get_mut(a), None);
+        assert_eq!(arena[c], 3);
+    }
+
+    #[test]
+    fn rebuilt_layout_hands_out_the_same_slots() {
+        let mut arena: Arena<u32, 8> = Arena::empty();
+        let ids: Vec<_> = (0..6).map(|i| arena.add(i)).collect();
+        arena.remove(ids[1]);
+        arena.remove(ids[4]);
+        arena.remove(ids[5]);
+        arena.remove(ids[0]);
+        let free: Vec<_> = arena.free_slots().collect();
+        assert_eq!(free, [0, 5, 4, 1]);
+
+        let mut rebuilt: Arena<u32, 8> = Arena::empty();
+        for (id, item) in arena.iter_with_ids() {
+            rebuilt.insert_at(id.index(), *item).unwrap();
+        }
+        assert!(rebuilt.insert_at(2, 9).is_err());
+        assert!(!rebuilt.set_free_slots(6, &[0, 5, 4]));
+        assert!(!rebuilt.set_free_slots(6, &[0, 5, 5, 1]));
+        assert!(rebuilt.set_free_slots(arena.used_slots(), &free));
+
+        for i in 10..15 {
+            assert_eq!(rebuilt.add(i).index(), arena.add(i).index());
+        }
+        assert_eq!(rebuilt.iter().collect::<Vec<_>>(), arena.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_with_stale_id_panics() {
+        let mut arena: Arena<u32, 4> = Arena::empty();
+        let a = arena.add(1);
+        arena.remove(a);
+        let _ = arena[a];
+    }
+
+    #[test]
+    fn full_arena_reuses_free_slots() {
+        let mut arena: Arena<u32, 2> = Arena::empty();
+        let a = arena.add(1);
+        arena.add(2);
+        assert!(arena.is_full());
+        assert_eq!(arena.try_add(3), Err(3));
+
+        arena.remove(a);
+        assert!(arena.try_add(3).is_ok());
+        assert!(arena.is_full());
+    }
+
+    #[test]
+    fn iteration_skips_empty_slots() {
+        let mut arena: Arena<u32, 8> = Arena::empty();
+        let ids: Vec<ArenaID> = (0..5).map(|i| arena.add(i)).collect();
+        arena.remove(ids[1]);
+        arena.remove(ids[3]);
+
+        assert_eq!(arena.iter().copied().collect::<Vec<_>>(), vec![0, 2, 4]);
+        for item in arena.iter_mut() {
+            *item *= 10;
+        }
+        assert_eq!(
+            arena.ids().collect::<Vec<_>>(), vec![ids[0], ids[2], ids[4]]
+        );
+        assert_eq!(arena[ids[4]], 40);
+
+        let mut pairs = 0;
+        arena.for_each_pair_mut(|a, b| {
+            assert!(*a < *b);
+            pairs += 1;
+        });
+        assert_eq!(pairs, 3);
+    }
+
+    #[test]
+    fn reset_invalidates_ids() {
+        let mut arena: Arena<u32, 4> = Arena::empty();
+        let a = arena.add(1);
+        let b = arena.add(2);
+        arena.reset();
+        assert!(arena.is_empty());
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.get(b), None);
+
+        let c = arena.add(3);
+        assert_eq!(c.index(), 0);
+        assert_eq!(arena.iter().count(), 1);
     }
 }
@@ -1,11 +1,28 @@
-
 use crate::math::{ Vec3, Matrix4 };
 
-// #[derive(Clone, Copy)]
+pub mod controller;
+pub use controller::{ CameraController, Shake };
+
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Projection {
+    // vertical field of view in degrees
+    Perspective { fov: f32 },
+    // height of the view in world units
+    Orthographic { height: f32 },
+}
+
+
+#[derive(Clone, Copy)]
 pub struct Camera {
     pub position: Vec3,
     pub up: Vec3,
     matrix: Matrix4,
+    projection: Projection,
+    aspect: f32,
+    near: f32,
+    far: f32,
     projection_matrix: Matrix4,
 }
 
@@ -15,14 +32,29 @@ impl Camera {
     pub fn perspective(
         position: Vec3, fov: f32, aspect: f32, near: f32, far: f32
     ) -> Self {
+        Self::new(position, Projection::Perspective { fov }, aspect, near, far)
+    }
+
+    pub fn orthographic(
+        position: Vec3, height: f32, aspect: f32, near: f32, far: f32
+    ) -> Self {
+        Self::new(position, Projection::Orthographic { height }, aspect, near, far)
+    }
+
+    fn new(
+        position: Vec3, projection: Projection, aspect: f32, near: f32, far: f32
+    ) -> Self {
 
         let up = Vec3::new(0.0, 1.0, 0.0);
-        Self {
-            position: position,
-            up: up,
+        let mut camera = Self {
+            position,
+            up,
             matrix: Matrix4::look_at(position, Vec3::zero(), up),
-            projection_matrix: Matrix4::perspective(fov, aspect, near, far),
-        }
+            projection, aspect, near, far,
+            projection_matrix: Matrix4::identity(),
+        };
+        camera.set_projection(projection);
+        camera
 
     }
 
@@ -30,6 +62,42 @@ impl Camera {
         self.matrix = Matrix4::look_at(self.position, target, self.up);
     }
 
+    // where the camera is looking, set by `look_at`
+    pub fn view_matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    pub fn set_view_matrix(&mut self, matrix: Matrix4) {
+        self.matrix = matrix;
+    }
+
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    // width over height of the viewport the camera draws into
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+        self.set_projection(self.projection);
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.projection_matrix = match projection {
+            Projection::Perspective { fov } => {
+                Matrix4::perspective(fov, self.aspect, self.near, self.far)
+            },
+            Projection::Orthographic { height } => {
+                let (w, h) = (height * self.aspect / 2.0, height / 2.0);
+                Matrix4::orthographic(-w, w, -h, h, self.near, self.far)
+            },
+        };
+    }
+
     pub fn view_projection_matrix(&self) -> Matrix4 {
         self.projection_matrix * self.matrix.inverse()
     }
@@ -0,0 +1,289 @@
+use crate::math::Vec3;
+use crate::engine::camera::{ Camera, Projection };
+#[cfg(feature = "std")]
+use crate::binary::{ Writer, Reader, Truncated };
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+
+
+// Moves `value` towards `target` like a critically damped spring, as fast as
+// it can without overshooting. `smooth_time` is roughly how long it takes to
+// get there, `velocity` carries over between calls.
+pub fn smooth_damp(
+    value: f32, target: f32, velocity: &mut f32, smooth_time: f32, dt: f32
+) -> f32 {
+    let omega = 2.0 / smooth_time.max(0.0001);
+    let x = omega * dt;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = value - target;
+    let temp = (*velocity + omega * change) * dt;
+    *velocity = (*velocity - omega * temp) * decay;
+    target + (change + temp) * decay
+}
+
+fn smooth_damp3(
+    value: Vec3, target: Vec3, velocity: &mut Vec3, smooth_time: f32, dt: f32
+) -> Vec3 {
+    Vec3::new(
+        smooth_damp(value.x, target.x, &mut velocity.x, smooth_time, dt),
+        smooth_damp(value.y, target.y, &mut velocity.y, smooth_time, dt),
+        smooth_damp(value.z, target.z, &mut velocity.z, smooth_time, dt),
+    )
+}
+
+
+// --------------------------------------------------------
+
+
+// Trauma based screen shake. Hits add trauma between 0 and 1 which wears off
+// over time, the camera moves by up to `max_offset` scaled by trauma squared
+// so small knocks barely register.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Shake {
+    pub max_offset: f32,
+    // trauma lost per second
+    pub decay: f32,
+    trauma: f32,
+    time: f32,
+}
+
+
+impl Shake {
+
+    pub fn new(max_offset: f32, decay: f32) -> Self {
+        Self { max_offset, decay, trauma: 0.0, time: 0.0 }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.trauma = (self.trauma - self.decay * dt).max(0.0);
+        self.time += dt;
+    }
+
+    // two sines at unrelated frequencies per axis, smooth and never more
+    // than `max_offset` either way
+    pub fn offset(&self) -> Vec3 {
+        let amount = self.max_offset * self.trauma * self.trauma;
+        let t = self.time;
+        let noise = |a: f32, b: f32| 0.5 * ((t * a).sin() + (t * b + 1.7).sin());
+        Vec3::new(amount * noise(37.0, 23.0), 0.0, amount * noise(31.0, 19.0))
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+// Keeps a top down camera over a moving target. The camera trails the
+// target smoothly, looks ahead in the direction it is moving, pulls back as
+// it speeds up and shakes on hits.
+pub struct CameraController {
+    // distance above the target while it stands still
+    pub height: f32,
+    pub fov: f32,
+    pub smooth_time: f32,
+    // seconds of the target's velocity to look ahead by, up to a distance
+    pub look_ahead: f32,
+    pub max_look_ahead: f32,
+    // extra height per unit of the target's speed, up to a distance
+    pub speed_zoom: f32,
+    pub max_speed_zoom: f32,
+    // multiplies the height, changed with `zoom_by`
+    pub zoom: f32,
+    pub shake: Shake,
+    focus: Vec3,
+    focus_velocity: Vec3,
+    distance: f32,
+    distance_velocity: f32,
+}
+
+
+impl CameraController {
+
+    const MIN_ZOOM: f32 = 0.5;
+    const MAX_ZOOM: f32 = 2.5;
+    // zoom doubles in about 0.7 seconds at full input
+    const ZOOM_RATE: f32 = 1.0;
+
+    pub fn new() -> Self {
+        let height = 300.0;
+        Self {
+            height,
+            fov: 25.0,
+            smooth_time: 0.3,
+            look_ahead: 0.35,
+            max_look_ahead: 80.0,
+            speed_zoom: 0.25,
+            max_speed_zoom: 150.0,
+            zoom: 1.0,
+            shake: Shake::new(12.0, 1.2),
+            focus: Vec3::zero(),
+            focus_velocity: Vec3::zero(),
+            distance: height,
+            distance_velocity: 0.0,
+        }
+    }
+
+    // a camera where the controller starts out
+    pub fn camera(&self, aspect: f32) -> Camera {
+        let position = self.focus + Vec3::new(0.0, self.distance, 0.1);
+        Camera::perspective(position, self.fov, aspect, 1.0, 4000.0)
+    }
+
+    // point the camera is looking at, before shaking
+    pub fn focus(&self) -> Vec3 {
+        self.focus
+    }
+
+    // `amount` from -1 to 1, positive zooms out
+    pub fn zoom_by(&mut self, amount: f32, dt: f32) {
+        self.zoom = (self.zoom * (amount * Self::ZOOM_RATE * dt).exp())
+            .clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+    }
+
+    // the orthographic view covers what the perspective one sees at the
+    // target, so switching doesn't jump
+    pub fn toggle_projection(&self, camera: &mut Camera) {
+        camera.set_projection(match camera.projection() {
+            Projection::Perspective { .. } => {
+                Projection::Orthographic { height: self.view_height() }
+            },
+            Projection::Orthographic { .. } => Projection::Perspective { fov: self.fov },
+        });
+    }
+
+    fn view_height(&self) -> f32 {
+        2.0 * self.distance * (self.fov.to_radians() / 2.0).tan()
+    }
+
+    pub fn update(&mut self, camera: &mut Camera, target: Vec3, velocity: Vec3, dt: f32) {
+        let mut ahead = velocity * self.look_ahead;
+        if ahead.len() > self.max_look_ahead {
+            ahead = ahead.unit() * self.max_look_ahead;
+        }
+        self.focus = smooth_damp3(
+            self.focus, target + ahead, &mut self.focus_velocity, self.smooth_time, dt
+        );
+
+        let pull_back = (velocity.len() * self.speed_zoom).min(self.max_speed_zoom);
+        self.distance = smooth_damp(
+            self.distance, (self.height + pull_back) * self.zoom,
+            &mut self.distance_velocity, self.smooth_time, dt
+        );
+
+        self.shake.update(dt);
+        let focus = self.focus + self.shake.offset();
+        camera.position = focus + Vec3::new(0.0, self.distance, 0.1);
+        camera.look_at(focus);
+
+        if let Projection::Orthographic { .. } = camera.projection() {
+            camera.set_projection(Projection::Orthographic { height: self.view_height() });
+        }
+    }
+
+    // only what changes while playing, the settings are code
+    #[cfg(feature = "std")]
+    pub fn save(&self, out: &mut Writer) {
+        out.vec3(self.focus);
+        out.vec3(self.focus_velocity);
+        out.f32(self.distance);
+        out.f32(self.distance_velocity);
+        out.f32(self.zoom);
+        out.f32(self.shake.trauma);
+        out.f32(self.shake.time);
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load(&mut self, input: &mut Reader) -> Result<(), Truncated> {
+        self.focus = input.vec3()?;
+        self.focus_velocity = input.vec3()?;
+        self.distance = input.f32()?;
+        self.distance_velocity = input.f32()?;
+        self.zoom = input.f32()?;
+        self.shake.trauma = input.f32()?;
+        self.shake.time = input.f32()?;
+        Ok(())
+    }
+
+}
+
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_converges_without_overshooting() {
+        let mut controller = CameraController::new();
+        let mut camera = controller.camera(1.0);
+        let target = Vec3::new(100.0, 0.0, -50.0);
+
+        let mut last = (controller.focus() - target).len();
+        for _ in 0..180 {
+            controller.update(&mut camera, target, Vec3::zero(), 1.0 / 60.0);
+            let distance = (controller.focus() - target).len();
+            assert!(distance <= last);
+            last = distance;
+        }
+        assert!(last < 0.01);
+        assert!((camera.position - Vec3::new(100.0, 300.0, -49.9)).len() < 0.01);
+
+        // moving targets are led, but never by more than the limit
+        for _ in 0..600 {
+            controller.update(&mut camera, target, Vec3::new(1000.0, 0.0, 0.0), 1.0 / 60.0);
+        }
+        let lead = controller.focus() - target;
+        assert!((lead.x - controller.max_look_ahead).abs() < 0.01);
+    }
+
+    #[test]
+    fn shake_stays_in_bounds_and_settles() {
+        let mut shake = Shake::new(12.0, 1.2);
+        shake.add_trauma(0.5);
+        shake.add_trauma(0.8);
+        assert_eq!(shake.trauma(), 1.0);
+
+        let mut largest: f32 = 0.0;
+        for i in 0..600 {
+            shake.update(if i % 3 == 0 { 0.02 } else { 0.013 });
+            let offset = shake.offset();
+            assert!(offset.x.abs() <= 12.0 && offset.z.abs() <= 12.0);
+            assert_eq!(offset.y, 0.0);
+            largest = largest.max(offset.x.abs());
+        }
+        assert!(largest > 1.0);
+        assert_eq!(shake.trauma(), 0.0);
+        assert_eq!(shake.offset(), Vec3::zero());
+    }
+
+    #[test]
+    fn projection_toggle_keeps_the_view() {
+        let mut controller = CameraController::new();
+        let mut camera = controller.camera(1.0);
+        controller.toggle_projection(&mut camera);
+        let Projection::Orthographic { height } = camera.projection() else { panic!() };
+        assert!((height - 2.0 * 300.0 * (12.5f32).to_radians().tan()).abs() < 0.01);
+
+        controller.zoom_by(1.0, 100.0);
+        assert_eq!(controller.zoom, CameraController::MAX_ZOOM);
+        controller.toggle_projection(&mut camera);
+        assert_eq!(camera.projection(), Projection::Perspective { fov: 25.0 });
+    }
+}
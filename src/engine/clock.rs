@@ -0,0 +1,134 @@
+// Game time in seconds. JS passes the real time between frames, the clock
+// scales it and holds it back while paused, so gameplay only ever sees game
+// time and runs the same at any refresh rate.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Clock {
+    time: f32,
+    dt: f32,
+    frame: u64,
+    scale: f32,
+    paused: bool,
+    step: bool,
+}
+
+
+impl Clock {
+
+    pub fn new() -> Self {
+        Self { time: 0.0, dt: 0.0, frame: 0, scale: 1.0, paused: false, step: false }
+    }
+
+    // back to time 0, pause and scale are kept
+    pub fn reset(&mut self) {
+        *self = Self { paused: self.paused, scale: self.scale, ..Self::new() };
+    }
+
+    // advances by the real time since the last frame and returns how much
+    // game time passed, which is 0 while paused
+    pub fn tick(&mut self, real_dt: f32) -> f32 {
+        let step = core::mem::take(&mut self.step);
+        self.dt = if !self.paused || step { real_dt.max(0.0) * self.scale } else { 0.0 };
+        self.time += self.dt;
+        if self.dt > 0.0 { self.frame += 1; }
+        self.dt
+    }
+
+    // jumps straight to a time that was recorded earlier
+    pub fn set(&mut self, time: f32, dt: f32) {
+        self.time = time;
+        self.dt = dt;
+        if dt > 0.0 { self.frame += 1; }
+    }
+
+    // puts the clock back to a saved time, pause and scale are kept
+    pub fn restore(&mut self, time: f32, frame: u64) {
+        self.time = time;
+        self.frame = frame;
+        self.dt = 0.0;
+    }
+
+    // elapsed game time
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // game time the last tick advanced by
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    // number of frames the game has advanced, paused frames don't count
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // the next tick runs while paused, then the clock stops again
+    pub fn step(&mut self) {
+        if self.paused { self.step = true; }
+    }
+
+    // 0.5 is half speed, negative scales are clamped to 0
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale.max(0.0);
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+}
+
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_scale_and_step() {
+        let mut clock = Clock::new();
+        assert_eq!(clock.tick(0.5), 0.5);
+        clock.set_scale(0.5);
+        assert_eq!(clock.tick(0.5), 0.25);
+        assert_eq!((clock.time(), clock.frame()), (0.75, 2));
+
+        clock.pause();
+        clock.tick(0.5);
+        assert_eq!((clock.time(), clock.frame(), clock.dt()), (0.75, 2, 0.0));
+
+        clock.step();
+        assert_eq!(clock.tick(0.5), 0.25);
+        assert_eq!(clock.tick(0.5), 0.0);
+        assert_eq!((clock.time(), clock.frame()), (1.0, 3));
+
+        // stepping only means something while paused
+        clock.resume();
+        clock.step();
+        clock.tick(1.0);
+        clock.pause();
+        assert_eq!(clock.tick(1.0), 0.0);
+
+        clock.reset();
+        assert_eq!((clock.time(), clock.frame(), clock.scale()), (0.0, 0, 0.5));
+        assert!(clock.is_paused());
+    }
+}
@@ -0,0 +1,132 @@
+use crate::math::{ Vec3, Matrix4 };
+use crate::engine::StackVec;
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
+
+
+// header of 1 length + 16 view projection floats, then room for 1000 lines
+const DEBUG_BUFFER_SIZE: usize = 1 + 16 + (1000 * 12);
+
+
+#[allow(dead_code)]
+#[link_section = "DEBUG_BUFFER_SIZE"]
+pub static DSIZE: u32 = DEBUG_BUFFER_SIZE as u32;
+
+
+pub type Color = [f32; 3];
+
+
+// Line segments drawn on top of the scene for debugging. Each line is two
+// vertices of x, y, z, r, g, b, lines that don't fit are dropped.
+pub struct DebugBuffer {
+    pub enabled: bool,
+    buffer: StackVec<f32, DEBUG_BUFFER_SIZE>,
+}
+
+
+impl DebugBuffer {
+
+    pub fn empty() -> Self {
+        Self { enabled: false, buffer: StackVec::empty() }
+    }
+
+    pub fn as_ptr(&mut self) -> *const f32 {
+        self.buffer.as_ptr()
+    }
+
+    pub fn buffer_reset(&mut self, view_projection: &Matrix4) {
+        self.buffer.reset();
+        self.buffer.add(0.0);
+        for row in view_projection.matrix.iter() {
+            for value in row.iter() {
+                self.buffer.add(*value);
+            }
+        }
+    }
+
+    pub fn update_length(&mut self) {
+        self.buffer[0] = self.buffer.len() as f32;
+    }
+
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: Color) {
+        if !self.enabled { return; }
+        if self.buffer.len() + 12 > DEBUG_BUFFER_SIZE { return; }
+        for v in [a, b] {
+            self.buffer.add(v.x);
+            self.buffer.add(v.y);
+            self.buffer.add(v.z);
+            self.buffer.add(color[0]);
+            self.buffer.add(color[1]);
+            self.buffer.add(color[2]);
+        }
+    }
+
+    pub fn vector(&mut self, origin: Vec3, vector: Vec3, color: Color) {
+        self.line(origin, origin + vector, color);
+    }
+
+    pub fn point(&mut self, point: Vec3, size: f32, color: Color) {
+        self.vector(point - Vec3::new(size, 0.0, 0.0), Vec3::new(size * 2.0, 0.0, 0.0), color);
+        self.vector(point - Vec3::new(0.0, 0.0, size), Vec3::new(0.0, 0.0, size * 2.0), color);
+    }
+
+    pub fn polygon(&mut self, vertices: &[Vec3], color: Color) {
+        for i in 0..vertices.len() {
+            self.line(vertices[i], vertices[(i + 1) % vertices.len()], color);
+        }
+    }
+
+    // circle on the XZ plane
+    pub fn circle(&mut self, center: Vec3, radius: f32, color: Color) {
+        const SEGMENTS: usize = 16;
+        let mut vertices = [Vec3::zero(); SEGMENTS];
+        for (i, vertex) in vertices.iter_mut().enumerate() {
+            let theta = (i as f32 / SEGMENTS as f32) * 2.0 * crate::PI;
+            *vertex = center + Vec3::new(theta.cos(), 0.0, theta.sin()) * radius;
+        }
+        self.polygon(&vertices, color);
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_draws_nothing() {
+        let mut debug = DebugBuffer::empty();
+        debug.buffer_reset(&Matrix4::identity());
+        debug.line(Vec3::zero(), Vec3::new(1.0, 0.0, 0.0), [1.0, 0.0, 0.0]);
+        debug.update_length();
+        assert_eq!(debug.buffer.len(), 17);
+        assert_eq!(debug.buffer[0], 17.0);
+    }
+
+    #[test]
+    fn line_layout() {
+        let mut debug = DebugBuffer::empty();
+        debug.enabled = true;
+        debug.buffer_reset(&Matrix4::identity());
+        debug.line(Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0), [0.5, 0.25, 1.0]);
+        debug.update_length();
+        assert_eq!(debug.buffer[0], 29.0);
+        assert_eq!(
+            &debug.buffer.slice()[17..],
+            &[1.0, 2.0, 3.0, 0.5, 0.25, 1.0, 4.0, 5.0, 6.0, 0.5, 0.25, 1.0]
+        );
+    }
+
+    #[test]
+    fn full_buffer_drops_lines() {
+        let mut debug = DebugBuffer::empty();
+        debug.enabled = true;
+        debug.buffer_reset(&Matrix4::identity());
+        for _ in 0..1001 {
+            debug.line(Vec3::zero(), Vec3::zero(), [1.0, 1.0, 1.0]);
+        }
+        assert_eq!(debug.buffer.len(), DEBUG_BUFFER_SIZE);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::engine::{ Arena, ArenaID, Frame };
+use crate::engine::profiler::Span;
+
+
+
+// upper bound on live entities, component storages are indexed up to this
+pub const MAX_ENTITIES: usize = 64;
+
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entity(ArenaID);
+
+impl Entity {
+    pub fn index(&self) -> usize {
+        self.0.index()
+    }
+}
+
+
+// --------------------------------------------------------
+
+
+pub struct Entities {
+    arena: Arena<(), MAX_ENTITIES>,
+}
+
+
+impl Entities {
+
+    pub fn empty() -> Self {
+        Self { arena: Arena::empty() }
+    }
+
+    pub fn create(&mut self) -> Entity {
+        Entity(self.arena.add(()))
+    }
+
+    // components have to be removed from their storages by the caller
+    pub fn destroy(&mut self, entity: Entity) -> bool {
+        self.arena.remove(entity.0).is_some()
+    }
+
+    pub fn alive(&self, entity: Entity) -> bool {
+        self.arena.contains(entity.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.arena.ids().map(Entity)
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+// Sparse set of up to `N` components of one type. Components are packed at
+// the front of `dense` and `sparse` maps an entity index to its position.
+pub struct Storage<T, const N: usize> {
+    len: usize,
+    dense: [Option<(Entity, T)>; N],
+    sparse: [Option<usize>; MAX_ENTITIES],
+}
+
+
+impl<T, const N: usize> Storage<T, N> {
+
+    pub fn empty() -> Self {
+        Self {
+            len: 0,
+            dense: core::array::from_fn(|_| None),
+            sparse: [None; MAX_ENTITIES],
+        }
+    }
+
+    // returns the component the entity had before, if any
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(old) = self.get_mut(entity) {
+            return Some(core::mem::replace(old, component));
+        }
+        // a previous owner of this index was destroyed without cleanup
+        self.remove_index(entity.index());
+
+        assert!(self.len < N, "component storage is full");
+        self.dense[self.len] = Some((entity, component));
+        self.sparse[entity.index()] = Some(self.len);
+        self.len += 1;
+        None
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        if !self.contains(entity) { return None; }
+        self.remove_index(entity.index())
+    }
+
+    fn remove_index(&mut self, index: usize) -> Option<T> {
+        let position = self.sparse[index].take()?;
+        self.len -= 1;
+        self.dense.swap(position, self.len);
+        if let Some((moved, _)) = &self.dense[position] {
+            if position != self.len {
+                self.sparse[moved.index()] = Some(position);
+            }
+        }
+        self.dense[self.len].take().map(|(_, component)| component)
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        let position = (*self.sparse.get(entity.index())?)?;
+        match &self.dense[position] {
+            Some((owner, component)) if *owner == entity => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        let position = (*self.sparse.get(entity.index())?)?;
+        match &mut self.dense[position] {
+            Some((owner, component)) if *owner == entity => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.dense[0..self.len].iter().flatten().map(|(e, c)| (*e, c))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.dense[0..self.len].iter_mut().flatten().map(|(e, c)| (*e, c))
+    }
+
+    pub fn components_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.iter_mut().map(|(_, component)| component)
+    }
+
+    // calls `func` once for every unordered pair of components
+    pub fn for_each_pair_mut<F>(&mut self, mut func: F)
+    where F: FnMut(&mut T, &mut T) {
+        let dense = &mut self.dense[0..self.len];
+        for i in 0..dense.len() {
+            let (left, right) = dense.split_at_mut(i + 1);
+            let Some((_, a)) = &mut left[i] else { continue };
+            for (_, b) in right.iter_mut().flatten() {
+                func(a, b);
+            }
+        }
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+// Queries over entities that have a component in every given storage. They
+// walk the first storage, so pass the smallest one first.
+
+pub fn query<A, B, const NA: usize, const NB: usize, F>(
+    a: &Storage<A, NA>, b: &Storage<B, NB>, mut func: F
+) where F: FnMut(Entity, &A, &B) {
+    for (entity, x) in a.iter() {
+        if let Some(y) = b.get(entity) {
+            func(entity, x, y);
+        }
+    }
+}
+
+pub fn query_mut<A, B, const NA: usize, const NB: usize, F>(
+    a: &mut Storage<A, NA>, b: &Storage<B, NB>, mut func: F
+) where F: FnMut(Entity, &mut A, &B) {
+    for (entity, x) in a.iter_mut() {
+        if let Some(y) = b.get(entity) {
+            func(entity, x, y);
+        }
+    }
+}
+
+pub fn query_mut2<A, B, const NA: usize, const NB: usize, F>(
+    a: &mut Storage<A, NA>, b: &mut Storage<B, NB>, mut func: F
+) where F: FnMut(Entity, &mut A, &mut B) {
+    for (entity, x) in a.iter_mut() {
+        if let Some(y) = b.get_mut(entity) {
+            func(entity, x, y);
+        }
+    }
+}
+
+
+// --------------------------------------------------------
+
+
+// Systems run stage by stage in this order, and in the order they were
+// added within a stage.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Stage {
+    Input,
+    Ai,
+    Physics,
+    Collision,
+    // reacting to the events published this frame
+    Events,
+    RenderUpload,
+}
+
+
+pub type System<W> = fn(&mut W, &mut Frame);
+
+
+pub struct Schedule<W, const N: usize> {
+    len: usize,
+    systems: [Option<(Stage, System<W>)>; N],
+}
+
+
+impl<W, const N: usize> Schedule<W, N> {
+
+    pub fn empty() -> Self {
+        Self { len: 0, systems: [None; N] }
+    }
+
+    pub fn add(&mut self, stage: Stage, system: System<W>) {
+        assert!(self.len < N, "schedule is full");
+        let position = self.systems[0..self.len].iter()
+            .position(|s| matches!(s, Some((other, _)) if *other > stage))
+            .unwrap_or(self.len);
+        self.systems[position..=self.len].rotate_right(1);
+        self.systems[position] = Some((stage, system));
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // every stage is timed as a profiler scope
+    pub fn run(&self, world: &mut W, frame: &mut Frame) {
+        let mut running: Option<(Stage, Span)> = None;
+        for (stage, system) in self.systems[0..self.len].iter().flatten() {
+            if running.as_ref().is_none_or(|(current, _)| current != stage) {
+                if let Some((_, span)) = running.take() {
+                    frame.profiler.end(span);
+                }
+                running = Some((*stage, frame.profiler.begin((*stage).into())));
+            }
+            system(world, frame);
+        }
+        if let Some((_, span)) = running {
+            frame.profiler.end(span);
+        }
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn destroyed_entities_are_stale() {
+        let mut entities = Entities::empty();
+        let a = entities.create();
+        assert!(entities.alive(a));
+        assert!(entities.destroy(a));
+        assert!(!entities.destroy(a));
+
+        let b = entities.create();
+        assert_eq!(a.index(), b.index());
+        assert!(!entities.alive(a));
+        assert!(entities.alive(b));
+    }
+
+    #[test]
+    fn storage_insert_remove() {
+        let mut entities = Entities::empty();
+        let mut storage: Storage<u32, 4> = Storage::empty();
+        let a = entities.create();
+        let b = entities.create();
+        let c = entities.create();
+
+        assert_eq!(storage.insert(a, 1), None);
+        assert_eq!(storage.insert(b, 2), None);
+        assert_eq!(storage.insert(c, 3), None);
+        assert_eq!(storage.insert(b, 20), Some(2));
+
+        // removing from the middle moves the last component into its place
+        assert_eq!(storage.remove(a), Some(1));
+        assert_eq!(storage.remove(a), None);
+        assert_eq!(storage.len(), 2);
+        assert_eq!(storage.get(b), Some(&20));
+        assert_eq!(storage.get(c), Some(&3));
+        assert_eq!(storage.iter().count(), 2);
+    }
+
+    #[test]
+    fn storage_ignores_stale_entities() {
+        let mut entities = Entities::empty();
+        let mut storage: Storage<u32, 4> = Storage::empty();
+        let a = entities.create();
+        storage.insert(a, 1);
+        entities.destroy(a);
+
+        let b = entities.create();
+        assert_eq!(storage.get(b), None);
+        assert_eq!(storage.insert(b, 2), None);
+        assert_eq!(storage.get(a), None);
+        assert_eq!(storage.len(), 1);
+    }
+
+    #[test]
+    fn query_joins_storages() {
+        let mut entities = Entities::empty();
+        let mut positions: Storage<f32, 8> = Storage::empty();
+        let mut velocities: Storage<f32, 8> = Storage::empty();
+
+        let moving = entities.create();
+        let still = entities.create();
+        positions.insert(moving, 0.0);
+        positions.insert(still, 5.0);
+        velocities.insert(moving, 2.0);
+
+        query_mut(&mut positions, &velocities, |_, p, v| *p += *v);
+        assert_eq!(positions.get(moving), Some(&2.0));
+        assert_eq!(positions.get(still), Some(&5.0));
+
+        let mut seen = 0;
+        query(&velocities, &positions, |e, _, _| {
+            assert_eq!(e, moving);
+            seen += 1;
+        });
+        assert_eq!(seen, 1);
+
+        query_mut2(&mut positions, &mut velocities, |_, p, v| {
+            *v = -*v;
+            *p += *v;
+        });
+        assert_eq!(positions.get(moving), Some(&0.0));
+    }
+
+    #[test]
+    fn schedule_runs_in_stage_order() {
+        let mut schedule: Schedule<Vec<u32>, 8> = Schedule::empty();
+        schedule.add(Stage::RenderUpload, |log, _| log.push(5));
+        schedule.add(Stage::Physics, |log, _| log.push(3));
+        schedule.add(Stage::Input, |log, _| log.push(1));
+        schedule.add(Stage::Physics, |log, _| log.push(4));
+        schedule.add(Stage::Ai, |log, _| log.push(2));
+
+        let mut log = Vec::new();
+        schedule.run(&mut log, &mut Frame::new());
+        assert_eq!(log, vec![1, 2, 3, 4, 5]);
+    }
+}
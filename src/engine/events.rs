@@ -0,0 +1,105 @@
+// Double buffered queue of up to `N` events per frame. Systems publish into
+// the current frame and read what earlier systems published, in stage order
+// and then in the order they were published. `swap` at the start of every
+// frame keeps the last frame around, so systems that run before the
+// publishers still see their events, one frame late.
+pub struct Events<E, const N: usize> {
+    current: [Option<E>; N],
+    current_len: usize,
+    previous: [Option<E>; N],
+    previous_len: usize,
+    dropped: usize,
+}
+
+
+impl<E: Copy, const N: usize> Events<E, N> {
+
+    pub fn empty() -> Self {
+        Self {
+            current: [None; N],
+            current_len: 0,
+            previous: [None; N],
+            previous_len: 0,
+            dropped: 0,
+        }
+    }
+
+    // false if the frame is already full, the event is dropped then
+    pub fn publish(&mut self, event: E) -> bool {
+        if self.current_len == N {
+            self.dropped += 1;
+            return false;
+        }
+        self.current[self.current_len] = Some(event);
+        self.current_len += 1;
+        true
+    }
+
+    // events published so far this frame
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.current[0..self.current_len].iter().flatten()
+    }
+
+    // everything published last frame
+    pub fn previous(&self) -> impl Iterator<Item = &E> {
+        self.previous[0..self.previous_len].iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.current_len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current_len == 0
+    }
+
+    // events that didn't fit since the queue was created
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    // starts a new frame, last frame's events are gone after this
+    pub fn swap(&mut self) {
+        core::mem::swap(&mut self.current, &mut self.previous);
+        self.previous_len = self.current_len;
+        self.current_len = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.current_len = 0;
+        self.previous_len = 0;
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_last_two_frames_in_order() {
+        let mut events: Events<u32, 3> = Events::empty();
+        events.publish(1);
+        events.publish(2);
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(events.previous().count(), 0);
+
+        events.swap();
+        events.publish(3);
+        assert_eq!(events.iter().copied().collect::<Vec<_>>(), [3]);
+        assert_eq!(events.previous().copied().collect::<Vec<_>>(), [1, 2]);
+
+        events.swap();
+        events.swap();
+        assert!(events.is_empty());
+        assert_eq!(events.previous().count(), 0);
+
+        for i in 0..4 {
+            events.publish(i);
+        }
+        assert_eq!(events.len(), 3);
+        assert_eq!(events.dropped(), 1);
+    }
+}
@@ -1,55 +1,70 @@
 
 use crate::math::{ Matrix4 };
-use crate::engine::{ MemoryBuffer };
-
-
-
-pub enum Key {
-    W = 0,
-    A,
-    S,
-    D,
-    Q,
-    E,
-    Space,
-}
+use crate::engine::{ MemoryBuffer, DebugBuffer, Profiler };
+use crate::engine::viewport::Views;
+use crate::engine::input::{ Input, InputMap, Action, Axis };
 
 
 
 pub struct Frame {
+    // game time and the time since the last frame in seconds, from `Clock`
     pub t: f32,
     pub dt: f32,
-    pub keys: u8,
-    pub projection_matrix: Matrix4,
+    pub input: Input,
+    pub bindings: InputMap,
     pub buffer: MemoryBuffer,
+    pub debug: DebugBuffer,
+    pub profiler: Profiler,
 }
 
 
 impl Frame {
 
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self {
-            t: 0.0, dt: 0.0, keys: 0,
-            projection_matrix: Matrix4::identity(),
+            t: 0.0, dt: 0.0,
+            input: Input::default(),
+            bindings: InputMap::new(),
             buffer: MemoryBuffer::empty(),
+            debug: DebugBuffer::empty(),
+            profiler: Profiler::new(),
         }
     }
 
-    pub fn update(&mut self, t: f32, dt: f32, keys: u8, matrix: Matrix4) {
+    // debug lines are drawn with `matrix`, the main view's view projection
+    pub fn update(&mut self, t: f32, dt: f32, input: &Input, matrix: Matrix4) {
         self.buffer.buffer_reset();
+        self.debug.buffer_reset(&matrix);
         self.t = t;
         self.dt = dt;
-        self.keys = keys;
-        self.projection_matrix = matrix;
+        self.input = *input;
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.bindings.pressed(&self.input, action)
     }
 
-    pub fn pressed(&self, key: Key) -> bool {
-        self.keys & (1 << key as u8) > 0
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.bindings.axis(&self.input, axis)
     }
 
-    pub fn add_view_matrix(&mut self, id: usize, mut matrix: Matrix4) {
-        matrix = self.projection_matrix * matrix;
-        self.buffer.add_matrix(id, 0.0, 0.0, &matrix);
+    pub fn add_world_matrix(&mut self, id: usize, matrix: Matrix4) {
+        self.buffer.add_matrix(id, 0, 0, &matrix);
+    }
+
+    // keeps room for `add_views`, which runs after everything else was
+    // written
+    pub fn reserve_views(&mut self, views: &Views) {
+        self.buffer.reserve_viewports(views.len());
+    }
+
+    // one viewport command for every view, JS draws a pass for each
+    pub fn add_views(&mut self, views: &Views) {
+        for (i, view) in views.iter().enumerate() {
+            let matrix = view.camera.view_projection_matrix();
+            self.buffer.set_viewport(i, view.mask, &view.rect, &matrix);
+        }
     }
 
 }
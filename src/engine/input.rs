@@ -0,0 +1,505 @@
+// Raw input is written by JS into an `Input` every frame, the game only
+// looks at it through named actions and axes. What drives each of them is
+// set in an `InputMap` and can be rebound at runtime.
+
+
+
+const MAX_BINDINGS: usize = 4;
+
+// size of `Input` in u32 words
+pub const INPUT_WORDS: usize = 14;
+
+// size of an `InputMap` in u32 words
+pub const BINDING_WORDS: usize = (Action::COUNT + Axis::COUNT) * MAX_BINDINGS;
+
+// stick values below this are treated as 0, the rest is rescaled to 0..1
+const DEAD_ZONE: f32 = 0.15;
+
+
+
+// Key codes shared with `keyCodes` in `js/main.js`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Key {
+    A = 0, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Digit0, Digit1, Digit2, Digit3, Digit4,
+    Digit5, Digit6, Digit7, Digit8, Digit9,
+    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    Space, Enter, Escape, Tab, ShiftLeft, ControlLeft, AltLeft,
+}
+
+
+impl Key {
+
+    const ALL: [Key; 47] = {
+        use Key::*;
+        [
+            A, B, C, D, E, F, G, H, I, J, K, L, M,
+            N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+            Digit0, Digit1, Digit2, Digit3, Digit4,
+            Digit5, Digit6, Digit7, Digit8, Digit9,
+            ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+            Space, Enter, Escape, Tab, ShiftLeft, ControlLeft, AltLeft,
+        ]
+    };
+
+    pub fn from_code(code: u8) -> Option<Key> {
+        Self::ALL.get(code as usize).copied()
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+// Shared with JS, which fills it in before every call to `render`. Layout
+// in u32 words:
+//
+//   0..4    key bitset, bit n is `Key` with code n
+//   4..8    mouse x, y in pixels and movement since the last frame
+//   8       mouse buttons bitset
+//   9..13   gamepad axes, -1..1
+//   13      gamepad buttons bitset
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Input {
+    pub keys: [u32; 4],
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    pub mouse_dx: f32,
+    pub mouse_dy: f32,
+    pub mouse_buttons: u32,
+    pub gamepad_axes: [f32; 4],
+    pub gamepad_buttons: u32,
+}
+
+
+impl Input {
+
+    pub fn to_words(&self) -> [u32; INPUT_WORDS] {
+        let mut words = [0; INPUT_WORDS];
+        words[0..4].copy_from_slice(&self.keys);
+        words[4] = self.mouse_x.to_bits();
+        words[5] = self.mouse_y.to_bits();
+        words[6] = self.mouse_dx.to_bits();
+        words[7] = self.mouse_dy.to_bits();
+        words[8] = self.mouse_buttons;
+        for i in 0..4 {
+            words[9 + i] = self.gamepad_axes[i].to_bits();
+        }
+        words[13] = self.gamepad_buttons;
+        words
+    }
+
+    pub fn from_words(words: &[u32; INPUT_WORDS]) -> Self {
+        let mut keys = [0; 4];
+        keys.copy_from_slice(&words[0..4]);
+        Self {
+            keys,
+            mouse_x: f32::from_bits(words[4]),
+            mouse_y: f32::from_bits(words[5]),
+            mouse_dx: f32::from_bits(words[6]),
+            mouse_dy: f32::from_bits(words[7]),
+            mouse_buttons: words[8],
+            gamepad_axes: core::array::from_fn(|i| f32::from_bits(words[9 + i])),
+            gamepad_buttons: words[13],
+        }
+    }
+
+    pub fn with_keys(keys: &[Key]) -> Self {
+        let mut input = Self::default();
+        for key in keys {
+            input.set_key(*key, true);
+        }
+        input
+    }
+
+    pub fn set_key(&mut self, key: Key, down: bool) {
+        let (word, bit) = (key as usize / 32, key as u32 % 32);
+        if down {
+            self.keys[word] |= 1 << bit;
+        } else {
+            self.keys[word] &= !(1 << bit);
+        }
+    }
+
+    pub fn key(&self, key: Key) -> bool {
+        self.keys[key as usize / 32] & (1 << (key as u32 % 32)) > 0
+    }
+
+    pub fn mouse_button(&self, button: u8) -> bool {
+        button < 32 && self.mouse_buttons & (1 << button) > 0
+    }
+
+    pub fn gamepad_button(&self, button: u8) -> bool {
+        button < 32 && self.gamepad_buttons & (1 << button) > 0
+    }
+
+    pub fn gamepad_axis(&self, axis: u8) -> f32 {
+        let value = self.gamepad_axes.get(axis as usize).copied().unwrap_or(0.0);
+        if value.abs() < DEAD_ZONE { return 0.0; }
+        let value = (value.abs() - DEAD_ZONE) / (1.0 - DEAD_ZONE);
+        value.min(1.0) * self.gamepad_axes[axis as usize].signum()
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Fire = 0,
+}
+
+impl Action {
+    const COUNT: usize = 1;
+
+    pub fn from_code(code: u8) -> Option<Action> {
+        match code {
+            0 => Some(Action::Fire),
+            _ => None,
+        }
+    }
+}
+
+
+// analog values from -1 to 1
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    Thrust = 0,
+    Turn,
+    Strafe,
+    Zoom,
+}
+
+impl Axis {
+    const COUNT: usize = 4;
+
+    pub fn from_code(code: u8) -> Option<Axis> {
+        match code {
+            0 => Some(Axis::Thrust),
+            1 => Some(Axis::Turn),
+            2 => Some(Axis::Strafe),
+            3 => Some(Axis::Zoom),
+            _ => None,
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Binding {
+    Key(Key),
+    MouseButton(u8),
+    GamepadButton(u8),
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AxisBinding {
+    // -1 while `negative` is held, 1 while `positive` is held
+    Keys { negative: Key, positive: Key },
+    Gamepad { axis: u8, invert: bool },
+}
+
+
+// A binding slot packs into a u32 as the kind in the low byte, 0 for an
+// empty slot, and its key codes, buttons or axis in the bytes above.
+impl Binding {
+    fn value(&self, input: &Input) -> bool {
+        match *self {
+            Binding::Key(key) => input.key(key),
+            Binding::MouseButton(button) => input.mouse_button(button),
+            Binding::GamepadButton(button) => input.gamepad_button(button),
+        }
+    }
+
+    fn to_word(slot: Option<Binding>) -> u32 {
+        match slot {
+            None => 0,
+            Some(Binding::Key(key)) => 1 | (key as u32) << 8,
+            Some(Binding::MouseButton(button)) => 2 | (button as u32) << 8,
+            Some(Binding::GamepadButton(button)) => 3 | (button as u32) << 8,
+        }
+    }
+
+    // None if the word isn't a valid slot
+    fn from_word(word: u32) -> Option<Option<Binding>> {
+        let arg = (word >> 8) as u8;
+        match word & 0xff {
+            0 => Some(None),
+            1 => Key::from_code(arg).map(|key| Some(Binding::Key(key))),
+            2 => Some(Some(Binding::MouseButton(arg))),
+            3 => Some(Some(Binding::GamepadButton(arg))),
+            _ => None,
+        }
+    }
+}
+
+
+impl AxisBinding {
+    fn value(&self, input: &Input) -> f32 {
+        match *self {
+            AxisBinding::Keys { negative, positive } => {
+                input.key(positive) as u8 as f32 - input.key(negative) as u8 as f32
+            },
+            AxisBinding::Gamepad { axis, invert } => {
+                let value = input.gamepad_axis(axis);
+                if invert { -value } else { value }
+            },
+        }
+    }
+
+    fn to_word(slot: Option<AxisBinding>) -> u32 {
+        match slot {
+            None => 0,
+            Some(AxisBinding::Keys { negative, positive }) => {
+                1 | (negative as u32) << 8 | (positive as u32) << 16
+            },
+            Some(AxisBinding::Gamepad { axis, invert }) => {
+                2 | (axis as u32) << 8 | (invert as u32) << 16
+            },
+        }
+    }
+
+    fn from_word(word: u32) -> Option<Option<AxisBinding>> {
+        let (a, b) = ((word >> 8) as u8, (word >> 16) as u8);
+        match word & 0xff {
+            0 => Some(None),
+            1 => Some(Some(AxisBinding::Keys {
+                negative: Key::from_code(a)?, positive: Key::from_code(b)?
+            })),
+            2 => Some(Some(AxisBinding::Gamepad { axis: a, invert: b != 0 })),
+            _ => None,
+        }
+    }
+}
+
+
+// --------------------------------------------------------
+
+
+// Up to `MAX_BINDINGS` bindings per action and axis, any of them can drive
+// it. Values of all bindings of an axis are added up and clamped.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct InputMap {
+    actions: [[Option<Binding>; MAX_BINDINGS]; Action::COUNT],
+    axes: [[Option<AxisBinding>; MAX_BINDINGS]; Axis::COUNT],
+}
+
+
+impl InputMap {
+
+    pub fn empty() -> Self {
+        Self {
+            actions: [[None; MAX_BINDINGS]; Action::COUNT],
+            axes: [[None; MAX_BINDINGS]; Axis::COUNT],
+        }
+    }
+
+    // WASD, QE and Space, arrow keys, mouse and a standard gamepad layout
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let mut map = Self::empty();
+        map.bind(Action::Fire, Binding::Key(Key::Space));
+        map.bind(Action::Fire, Binding::MouseButton(0));
+        map.bind(Action::Fire, Binding::GamepadButton(7));
+
+        map.bind_axis(Axis::Thrust, AxisBinding::Keys { negative: Key::S, positive: Key::W });
+        map.bind_axis(Axis::Thrust, AxisBinding::Keys {
+            negative: Key::ArrowDown, positive: Key::ArrowUp
+        });
+        map.bind_axis(Axis::Thrust, AxisBinding::Gamepad { axis: 1, invert: true });
+
+        map.bind_axis(Axis::Turn, AxisBinding::Keys { negative: Key::A, positive: Key::D });
+        map.bind_axis(Axis::Turn, AxisBinding::Keys {
+            negative: Key::ArrowLeft, positive: Key::ArrowRight
+        });
+        map.bind_axis(Axis::Turn, AxisBinding::Gamepad { axis: 0, invert: false });
+
+        map.bind_axis(Axis::Strafe, AxisBinding::Keys { negative: Key::Q, positive: Key::E });
+        map.bind_axis(Axis::Strafe, AxisBinding::Gamepad { axis: 2, invert: false });
+
+        map.bind_axis(Axis::Zoom, AxisBinding::Keys { negative: Key::Z, positive: Key::X });
+        map.bind_axis(Axis::Zoom, AxisBinding::Gamepad { axis: 3, invert: false });
+        map
+    }
+
+    // adds a binding, returns false if the action has no free slots left
+    pub fn bind(&mut self, action: Action, binding: Binding) -> bool {
+        insert(&mut self.actions[action as usize], binding)
+    }
+
+    pub fn bind_axis(&mut self, axis: Axis, binding: AxisBinding) -> bool {
+        insert(&mut self.axes[axis as usize], binding)
+    }
+
+    // replaces the binding of the same kind, or adds it if there was none
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let slots = &mut self.actions[action as usize];
+        let same = |b: &Binding| {
+            core::mem::discriminant(b) == core::mem::discriminant(&binding)
+        };
+        replace(slots, binding, same);
+    }
+
+    pub fn rebind_axis(&mut self, axis: Axis, binding: AxisBinding) {
+        let slots = &mut self.axes[axis as usize];
+        let same = |b: &AxisBinding| {
+            core::mem::discriminant(b) == core::mem::discriminant(&binding)
+        };
+        replace(slots, binding, same);
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.actions[action as usize] = [None; MAX_BINDINGS];
+    }
+
+    pub fn clear_axis(&mut self, axis: Axis) {
+        self.axes[axis as usize] = [None; MAX_BINDINGS];
+    }
+
+    pub fn pressed(&self, input: &Input, action: Action) -> bool {
+        self.actions[action as usize].iter().flatten().any(|b| b.value(input))
+    }
+
+    pub fn axis(&self, input: &Input, axis: Axis) -> f32 {
+        self.axes[axis as usize].iter().flatten()
+            .map(|b| b.value(input))
+            .sum::<f32>()
+            .clamp(-1.0, 1.0)
+    }
+
+    // the slots of every action, then of every axis
+    pub fn to_words(&self) -> [u32; BINDING_WORDS] {
+        let actions = self.actions.as_flattened().iter().map(|b| Binding::to_word(*b));
+        let axes = self.axes.as_flattened().iter().map(|b| AxisBinding::to_word(*b));
+        let mut words = [0; BINDING_WORDS];
+        for (word, value) in words.iter_mut().zip(actions.chain(axes)) {
+            *word = value;
+        }
+        words
+    }
+
+    // None if any of the words isn't a valid binding
+    pub fn from_words(words: &[u32; BINDING_WORDS]) -> Option<Self> {
+        let (actions, axes) = words.split_at(Action::COUNT * MAX_BINDINGS);
+        let mut map = Self::empty();
+        for (slot, word) in map.actions.as_flattened_mut().iter_mut().zip(actions) {
+            *slot = Binding::from_word(*word)?;
+        }
+        for (slot, word) in map.axes.as_flattened_mut().iter_mut().zip(axes) {
+            *slot = AxisBinding::from_word(*word)?;
+        }
+        Some(map)
+    }
+
+}
+
+
+fn insert<T>(slots: &mut [Option<T>; MAX_BINDINGS], binding: T) -> bool {
+    match slots.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => {
+            *slot = Some(binding);
+            true
+        },
+        None => false,
+    }
+}
+
+fn replace<T: Copy>(
+    slots: &mut [Option<T>; MAX_BINDINGS], binding: T, same: impl Fn(&T) -> bool
+) {
+    match slots.iter_mut().flatten().find(|b| same(b)) {
+        Some(b) => *b = binding,
+        None => {
+            if !insert(slots, binding) {
+                slots[MAX_BINDINGS - 1] = Some(binding);
+            }
+        },
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_past_the_first_word() {
+        let mut input = Input::with_keys(&[Key::W, Key::Space, Key::AltLeft]);
+        assert!(input.key(Key::W));
+        assert!(input.key(Key::Space));
+        assert!(input.key(Key::AltLeft));
+        assert!(!input.key(Key::S));
+
+        input.set_key(Key::Space, false);
+        assert!(!input.key(Key::Space));
+        assert_eq!(Key::from_code(Key::AltLeft as u8), Some(Key::AltLeft));
+        assert_eq!(Key::from_code(200), None);
+    }
+
+    #[test]
+    fn axes_combine_keys_and_gamepad() {
+        let map = InputMap::new();
+        let mut input = Input::with_keys(&[Key::W]);
+        assert_eq!(map.axis(&input, Axis::Thrust), 1.0);
+
+        input.set_key(Key::S, true);
+        assert_eq!(map.axis(&input, Axis::Thrust), 0.0);
+
+        // stick pushed halfway up, past the dead zone
+        let mut input = Input::default();
+        input.gamepad_axes[1] = -0.575;
+        assert!((map.axis(&input, Axis::Thrust) - 0.5).abs() < 0.0001);
+
+        // stick drift is ignored, and keys and stick together are clamped
+        input.gamepad_axes[1] = -0.1;
+        assert_eq!(map.axis(&input, Axis::Thrust), 0.0);
+        input.gamepad_axes[1] = -1.0;
+        input.set_key(Key::W, true);
+        assert_eq!(map.axis(&input, Axis::Thrust), 1.0);
+    }
+
+    #[test]
+    fn rebinding() {
+        let mut map = InputMap::new();
+        map.rebind(Action::Fire, Binding::Key(Key::F));
+        assert!(!map.pressed(&Input::with_keys(&[Key::Space]), Action::Fire));
+        assert!(map.pressed(&Input::with_keys(&[Key::F]), Action::Fire));
+
+        // other kinds of bindings are kept
+        let input = Input { mouse_buttons: 1, ..Default::default() };
+        assert!(map.pressed(&input, Action::Fire));
+
+        map.rebind_axis(Axis::Turn, AxisBinding::Keys {
+            negative: Key::J, positive: Key::L
+        });
+        let input = Input::with_keys(&[Key::J]);
+        assert_eq!(map.axis(&input, Axis::Turn), -1.0);
+        assert_eq!(map.axis(&Input::with_keys(&[Key::A]), Axis::Turn), 0.0);
+
+        map.clear(Action::Fire);
+        assert!(!map.pressed(&Input::with_keys(&[Key::F]), Action::Fire));
+    }
+
+    #[test]
+    fn bindings_round_trip_through_words() {
+        let mut map = InputMap::new();
+        map.rebind(Action::Fire, Binding::Key(Key::F));
+        map.rebind_axis(Axis::Zoom, AxisBinding::Gamepad { axis: 2, invert: true });
+        map.clear_axis(Axis::Strafe);
+        assert_eq!(InputMap::from_words(&map.to_words()), Some(map));
+
+        let mut words = map.to_words();
+        words[0] = 1 | 200 << 8;
+        assert_eq!(InputMap::from_words(&words), None);
+        words[0] = 9;
+        assert_eq!(InputMap::from_words(&words), None);
+    }
+}
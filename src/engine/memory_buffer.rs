@@ -1,7 +1,43 @@
-
 use crate::math::Matrix4;
-use crate::engine::Arena;
+use crate::engine::StackVec;
+use crate::engine::viewport::Rect;
+
+
+// Frame protocol, every field is one u32 word. Floats are sent as their bit
+// patterns, JS reads the same memory through a Uint32Array and a
+// Float32Array.
+//
+// header:  version, length in words, command count, flags, dropped commands
+// command: (opcode << 24) | payload length in words, then the payload
+//
+//   SET_UNIFORM   entity, uniform block, uniform, values...
+//   SET_VISIBLE   entity, 0 or 1
+//   SET_VIEWPORT  view, layer mask, x, y, width, height, 16 floats of the
+//                 view projection matrix
+//   SET_LAYERS    entity, layers
+//
+// Entities carry their world matrix in uniform 0 of block 0. JS draws the
+// scene once for every viewport, in the order they are sent, with the
+// entities whose layers share a bit with the viewport's mask. Entities start
+// out on layer 1.
+//
+// Commands that don't fit are dropped whole and counted, and the OVERFLOW
+// flag is set, so the frame still decodes. Viewports are sent last, room for
+// them can be reserved at the start of the frame so they are never dropped.
+
+pub const VERSION: u32 = 2;
+
+pub const HEADER_WORDS: usize = 5;
+
+pub const FLAG_OVERFLOW: u32 = 1 << 0;
 
+pub const OP_SET_UNIFORM: u32 = 1;
+pub const OP_SET_VISIBLE: u32 = 2;
+pub const OP_SET_VIEWPORT: u32 = 3;
+pub const OP_SET_LAYERS: u32 = 4;
+
+// a SET_VIEWPORT command with its opcode word
+pub const VIEWPORT_WORDS: usize = 1 + 2 + 20;
 
 const BUFFER_SIZE: usize = 4000;
 
@@ -11,40 +47,359 @@ const BUFFER_SIZE: usize = 4000;
 pub static BSIZE: u32 = BUFFER_SIZE as u32;
 
 
-pub type MemoryBuffer = Arena<f32, BUFFER_SIZE>;
+pub type MemoryBuffer = FrameBuffer<BUFFER_SIZE>;
+
+
+pub struct FrameBuffer<const N: usize> {
+    words: StackVec<u32, N>,
+    commands: u32,
+    dropped: u32,
+    // words only SET_VIEWPORT can use
+    reserved: usize,
+}
+
 
+impl<const N: usize> FrameBuffer<N> {
 
-impl MemoryBuffer {
+    pub fn empty() -> Self {
+        let mut buffer = Self {
+            words: StackVec::empty(),
+            commands: 0,
+            dropped: 0,
+            reserved: 0,
+        };
+        buffer.buffer_reset();
+        buffer
+    }
+
+    pub fn as_ptr(&mut self) -> *const u32 {
+        self.words.as_ptr()
+    }
 
     pub fn buffer_reset(&mut self) {
-        self.reset();
-        self.add(0.0);
+        self.words.reset();
+        for _ in 0..HEADER_WORDS {
+            self.words.add(0);
+        }
+        self.commands = 0;
+        self.dropped = 0;
+        self.reserved = 0;
     }
 
+    // keeps room for `count` viewports until the frame is reset
+    pub fn reserve_viewports(&mut self, count: usize) {
+        self.reserved += count * VIEWPORT_WORDS;
+    }
+
+    // writes the header, after this the buffer is ready to be read by JS
     pub fn update_length(&mut self) {
-        self[0.into()] = self.len() as f32;
+        self.words[0] = VERSION;
+        self.words[1] = self.words.len() as u32;
+        self.words[2] = self.commands;
+        self.words[3] = if self.dropped > 0 { FLAG_OVERFLOW } else { 0 };
+        self.words[4] = self.dropped;
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.dropped > 0
+    }
+
+    // words that fit, header included
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn commands(&self) -> usize {
+        self.commands as usize
     }
 
-    pub fn add_float(&mut self, id: usize, ublock: f32, uvar: f32, value: f32) {
-        self.add(id as f32);
-        self.add(ublock);
-        self.add(uvar);
-        self.add(1.0);
-        self.add(value);
+    pub fn dropped(&self) -> usize {
+        self.dropped as usize
+    }
+
+    pub fn words(&self) -> &[u32] {
+        self.words.slice()
+    }
+
+    // returns false if the command was dropped for lack of space
+    fn command(&mut self, opcode: u32, payload: &[u32], values: &[f32]) -> bool {
+        let len = payload.len() + values.len();
+        if self.words.len() + 1 + len + self.reserved > N {
+            self.dropped += 1;
+            return false;
+        }
+        self.words.add((opcode << 24) | len as u32);
+        for word in payload {
+            self.words.add(*word);
+        }
+        for value in values {
+            self.words.add(value.to_bits());
+        }
+        self.commands += 1;
+        true
+    }
+
+    pub fn add_float(
+        &mut self, id: usize, ublock: u32, uvar: u32, value: f32
+    ) -> bool {
+        self.command(OP_SET_UNIFORM, &[id as u32, ublock, uvar], &[value])
     }
 
     pub fn add_matrix(
-        &mut self, id: usize, ublock: f32, uvar: f32, matrix: &Matrix4
-    ) {
-        self.add(id as f32);
-        self.add(ublock);
-        self.add(uvar);
-        self.add(16.0);
-        for row in matrix.matrix.iter() {
-            for value in row.iter() {
-                self.add(*value);
-            }
+        &mut self, id: usize, ublock: u32, uvar: u32, matrix: &Matrix4
+    ) -> bool {
+        self.command(
+            OP_SET_UNIFORM, &[id as u32, ublock, uvar],
+            matrix.matrix.as_flattened(),
+        )
+    }
+
+    pub fn set_visible(&mut self, id: usize, visible: bool) -> bool {
+        self.command(OP_SET_VISIBLE, &[id as u32, visible as u32], &[])
+    }
+
+    pub fn set_layers(&mut self, id: usize, layers: u32) -> bool {
+        self.command(OP_SET_LAYERS, &[id as u32, layers], &[])
+    }
+
+    pub fn set_viewport(
+        &mut self, view: usize, mask: u32, rect: &Rect, view_projection: &Matrix4
+    ) -> bool {
+        let mut values = [0.0; 20];
+        values[0..4].copy_from_slice(&[rect.x, rect.y, rect.width, rect.height]);
+        values[4..20].copy_from_slice(view_projection.matrix.as_flattened());
+        self.reserved = self.reserved.saturating_sub(VIEWPORT_WORDS);
+        self.command(OP_SET_VIEWPORT, &[view as u32, mask], &values)
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Header {
+    pub version: u32,
+    pub length: u32,
+    pub commands: u32,
+    pub flags: u32,
+    pub dropped: u32,
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Command<'a> {
+    SetUniform { entity: u32, block: u32, uniform: u32, values: &'a [u32] },
+    SetVisible { entity: u32, visible: bool },
+    // rect and view projection matrix as f32 bit patterns
+    SetViewport { view: u32, mask: u32, values: &'a [u32] },
+    SetLayers { entity: u32, layers: u32 },
+}
+
+
+impl<'a> Command<'a> {
+    // uniform values and viewports are carried as f32 bit patterns
+    pub fn values(&self) -> impl Iterator<Item = f32> + 'a {
+        let values: &'a [u32] = match self {
+            Command::SetUniform { values, .. } => values,
+            Command::SetViewport { values, .. } => values,
+            _ => &[],
+        };
+        values.iter().map(|bits| f32::from_bits(*bits))
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DecodeError {
+    Truncated,
+    Version(u32),
+    UnknownOpcode(u32),
+    BadPayload(u32),
+}
+
+
+// reads a frame the way JS does, checking the header and every command
+pub fn decode(
+    words: &[u32]
+) -> Result<(Header, impl Iterator<Item = Command<'_>>), DecodeError> {
+    if words.len() < HEADER_WORDS { return Err(DecodeError::Truncated); }
+    let header = Header {
+        version: words[0],
+        length: words[1],
+        commands: words[2],
+        flags: words[3],
+        dropped: words[4],
+    };
+    if header.version != VERSION { return Err(DecodeError::Version(header.version)); }
+    let body = words.get(HEADER_WORDS..header.length as usize)
+        .ok_or(DecodeError::Truncated)?;
+
+    // validate up front so the iterator can't fail half way through
+    let mut offset = 0;
+    for _ in 0..header.commands {
+        let (_, len) = split_command(*body.get(offset).ok_or(DecodeError::Truncated)?);
+        offset += 1 + len;
+        if offset > body.len() { return Err(DecodeError::Truncated); }
+        decode_command(&body[offset - 1 - len..offset])?;
+    }
+    if offset != body.len() { return Err(DecodeError::Truncated); }
+
+    let mut offset = 0;
+    let commands = (0..header.commands).map(move |_| {
+        let (_, len) = split_command(body[offset]);
+        let command = &body[offset..offset + 1 + len];
+        offset += 1 + len;
+        decode_command(command).expect("validated above")
+    });
+    Ok((header, commands))
+}
+
+fn split_command(word: u32) -> (u32, usize) {
+    (word >> 24, (word & 0x00ff_ffff) as usize)
+}
+
+fn decode_command(command: &[u32]) -> Result<Command<'_>, DecodeError> {
+    let (opcode, _) = split_command(command[0]);
+    let payload = &command[1..];
+    match opcode {
+        OP_SET_UNIFORM => match payload {
+            [entity, block, uniform, values @ ..] if !values.is_empty() => Ok(
+                Command::SetUniform {
+                    entity: *entity, block: *block, uniform: *uniform, values
+                }
+            ),
+            _ => Err(DecodeError::BadPayload(opcode)),
+        },
+        OP_SET_VISIBLE => match payload {
+            [entity, visible] if *visible <= 1 => Ok(
+                Command::SetVisible { entity: *entity, visible: *visible == 1 }
+            ),
+            _ => Err(DecodeError::BadPayload(opcode)),
+        },
+        OP_SET_VIEWPORT => match payload {
+            [view, mask, values @ ..] if values.len() == 20 => Ok(
+                Command::SetViewport { view: *view, mask: *mask, values }
+            ),
+            _ => Err(DecodeError::BadPayload(opcode)),
+        },
+        OP_SET_LAYERS => match payload {
+            [entity, layers] => Ok(
+                Command::SetLayers { entity: *entity, layers: *layers }
+            ),
+            _ => Err(DecodeError::BadPayload(opcode)),
+        },
+        _ => Err(DecodeError::UnknownOpcode(opcode)),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut buffer: FrameBuffer<64> = FrameBuffer::empty();
+        let mut matrix = Matrix4::identity();
+        matrix.translate(crate::math::Vec3::new(1.5, -2.0, 1e-7));
+
+        // ids past 2^24 don't survive a trip through f32
+        assert!(buffer.add_matrix(16_777_217, 0, 0, &matrix));
+        assert!(buffer.add_float(3, 0, 1, 0.25));
+        assert!(buffer.set_visible(7, false));
+        assert!(buffer.set_layers(7, 0b110));
+        assert!(buffer.set_viewport(1, 0b10, &Rect::new(0.75, 0.0, 0.25, 0.5), &matrix));
+        buffer.update_length();
+
+        let (header, commands) = decode(buffer.words()).unwrap();
+        assert_eq!(header, Header {
+            version: VERSION, length: 5 + 20 + 5 + 3 + 3 + 23, commands: 5,
+            flags: 0, dropped: 0,
+        });
+
+        let commands: Vec<Command> = commands.collect();
+        match commands[0] {
+            Command::SetUniform { entity, block, uniform, .. } => {
+                assert_eq!((entity, block, uniform), (16_777_217, 0, 0));
+            },
+            _ => panic!("expected SetUniform"),
         }
+        let values: Vec<f32> = commands[0].values().collect();
+        assert_eq!(values, matrix.matrix.as_flattened());
+
+        assert_eq!(commands[1].values().collect::<Vec<_>>(), vec![0.25]);
+        assert_eq!(commands[2], Command::SetVisible { entity: 7, visible: false });
+        assert_eq!(commands[3], Command::SetLayers { entity: 7, layers: 0b110 });
+        let Command::SetViewport { view, mask, .. } = commands[4] else { panic!() };
+        assert_eq!((view, mask), (1, 0b10));
+        let values: Vec<f32> = commands[4].values().collect();
+        assert_eq!(&values[0..4], &[0.75, 0.0, 0.25, 0.5]);
+        assert_eq!(&values[4..], matrix.matrix.as_flattened());
+    }
+
+    #[test]
+    fn overflow_drops_whole_commands() {
+        let mut buffer: FrameBuffer<32> = FrameBuffer::empty();
+        let matrix = Matrix4::identity();
+
+        assert!(buffer.add_matrix(1, 0, 0, &matrix));
+        assert!(!buffer.add_matrix(2, 0, 0, &matrix));
+        assert!(buffer.set_visible(3, true));
+        assert!(!buffer.add_float(4, 0, 1, 1.0));
+        assert!(buffer.overflowed());
+        buffer.update_length();
+
+        let (header, commands) = decode(buffer.words()).unwrap();
+        assert_eq!(header.flags & FLAG_OVERFLOW, FLAG_OVERFLOW);
+        assert_eq!(header.dropped, 2);
+        assert_eq!(header.commands, 2);
+        assert_eq!(commands.count(), 2);
+
+        buffer.buffer_reset();
+        buffer.update_length();
+        let (header, commands) = decode(buffer.words()).unwrap();
+        assert_eq!((header.flags, header.commands), (0, 0));
+        assert_eq!(commands.count(), 0);
+    }
+
+    #[test]
+    fn reserved_viewports_are_not_dropped() {
+        let mut buffer: FrameBuffer<64> = FrameBuffer::empty();
+        let matrix = Matrix4::identity();
+        buffer.reserve_viewports(1);
+
+        assert!(buffer.add_matrix(1, 0, 0, &matrix));
+        assert!(!buffer.add_matrix(2, 0, 0, &matrix));
+        assert!(buffer.set_viewport(0, 1, &Rect::new(0.0, 0.0, 1.0, 1.0), &matrix));
+        assert_eq!(buffer.words().len(), 5 + 20 + VIEWPORT_WORDS);
+        assert_eq!(buffer.dropped(), 1);
+
+        // the reservation is used up, the rest of the buffer is free again
+        assert!(buffer.set_visible(3, true));
     }
 
+    #[test]
+    fn rejects_malformed_frames() {
+        let mut buffer: FrameBuffer<32> = FrameBuffer::empty();
+        buffer.set_visible(1, true);
+        buffer.update_length();
+        let words = buffer.words().to_vec();
+
+        let mut bad = words.clone();
+        bad[0] = 99;
+        assert_eq!(decode(&bad).err(), Some(DecodeError::Version(99)));
+
+        assert_eq!(decode(&words[0..7]).err(), Some(DecodeError::Truncated));
+
+        let mut bad = words.clone();
+        bad[5] = (9 << 24) | 2;
+        assert_eq!(decode(&bad).err(), Some(DecodeError::UnknownOpcode(9)));
+
+        let mut bad = words.clone();
+        bad[7] = 2;
+        assert_eq!(decode(&bad).err(), Some(DecodeError::BadPayload(OP_SET_VISIBLE)));
+    }
 }
@@ -0,0 +1,241 @@
+// Meshes imported at build time by `tools/assets` from the files in `assets/`
+// and embedded with `add_mesh!`. The bytes go to JS as they are, this only
+// checks them and hands out the buffers a `RenderDescriptor` binds.
+//
+// Binary format, little endian:
+//
+//   header:  "NMSH", version u16, flags u16, vertices u32, indices u32
+//   streams: positions f32 x3, then normals f32 x3, uvs f32 x2 and colors
+//            u8 x4 for the ones set in the flags, one stream after the other
+//   indices: u16, or u32 with WIDE_INDICES
+
+use crate::engine::Buffer;
+
+
+
+const MAGIC: &[u8; 4] = b"NMSH";
+const VERSION: u16 = 1;
+
+pub const HEADER_SIZE: usize = 16;
+
+const HAS_NORMALS: u16 = 1 << 0;
+const HAS_UVS: u16 = 1 << 1;
+const HAS_COLORS: u16 = 1 << 2;
+const WIDE_INDICES: u16 = 1 << 3;
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Attribute {
+    Position,
+    Normal,
+    Uv,
+    Color,
+}
+
+
+impl Attribute {
+
+    // in the order the streams are stored
+    pub const ALL: [Attribute; 4] = [
+        Attribute::Position, Attribute::Normal, Attribute::Uv, Attribute::Color,
+    ];
+
+    // what JS calls the stream, the buffer is named "<mesh>.<attribute>"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Attribute::Position => "position",
+            Attribute::Normal => "normal",
+            Attribute::Uv => "uv",
+            Attribute::Color => "color",
+        }
+    }
+
+    // components per vertex
+    pub fn size(&self) -> usize {
+        match self {
+            Attribute::Position | Attribute::Normal => 3,
+            Attribute::Uv => 2,
+            Attribute::Color => 4,
+        }
+    }
+
+    fn stride(&self) -> usize {
+        match self {
+            Attribute::Color => 4,
+            _ => self.size() * 4,
+        }
+    }
+
+    fn flag(&self) -> u16 {
+        match self {
+            Attribute::Position => 0,
+            Attribute::Normal => HAS_NORMALS,
+            Attribute::Uv => HAS_UVS,
+            Attribute::Color => HAS_COLORS,
+        }
+    }
+
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MeshError {
+    Truncated,
+    BadMagic,
+    Version(u16),
+    UnknownFlags(u16),
+    TrailingBytes,
+    IndexOutOfRange,
+}
+
+
+// --------------------------------------------------------
+
+
+#[derive(Clone, Copy, Debug)]
+pub struct Mesh {
+    name: &'static str,
+    flags: u16,
+    vertices: usize,
+    indices: usize,
+}
+
+
+impl Mesh {
+
+    pub fn parse(name: &'static str, bytes: &[u8]) -> Result<Self, MeshError> {
+        let header = bytes.get(0..HEADER_SIZE).ok_or(MeshError::Truncated)?;
+        if &header[0..4] != MAGIC { return Err(MeshError::BadMagic); }
+        let version = u16::from_le_bytes([header[4], header[5]]);
+        if version != VERSION { return Err(MeshError::Version(version)); }
+        let flags = u16::from_le_bytes([header[6], header[7]]);
+        if flags & !(HAS_NORMALS | HAS_UVS | HAS_COLORS | WIDE_INDICES) != 0 {
+            return Err(MeshError::UnknownFlags(flags));
+        }
+        let word = |at: usize| u32::from_le_bytes([
+            header[at], header[at + 1], header[at + 2], header[at + 3]
+        ]) as usize;
+        let mesh = Self { name, flags, vertices: word(8), indices: word(12) };
+
+        let len = mesh.index_offset()
+            .checked_add(mesh.indices.checked_mul(mesh.index_size()).ok_or(MeshError::Truncated)?)
+            .ok_or(MeshError::Truncated)?;
+        if bytes.len() < len { return Err(MeshError::Truncated); }
+        if bytes.len() > len { return Err(MeshError::TrailingBytes); }
+
+        let indices = &bytes[mesh.index_offset()..];
+        let out_of_range = indices.chunks_exact(mesh.index_size()).any(|index| {
+            let index = match index {
+                [a, b] => u16::from_le_bytes([*a, *b]) as usize,
+                [a, b, c, d] => u32::from_le_bytes([*a, *b, *c, *d]) as usize,
+                _ => unreachable!(),
+            };
+            index >= mesh.vertices
+        });
+        if out_of_range { return Err(MeshError::IndexOutOfRange); }
+
+        Ok(mesh)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.vertices
+    }
+
+    pub fn indices(&self) -> usize {
+        self.indices
+    }
+
+    pub fn has(&self, attribute: Attribute) -> bool {
+        self.flags & attribute.flag() == attribute.flag()
+    }
+
+    // bytes per index, 2 or 4
+    pub fn index_size(&self) -> usize {
+        if self.flags & WIDE_INDICES != 0 { 4 } else { 2 }
+    }
+
+    // where the stream of `attribute` starts, None if the mesh doesn't have it
+    pub fn offset(&self, attribute: Attribute) -> Option<usize> {
+        if !self.has(attribute) { return None; }
+        let before = Attribute::ALL.iter()
+            .take_while(|a| **a != attribute)
+            .filter(|a| self.has(**a))
+            .map(|a| a.stride() * self.vertices)
+            .sum::<usize>();
+        Some(HEADER_SIZE + before)
+    }
+
+    pub fn index_offset(&self) -> usize {
+        Attribute::ALL.iter()
+            .filter(|a| self.has(**a))
+            .fold(HEADER_SIZE, |offset, a| offset.saturating_add(a.stride().saturating_mul(self.vertices)))
+    }
+
+    // the stream of `attribute` as a buffer to bind to a shader attribute
+    pub fn buffer(&self, attribute: Attribute) -> Option<Buffer> {
+        self.has(attribute).then(|| {
+            Buffer::stream(self.name, attribute.name(), attribute.size(), self.vertices)
+        })
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a triangle with colors and one index too many
+    fn bytes() -> Vec<u8> {
+        let mut bytes = b"NMSH".to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&HAS_COLORS.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 3 * 12]);
+        bytes.extend_from_slice(&[255; 3 * 4]);
+        for index in [0u16, 1, 2, 0] {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn streams_follow_the_header() {
+        let mesh = Mesh::parse("triangle", &bytes()).unwrap();
+        assert_eq!((mesh.vertices(), mesh.indices(), mesh.index_size()), (3, 4, 2));
+        assert_eq!(mesh.offset(Attribute::Position), Some(16));
+        assert_eq!(mesh.offset(Attribute::Normal), None);
+        assert_eq!(mesh.offset(Attribute::Color), Some(16 + 36));
+        assert_eq!(mesh.index_offset(), 16 + 36 + 12);
+        assert!(mesh.buffer(Attribute::Uv).is_none());
+        assert_eq!(mesh.buffer(Attribute::Color).unwrap().vertices(), 3);
+    }
+
+    #[test]
+    fn broken_meshes_are_rejected() {
+        let mut bytes = bytes();
+        assert_eq!(Mesh::parse("m", &bytes[0..10]).unwrap_err(), MeshError::Truncated);
+        assert_eq!(Mesh::parse("m", &bytes[0..bytes.len() - 1]).unwrap_err(), MeshError::Truncated);
+
+        bytes.push(0);
+        assert_eq!(Mesh::parse("m", &bytes).unwrap_err(), MeshError::TrailingBytes);
+        bytes.pop();
+
+        let last = bytes.len() - 2;
+        bytes[last] = 3;
+        assert_eq!(Mesh::parse("m", &bytes).unwrap_err(), MeshError::IndexOutOfRange);
+
+        bytes[6] = 1 << 5;
+        assert_eq!(Mesh::parse("m", &bytes).unwrap_err(), MeshError::UnknownFlags(1 << 5));
+        bytes[4] = 9;
+        assert_eq!(Mesh::parse("m", &bytes).unwrap_err(), MeshError::Version(9));
+        bytes[0] = b'X';
+        assert_eq!(Mesh::parse("m", &bytes).unwrap_err(), MeshError::BadMagic);
+    }
+}
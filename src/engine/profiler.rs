@@ -0,0 +1,254 @@
+use crate::engine::ecs::Stage;
+use crate::platform;
+
+
+
+// Where the time of a frame goes. Every stage of the schedule is a scope of
+// its own, `Frame` covers all of `Engine::render`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scope {
+    Frame,
+    Input,
+    Ai,
+    Physics,
+    Collision,
+    Events,
+    RenderUpload,
+}
+
+
+impl Scope {
+    pub const COUNT: usize = 7;
+}
+
+
+impl From<Stage> for Scope {
+    fn from(stage: Stage) -> Self {
+        match stage {
+            Stage::Input => Scope::Input,
+            Stage::Ai => Scope::Ai,
+            Stage::Physics => Scope::Physics,
+            Stage::Collision => Scope::Collision,
+            Stage::Events => Scope::Events,
+            Stage::RenderUpload => Scope::RenderUpload,
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Counter {
+    CollisionTests,
+    BodiesIntegrated,
+    // words written to the frame buffer, and how many fit
+    BufferWords,
+    BufferCapacity,
+    Commands,
+    DroppedCommands,
+}
+
+
+impl Counter {
+    pub const COUNT: usize = 6;
+}
+
+
+// A running scope, handed back to `Profiler::end` when it is done
+#[must_use]
+pub struct Span {
+    scope: Scope,
+    start: f64,
+}
+
+
+// --------------------------------------------------------
+
+
+// Layout read by JS, every field is one u32 word:
+//
+// header: frames in the ring, words per frame, frames recorded so far
+// frame:  clock frame, milliseconds spent in every `Scope` as f32 bit
+//         patterns, then every `Counter`
+//
+// The last recorded frame is at `(recorded - 1) % frames`.
+
+pub const HISTORY: usize = 120;
+
+pub const HEADER_WORDS: usize = 3;
+
+pub const RECORD_WORDS: usize = 1 + Scope::COUNT + Counter::COUNT;
+
+const PROFILER_SIZE: usize = HEADER_WORDS + HISTORY * RECORD_WORDS;
+
+
+#[allow(dead_code)]
+#[link_section = "PROFILER_SIZE"]
+pub static PSIZE: u32 = PROFILER_SIZE as u32;
+
+
+// Scoped timers on the host's high resolution clock and per frame counters,
+// kept for the last `HISTORY` frames so a dev overlay can chart them.
+pub struct Profiler {
+    pub enabled: bool,
+    times: [f64; Scope::COUNT],
+    counters: [u32; Counter::COUNT],
+    ring: [u32; PROFILER_SIZE],
+    recorded: u32,
+}
+
+
+impl Profiler {
+
+    pub fn new() -> Self {
+        let mut ring = [0; PROFILER_SIZE];
+        ring[0] = HISTORY as u32;
+        ring[1] = RECORD_WORDS as u32;
+        Self {
+            enabled: true,
+            times: [0.0; Scope::COUNT],
+            counters: [0; Counter::COUNT],
+            ring,
+            recorded: 0,
+        }
+    }
+
+    pub fn as_ptr(&self) -> *const u32 {
+        self.ring.as_ptr()
+    }
+
+    pub fn begin(&self, scope: Scope) -> Span {
+        let start = if self.enabled { platform::with(|p| p.now()) } else { 0.0 };
+        Span { scope, start }
+    }
+
+    // spans of the same scope add up over the frame
+    pub fn end(&mut self, span: Span) {
+        if !self.enabled { return; }
+        let elapsed = platform::with(|p| p.now()) - span.start;
+        self.times[span.scope as usize] += elapsed.max(0.0);
+    }
+
+    pub fn count(&mut self, counter: Counter, amount: usize) {
+        let value = &mut self.counters[counter as usize];
+        *value = value.saturating_add(amount as u32);
+    }
+
+    pub fn set(&mut self, counter: Counter, value: usize) {
+        self.counters[counter as usize] = value as u32;
+    }
+
+    // milliseconds spent in `scope` so far this frame
+    pub fn time(&self, scope: Scope) -> f64 {
+        self.times[scope as usize]
+    }
+
+    pub fn counter(&self, counter: Counter) -> u32 {
+        self.counters[counter as usize]
+    }
+
+    // stores the frame in the ring and starts the next one from zero
+    pub fn finish_frame(&mut self, frame: u64) {
+        if self.enabled {
+            let slot = self.recorded as usize % HISTORY;
+            let record = &mut self.ring[HEADER_WORDS + slot * RECORD_WORDS..][..RECORD_WORDS];
+            record[0] = frame as u32;
+            for (word, time) in record[1..].iter_mut().zip(self.times) {
+                *word = (time as f32).to_bits();
+            }
+            record[1 + Scope::COUNT..].copy_from_slice(&self.counters);
+            self.recorded = self.recorded.wrapping_add(1);
+            self.ring[2] = self.recorded;
+        }
+        self.times = [0.0; Scope::COUNT];
+        self.counters = [0; Counter::COUNT];
+    }
+
+    pub fn recorded(&self) -> u32 {
+        self.recorded
+    }
+
+    // an earlier frame, 0 is the last one recorded
+    pub fn record(&self, ago: usize) -> Option<Record<'_>> {
+        if ago >= HISTORY || ago >= self.recorded as usize { return None; }
+        let slot = (self.recorded as usize - 1 - ago) % HISTORY;
+        let words = &self.ring[HEADER_WORDS + slot * RECORD_WORDS..][..RECORD_WORDS];
+        Some(Record { words })
+    }
+
+}
+
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+// one frame in the ring, read the way JS reads it
+pub struct Record<'a> {
+    words: &'a [u32],
+}
+
+
+impl Record<'_> {
+
+    pub fn frame(&self) -> u32 {
+        self.words[0]
+    }
+
+    pub fn time(&self, scope: Scope) -> f32 {
+        f32::from_bits(self.words[1 + scope as usize])
+    }
+
+    pub fn counter(&self, counter: Counter) -> u32 {
+        self.words[1 + Scope::COUNT + counter as usize]
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::HeadlessPlatform;
+
+    #[test]
+    fn spans_and_counters_end_up_in_the_ring() {
+        let platform = HeadlessPlatform::install();
+        platform.set_clock_step(0.5);
+        let mut profiler = Profiler::new();
+
+        for frame in 0..(HISTORY as u64 + 5) {
+            let span = profiler.begin(Scope::Physics);
+            profiler.end(span);
+            let span = profiler.begin(Scope::Physics);
+            profiler.end(span);
+            profiler.count(Counter::CollisionTests, 3);
+            profiler.count(Counter::CollisionTests, frame as usize);
+            profiler.set(Counter::BufferCapacity, 4000);
+            profiler.finish_frame(frame);
+        }
+        assert_eq!(profiler.time(Scope::Physics), 0.0);
+
+        let last = profiler.record(0).unwrap();
+        assert_eq!(last.frame(), HISTORY as u32 + 4);
+        assert_eq!(last.time(Scope::Physics), 1.0);
+        assert_eq!(last.time(Scope::Collision), 0.0);
+        assert_eq!(last.counter(Counter::CollisionTests), 3 + HISTORY as u32 + 4);
+        assert_eq!(last.counter(Counter::BufferCapacity), 4000);
+
+        // the oldest frames were overwritten
+        assert_eq!(profiler.record(HISTORY - 1).unwrap().frame(), 5);
+        assert!(profiler.record(HISTORY).is_none());
+        assert_eq!(profiler.ring[0..3], [HISTORY as u32, RECORD_WORDS as u32, HISTORY as u32 + 5]);
+
+        // while disabled nothing is timed or recorded
+        profiler.enabled = false;
+        let span = profiler.begin(Scope::Frame);
+        profiler.end(span);
+        profiler.finish_frame(0);
+        assert_eq!(profiler.recorded(), HISTORY as u32 + 5);
+    }
+}
@@ -0,0 +1,500 @@
+use core::fmt::{ self, Write };
+
+use crate::engine::Mesh;
+
+
+
+const MAX_ATTRIBUTES: usize = 4;
+const MAX_UNIFORM_BLOCKS: usize = 2;
+const MAX_UNIFORMS: usize = 4;
+const WIRE_SIZE: usize = 512;
+
+
+
+// Handle to a shader sent to JS by `add_shader!`. Keeps the sources around
+// so descriptors can be checked against what the shader declares.
+#[derive(Clone, Copy, Debug)]
+pub struct Shader {
+    name: &'static str,
+    vert: &'static str,
+    frag: &'static str,
+}
+
+
+impl Shader {
+
+    pub fn new(name: &'static str, vert: &'static str, frag: &'static str) -> Self {
+        Self { name, vert, frag }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // names of the `in` variables of the vertex shader, declared one per
+    // line with an optional `layout(...)` qualifier
+    fn attributes(&self) -> impl Iterator<Item = &'static str> {
+        self.vert.lines().filter_map(|line| {
+            let line = line.split("//").next()?.trim();
+            let line = match line.strip_prefix("layout") {
+                Some(rest) => rest.trim_start().strip_prefix('(')?.split_once(')')?.1.trim_start(),
+                None => line,
+            };
+            let line = line.strip_prefix("in")?;
+            if !line.starts_with(char::is_whitespace) { return None; }
+            line.split(';').next()?.split_whitespace().last()
+        })
+    }
+
+    // body of `uniform <name> { ... };` from either stage
+    fn uniform_block(&self, name: &str) -> Option<&'static str> {
+        [self.vert, self.frag].into_iter()
+            .flat_map(|source| source.split("uniform ").skip(1))
+            .find(|rest| rest.split_whitespace().next() == Some(name))
+            .and_then(|rest| {
+                let start = rest.find('{')? + 1;
+                let end = rest.find('}')?;
+                rest.get(start..end)
+            })
+    }
+
+}
+
+
+// Handle to a vertex buffer sent to JS by `add_buffer!`, or one of the
+// streams of a mesh sent by `add_mesh!`. `size` is the number of components
+// per vertex.
+#[derive(Clone, Copy, Debug)]
+pub struct Buffer {
+    name: &'static str,
+    stream: Option<&'static str>,
+    size: usize,
+    len: usize,
+}
+
+
+impl Buffer {
+
+    // `data` is the JSON array the buffer is loaded from
+    pub fn new(name: &'static str, size: usize, data: &'static str) -> Self {
+        let len = data
+            .split(|c: char| c == ',' || c == '[' || c == ']' || c.is_whitespace())
+            .filter(|value| !value.is_empty())
+            .count();
+        Self { name, stream: None, size, len }
+    }
+
+    // a stream of the mesh `name`, JS knows it as "<name>.<stream>"
+    pub fn stream(
+        name: &'static str, stream: &'static str, size: usize, vertices: usize
+    ) -> Self {
+        Self { name, stream: Some(stream), size, len: size * vertices }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn write_name(&self, out: &mut impl Write) -> fmt::Result {
+        out.write_str(self.name)?;
+        match self.stream {
+            Some(stream) => write!(out, ".{}", stream),
+            None => Ok(()),
+        }
+    }
+
+    pub fn vertices(&self) -> usize {
+        self.len / self.size
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DescriptorError {
+    NoVertices,
+    UnknownAttribute(&'static str),
+    UnboundAttribute(&'static str),
+    BufferTooShort(&'static str),
+    UnknownUniformBlock(&'static str),
+    UnknownUniform(&'static str),
+    TooMany,
+}
+
+
+#[derive(Clone, Copy, Debug)]
+struct UniformBlock {
+    name: &'static str,
+    len: usize,
+    uniforms: [&'static str; MAX_UNIFORMS],
+}
+
+
+// Everything JS needs to create a webgl entity: which shader draws it, which
+// buffers feed its attributes, the uniform blocks it updates and how many
+// vertices to draw. Entities with the indices of a mesh are drawn with
+// `drawElements`, and `count` is then the number of indices. Uniforms are
+// addressed by block and position in the order they are added here.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderDescriptor {
+    shader: Shader,
+    count: usize,
+    indices: Option<Mesh>,
+    nattributes: usize,
+    attributes: [(&'static str, Option<Buffer>); MAX_ATTRIBUTES],
+    nblocks: usize,
+    blocks: [UniformBlock; MAX_UNIFORM_BLOCKS],
+}
+
+
+pub struct RenderDescriptorBuilder {
+    descriptor: RenderDescriptor,
+    error: Option<DescriptorError>,
+}
+
+
+impl RenderDescriptor {
+
+    pub fn builder(shader: Shader) -> RenderDescriptorBuilder {
+        RenderDescriptorBuilder {
+            descriptor: Self {
+                shader,
+                count: 0,
+                indices: None,
+                nattributes: 0,
+                attributes: [("", None); MAX_ATTRIBUTES],
+                nblocks: 0,
+                blocks: [UniformBlock {
+                    name: "", len: 0, uniforms: [""; MAX_UNIFORMS]
+                }; MAX_UNIFORM_BLOCKS],
+            },
+            error: None,
+        }
+    }
+
+    pub fn shader(&self) -> Shader {
+        self.shader
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn indices(&self) -> Option<Mesh> {
+        self.indices
+    }
+
+
+    // JSON read by `js_add_entity`
+    pub fn serialize(&self, out: &mut impl Write) -> fmt::Result {
+        write!(out, r#"{{"shader":"{}","count":{},"#, self.shader.name, self.count)?;
+        if let Some(mesh) = self.indices {
+            write!(out, r#""indices":"{}","#, mesh.name())?;
+        }
+        out.write_str(r#""attributes":{"#)?;
+        for (i, (name, buffer)) in self.attributes[0..self.nattributes].iter().enumerate() {
+            if i > 0 { out.write_char(',')?; }
+            write!(out, r#""{}":""#, name)?;
+            if let Some(buffer) = buffer {
+                buffer.write_name(out)?;
+            }
+            out.write_char('"')?;
+        }
+        out.write_str(r#"},"uniforms":{"#)?;
+        for (i, block) in self.blocks[0..self.nblocks].iter().enumerate() {
+            if i > 0 { out.write_char(',')?; }
+            write!(out, r#""{}":["#, block.name)?;
+            for (j, uniform) in block.uniforms[0..block.len].iter().enumerate() {
+                if j > 0 { out.write_char(',')?; }
+                write!(out, r#""{}""#, uniform)?;
+            }
+            out.write_char(']')?;
+        }
+        out.write_str("}}")
+    }
+
+    pub fn to_wire(&self) -> WireBuffer {
+        let mut wire = WireBuffer { bytes: [0; WIRE_SIZE], len: 0 };
+        self.serialize(&mut wire).expect("render descriptor too long");
+        wire
+    }
+
+}
+
+
+impl RenderDescriptorBuilder {
+
+    pub fn count(mut self, count: usize) -> Self {
+        self.descriptor.count = count;
+        self
+    }
+
+    // draws the triangles of `mesh` by index, `count` becomes its number of
+    // indices
+    pub fn indices(mut self, mesh: Mesh) -> Self {
+        self.descriptor.indices = Some(mesh);
+        self.descriptor.count = mesh.indices();
+        self
+    }
+
+    pub fn attribute(mut self, name: &'static str, buffer: Buffer) -> Self {
+        let d = &mut self.descriptor;
+        if d.nattributes == MAX_ATTRIBUTES {
+            self.error.get_or_insert(DescriptorError::TooMany);
+        } else {
+            d.attributes[d.nattributes] = (name, Some(buffer));
+            d.nattributes += 1;
+        }
+        self
+    }
+
+    pub fn uniform_block(
+        mut self, name: &'static str, uniforms: &[&'static str]
+    ) -> Self {
+        let d = &mut self.descriptor;
+        if d.nblocks == MAX_UNIFORM_BLOCKS || uniforms.len() > MAX_UNIFORMS {
+            self.error.get_or_insert(DescriptorError::TooMany);
+        } else {
+            let block = &mut d.blocks[d.nblocks];
+            block.name = name;
+            block.len = uniforms.len();
+            block.uniforms[0..uniforms.len()].copy_from_slice(uniforms);
+            d.nblocks += 1;
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<RenderDescriptor, DescriptorError> {
+        if let Some(error) = self.error { return Err(error); }
+        let d = self.descriptor;
+        if d.count == 0 { return Err(DescriptorError::NoVertices); }
+
+        // indices can point at any vertex of their mesh
+        let vertices = match d.indices {
+            Some(mesh) if d.count > mesh.indices() => {
+                return Err(DescriptorError::BufferTooShort("indices"));
+            },
+            Some(mesh) => mesh.vertices(),
+            None => d.count,
+        };
+
+        let attributes = &d.attributes[0..d.nattributes];
+        for (name, buffer) in attributes {
+            if !d.shader.attributes().any(|a| a == *name) {
+                return Err(DescriptorError::UnknownAttribute(name));
+            }
+            if buffer.map_or(0, |b| b.vertices()) < vertices {
+                return Err(DescriptorError::BufferTooShort(name));
+            }
+        }
+        if let Some(name) = d.shader.attributes()
+            .find(|a| !attributes.iter().any(|(name, _)| name == a)) {
+            return Err(DescriptorError::UnboundAttribute(name));
+        }
+
+        for block in d.blocks[0..d.nblocks].iter() {
+            let body = d.shader.uniform_block(block.name)
+                .ok_or(DescriptorError::UnknownUniformBlock(block.name))?;
+            for uniform in block.uniforms[0..block.len].iter() {
+                let declared = body.split(';').any(|declaration| {
+                    declaration.split_whitespace().last() == Some(uniform)
+                });
+                if !declared {
+                    return Err(DescriptorError::UnknownUniform(uniform));
+                }
+            }
+        }
+
+        Ok(d)
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+pub struct WireBuffer {
+    bytes: [u8; WIRE_SIZE],
+    len: usize,
+}
+
+
+impl WireBuffer {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[0..self.len]
+    }
+}
+
+
+impl Write for WireBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > WIRE_SIZE { return Err(fmt::Error); }
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::mesh::Attribute;
+
+    const VERT: &str = "#version 300 es
+
+in vec4 a_position;
+in vec3 a_color;
+out vec3 v_color;
+
+uniform objectData {
+    mat4 u_matrix;
+    float u_collide;
+};
+
+void main() {}
+";
+
+    fn shader() -> Shader {
+        Shader::new("vertex_color", VERT, "in vec3 v_color;")
+    }
+
+    fn buffer(name: &'static str) -> Buffer {
+        Buffer::new(name, 3, "[\n 1, 2, 3,\n 4, 5, 6,\n\n 7, 8, 9\n]")
+    }
+
+    fn builder() -> RenderDescriptorBuilder {
+        RenderDescriptor::builder(shader())
+            .count(3)
+            .attribute("a_position", buffer("positions"))
+            .attribute("a_color", buffer("colors"))
+    }
+
+    #[test]
+    fn reads_qualified_and_commented_attributes() {
+        let shader = Shader::new("layout", "#version 300 es
+layout(location = 0) in vec3 a_position;
+layout (location=1)in vec3 a_normal;
+in vec3 a_color; // per vertex
+// in vec2 a_uv;
+int count;
+", "");
+        let attributes: Vec<_> = shader.attributes().collect();
+        assert_eq!(attributes, ["a_position", "a_normal", "a_color"]);
+
+        assert_eq!(
+            RenderDescriptor::builder(shader).count(3)
+                .attribute("a_position", buffer("positions"))
+                .attribute("a_normal", buffer("normals"))
+                .build().err(),
+            Some(DescriptorError::UnboundAttribute("a_color"))
+        );
+    }
+
+    #[test]
+    fn buffer_counts_vertices() {
+        assert_eq!(buffer("b").vertices(), 3);
+    }
+
+    #[test]
+    fn serializes_to_wire_format() {
+        let descriptor = builder()
+            .uniform_block("objectData", &["u_matrix", "u_collide"])
+            .build().unwrap();
+        let wire = descriptor.to_wire();
+        assert_eq!(
+            core::str::from_utf8(wire.as_bytes()).unwrap(),
+            concat!(
+                r#"{"shader":"vertex_color","count":3,"#,
+                r#""attributes":{"a_position":"positions","a_color":"colors"},"#,
+                r#""uniforms":{"objectData":["u_matrix","u_collide"]}}"#,
+            )
+        );
+    }
+
+    #[test]
+    fn indexed_descriptors_draw_mesh_streams() {
+        // 4 vertices with colors, 6 indices
+        let mut bytes = b"NMSH".to_vec();
+        for word in [1u16, 1 << 2] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        for word in [4u32, 6] {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0; 4 * 16]);
+        for index in [0u16, 1, 2, 0, 2, 3] {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        let mesh = Mesh::parse("quad", &bytes).unwrap();
+        let color = mesh.buffer(Attribute::Color).unwrap();
+
+        let descriptor = RenderDescriptor::builder(shader())
+            .indices(mesh)
+            .attribute("a_position", mesh.buffer(Attribute::Position).unwrap())
+            .attribute("a_color", color)
+            .build().unwrap();
+        assert_eq!(descriptor.count(), 6);
+        let wire = descriptor.to_wire();
+        assert_eq!(
+            core::str::from_utf8(wire.as_bytes()).unwrap(),
+            concat!(
+                r#"{"shader":"vertex_color","count":6,"indices":"quad","#,
+                r#""attributes":{"a_position":"quad.position","a_color":"quad.color"},"#,
+                r#""uniforms":{}}"#,
+            )
+        );
+
+        // the buffers have to cover every vertex, not every index
+        assert_eq!(
+            RenderDescriptor::builder(shader()).indices(mesh)
+                .attribute("a_position", buffer("positions"))
+                .attribute("a_color", color)
+                .build().err(),
+            Some(DescriptorError::BufferTooShort("a_position"))
+        );
+        assert_eq!(
+            RenderDescriptor::builder(shader()).indices(mesh).count(7)
+                .attribute("a_position", color)
+                .attribute("a_color", color)
+                .build().err(),
+            Some(DescriptorError::BufferTooShort("indices"))
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_descriptors() {
+        assert_eq!(
+            builder().count(0).build().err(),
+            Some(DescriptorError::NoVertices)
+        );
+        assert_eq!(
+            builder().count(4).build().err(),
+            Some(DescriptorError::BufferTooShort("a_position"))
+        );
+        assert_eq!(
+            builder().attribute("a_normal", buffer("normals")).build().err(),
+            Some(DescriptorError::UnknownAttribute("a_normal"))
+        );
+        assert_eq!(
+            RenderDescriptor::builder(shader()).count(3)
+                .attribute("a_position", buffer("positions"))
+                .build().err(),
+            Some(DescriptorError::UnboundAttribute("a_color"))
+        );
+        assert_eq!(
+            builder().uniform_block("frameData", &["u_matrix"]).build().err(),
+            Some(DescriptorError::UnknownUniformBlock("frameData"))
+        );
+        assert_eq!(
+            builder().uniform_block("objectData", &["u_color"]).build().err(),
+            Some(DescriptorError::UnknownUniform("u_color"))
+        );
+    }
+}
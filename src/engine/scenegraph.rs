@@ -1,59 +1,53 @@
-use core::cell::Cell;
-
 use crate::math::{ Matrix4 };
-use crate::engine::{ Arena, MemoryBuffer };
-use crate::engine::entity::{ Entity, EntityBehavior };
-
-
+use crate::engine::{ Arena, ArenaID, Frame };
+use crate::engine::viewport::DEFAULT_LAYER;
+use crate::utils;
 
-const NOBJECTS: usize = 100;
-const NCHILDREN: usize = 10;
 
 
+const NNODES: usize = 256;
 
-#[derive(Default, Clone, Copy)]
-pub struct NodeID(usize);
-
-impl From<usize> for NodeID {
-    fn from(value: usize) -> Self { Self(value) }
-}
 
-impl From<NodeID> for usize {
-    fn from(value: NodeID) -> usize { value.0 }
-}
 
-impl From<NodeID> for f32 {
-    fn from(value: NodeID) -> f32 { value.0 as f32 }
-}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NodeID(ArenaID);
 
 
 // --------------------------------------------------------
 
 
+// Children are kept as an intrusive linked list through `first_child` and
+// `next_sibling`.
 struct Node {
-    entity: Cell<Entity>,
-    // children: Arena<NodeID, NCHILDREN>,
-    children: Vec<NodeID>,
+    visible: bool,
+    // visibility JS was last told about, entities start out drawn
+    shown: bool,
+    // views draw the entity if their mask shares a bit with these, `sent`
+    // is what JS was last told
+    layers: u32,
+    sent_layers: u32,
+    entity: Option<usize>,
+    parent: Option<NodeID>,
+    first_child: Option<NodeID>,
+    next_sibling: Option<NodeID>,
+    local: Matrix4,
+    world: Matrix4,
 }
 
 
 impl Default for Node {
     fn default() -> Self {
         Self {
-            entity: Cell::new(Entity::default()),
-            // children: Arena::empty(),
-            children: Vec::with_capacity(NCHILDREN),
-        }
-    }
-}
-
-
-impl Node {
-    fn new(entity: Entity) -> Self {
-        Self {
-            entity: Cell::new(entity),
-            // children: Arena::empty()
-            children: Vec::with_capacity(NCHILDREN),
+            visible: true,
+            shown: true,
+            layers: DEFAULT_LAYER,
+            sent_layers: DEFAULT_LAYER,
+            entity: None,
+            parent: None,
+            first_child: None,
+            next_sibling: None,
+            local: Matrix4::identity(),
+            world: Matrix4::identity(),
         }
     }
 }
@@ -63,100 +57,366 @@ impl Node {
 
 
 pub struct Scenegraph {
-    // nodes: Arena<Node, NOBJECTS>,
-    nodes: Vec<Node>,
-    loaded: u16,
+    root: NodeID,
+    nodes: Arena<Node, NNODES>,
 }
 
 
 impl Scenegraph {
 
     pub fn empty() -> Self {
-        Self {
-            // nodes: Arena::empty(),
-            nodes: Vec::with_capacity(NOBJECTS),
-            loaded: 0,
-        }
+        let mut nodes = Arena::empty();
+        let root = NodeID(nodes.add(Node::default()));
+        Self { root, nodes }
     }
 
 
-    pub fn root(&mut self) -> NodeID {
-        if self.nodes.len() == 0 {
-            // self.nodes.add(Node::default());
-            self.nodes.push(Node::default());
+    pub fn root(&self) -> NodeID {
+        self.root
+    }
+
+
+    // `entity` is the webgl entity id the world matrix of this node is sent
+    // to, nodes without one only group and transform their children
+    pub fn add(&mut self, parent: NodeID, entity: Option<usize>) -> NodeID {
+        assert!(self.contains(parent));
+
+        let node = Node {
+            entity,
+            parent: Some(parent),
+            next_sibling: self.node(parent).first_child,
+            ..Default::default()
+        };
+
+        let id = NodeID(self.nodes.add(node));
+
+        self.node_mut(parent).first_child = Some(id);
+        id
+    }
+
+
+    // removes the node along with all of its children, destroying their
+    // webgl entities
+    pub fn remove(&mut self, id: NodeID) {
+        assert!(id != self.root(), "the root node can't be removed");
+        assert!(self.contains(id));
+        self.detach(id);
+        self.release(id);
+    }
+
+    fn release(&mut self, id: NodeID) {
+        let mut child = self.node(id).first_child;
+        while let Some(c) = child {
+            child = self.node(c).next_sibling;
+            self.release(c);
+        }
+        if let Some(node) = self.nodes.remove(id.0) {
+            if let Some(entity) = node.entity {
+                utils::webgl_destroy_entity(entity);
+            }
         }
-        NodeID::from(0)
     }
 
 
-    pub fn add_entity(&mut self, parent: NodeID, entity: Entity) -> NodeID {
+    // moves the node and its children under `parent`, the local transform is
+    // kept so the world transform now follows the new parent
+    pub fn reparent(&mut self, id: NodeID, parent: NodeID) {
+        assert!(id != self.root(), "the root node can't be reparented");
+        assert!(self.contains(id) && self.contains(parent));
 
-        let meta = entity.shader_metadata();
-        // let id = NodeID::from(self.nodes.add(Node::new(entity)));
-        // self.nodes[parent.0].children.add(id);
-        self.nodes.push(Node::new(entity));
-        let id = NodeID::from(self.nodes.len() - 1);
-        self.nodes[parent.0].children.push(id);
+        let mut ancestor = Some(parent);
+        while let Some(a) = ancestor {
+            assert!(a != id, "a node can't be reparented under itself");
+            ancestor = self.node(a).parent;
+        }
+
+        self.detach(id);
+        let next_sibling = self.node(parent).first_child;
+        let node = self.node_mut(id);
+        node.parent = Some(parent);
+        node.next_sibling = next_sibling;
+        self.node_mut(parent).first_child = Some(id);
+    }
 
-        if let Some(m) = meta {
-            unsafe { crate::js_add_object(id.0, m.as_ptr(), m.len()) }
+    fn detach(&mut self, id: NodeID) {
+        let parent = self.node(id).parent.expect("node has no parent");
+        let next_sibling = self.node(id).next_sibling;
+
+        if self.node(parent).first_child == Some(id) {
+            self.node_mut(parent).first_child = next_sibling;
+        } else {
+            let mut child = self.node(parent).first_child;
+            while let Some(c) = child {
+                if self.node(c).next_sibling == Some(id) {
+                    self.node_mut(c).next_sibling = next_sibling;
+                    break;
+                }
+                child = self.node(c).next_sibling;
+            }
         }
 
-        id
+        let node = self.node_mut(id);
+        node.parent = None;
+        node.next_sibling = None;
     }
 
 
-    pub fn load(&mut self, id: NodeID) -> Entity {
-        self.loaded += 1;
-        self.nodes[id.0].entity.take()
+    pub fn contains(&self, id: NodeID) -> bool {
+        self.nodes.contains(id.0)
     }
 
-    pub fn store(&mut self, id: NodeID, entity: Entity) {
-        self.loaded -= 1;
-        self.nodes[id.0].entity.replace(entity);
+    pub fn parent(&self, id: NodeID) -> Option<NodeID> {
+        self.node(id).parent
     }
 
-    pub fn with<T, F>(&self, id: NodeID, mut func: F)
-    where
-        F: FnMut(T) -> T,
-        T: From<Entity> + Into<Entity>
-    {
-        let mut entity = self.nodes[id.0].entity.take();
-        entity = func(entity.into()).into();
-        self.nodes[id.0].entity.replace(entity);
+    pub fn children(&self, id: NodeID) -> impl Iterator<Item = NodeID> + '_ {
+        let mut child = self.node(id).first_child;
+        core::iter::from_fn(move || {
+            let c = child?;
+            child = self.node(c).next_sibling;
+            Some(c)
+        })
     }
 
+    pub fn set_local(&mut self, id: NodeID, matrix: Matrix4) {
+        self.node_mut(id).local = matrix;
+    }
 
-    pub fn recursive_update(
-        &self, id: NodeID, dt: f32,
-        m: Matrix4, pm: Matrix4, b: &mut MemoryBuffer
-    ) {
-        if self.loaded > 0 { panic!("not all loaded Node{{}} returned!"); }
-        self.recursive_update_internal(id, dt, m, pm, b);
+    pub fn local(&self, id: NodeID) -> Matrix4 {
+        self.node(id).local
     }
 
-    fn recursive_update_internal(
-        &self, nodeid: NodeID, dt: f32,
-        mut matrix: Matrix4, projection_matrix: Matrix4,
-        buffer: &mut MemoryBuffer
-    ) {
+    // hidden nodes and their children are not drawn and don't get matrices
+    pub fn set_visible(&mut self, id: NodeID, visible: bool) {
+        self.node_mut(id).visible = visible;
+    }
 
-        let node = &self.nodes[nodeid.0];
-        let mut entity = node.entity.take();
 
-        matrix = entity.update_matrix(dt, matrix);
+    // layers of the node's own entity, children keep theirs
+    pub fn set_layers(&mut self, id: NodeID, layers: u32) {
+        self.node_mut(id).layers = layers;
+    }
 
-        if entity.shader_metadata().is_some() {
-            let view_matrix = projection_matrix * matrix;
-            buffer.add_matrix(f32::from(nodeid), 0.0, 0.0, &view_matrix);
+    pub fn layers(&self, id: NodeID) -> u32 {
+        self.node(id).layers
+    }
+
+
+    // world transform from the current local transforms of the node and all
+    // of its ancestors, usable before `update` runs for the frame
+    pub fn world(&self, id: NodeID) -> Matrix4 {
+        let node = self.node(id);
+        match node.parent {
+            Some(parent) => self.world(parent) * node.local,
+            None => node.local,
+        }
+    }
+
+
+    // walks the graph from the root, storing world matrices and writing the
+    // world matrix of every visible entity into the frame, along with a
+    // command for every entity that was hidden, shown or changed layers
+    pub fn update(&mut self, frame: &mut Frame) {
+        let root = self.root();
+        self.update_node(root, Matrix4::identity(), true, frame);
+    }
+
+    fn update_node(
+        &mut self, id: NodeID, parent: Matrix4, shown: bool, frame: &mut Frame
+    ) {
+        let node = self.node_mut(id);
+        let shown = shown && node.visible;
+
+        if let Some(entity) = node.entity {
+            // if the command is dropped it is sent again next frame
+            if node.shown != shown && frame.buffer.set_visible(entity, shown) {
+                node.shown = shown;
+            }
+            if node.sent_layers != node.layers && frame.buffer.set_layers(entity, node.layers) {
+                node.sent_layers = node.layers;
+            }
+        }
+        if shown {
+            node.world = parent * node.local;
+            if let Some(entity) = node.entity {
+                frame.add_world_matrix(entity, node.world);
+            }
         }
 
-        node.entity.replace(entity);
+        let world = node.world;
+        let mut child = node.first_child;
+        while let Some(c) = child {
+            child = self.node(c).next_sibling;
+            self.update_node(c, world, shown, frame);
+        }
+    }
+
+
+    fn node(&self, id: NodeID) -> &Node {
+        &self.nodes[id.0]
+    }
+
+    fn node_mut(&mut self, id: NodeID) -> &mut Node {
+        &mut self.nodes[id.0]
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::HeadlessPlatform;
+    use crate::math::Vec3;
+    use crate::engine::memory_buffer::{ decode, Command };
+    use crate::engine::Input;
+
+    fn translation(x: f32, y: f32, z: f32) -> Matrix4 {
+        Matrix4::translation(x, y, z)
+    }
+
+    fn origin(scene: &Scenegraph, id: NodeID) -> Vec3 {
+        scene.world(id) * Vec3::zero()
+    }
+
+    #[test]
+    fn child_follows_parent() {
+        let mut scene = Scenegraph::empty();
+        let parent = scene.add(scene.root(), None);
+        let child = scene.add(parent, None);
+
+        scene.set_local(parent, translation(10.0, 0.0, 0.0));
+        scene.set_local(child, translation(0.0, 0.0, 5.0));
+        assert_eq!(origin(&scene, child), Vec3::new(10.0, 0.0, 5.0));
+
+        let mut rotated = translation(10.0, 0.0, 0.0);
+        rotated.rotate_y(crate::PI / 2.0);
+        scene.set_local(parent, rotated);
+        let position = origin(&scene, child);
+        assert!((position - Vec3::new(15.0, 0.0, 0.0)).len() < 0.0001);
+    }
 
-        // for id in node.children.slice() {
-        for id in node.children.iter() {
-            self.recursive_update(*id, dt, matrix, projection_matrix, buffer);
+    #[test]
+    fn reparent_keeps_local_transform() {
+        let mut scene = Scenegraph::empty();
+        let a = scene.add(scene.root(), None);
+        let b = scene.add(scene.root(), None);
+        let child = scene.add(a, None);
+        scene.set_local(a, translation(1.0, 0.0, 0.0));
+        scene.set_local(b, translation(0.0, 0.0, 7.0));
+        scene.set_local(child, translation(0.0, 2.0, 0.0));
+
+        scene.reparent(child, b);
+        assert_eq!(scene.parent(child), Some(b));
+        assert_eq!(scene.children(a).count(), 0);
+        assert_eq!(scene.children(b).collect::<Vec<_>>(), vec![child]);
+        assert_eq!(origin(&scene, child), Vec3::new(0.0, 2.0, 7.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn reparent_under_descendant_panics() {
+        let mut scene = Scenegraph::empty();
+        let a = scene.add(scene.root(), None);
+        let b = scene.add(a, None);
+        scene.reparent(a, b);
+    }
+
+    #[test]
+    fn remove_takes_children() {
+        let mut scene = Scenegraph::empty();
+        let a = scene.add(scene.root(), None);
+        let b = scene.add(scene.root(), None);
+        let child = scene.add(a, None);
+        let grandchild = scene.add(child, None);
+
+        scene.remove(a);
+        assert!(!scene.contains(a));
+        assert!(!scene.contains(child));
+        assert!(!scene.contains(grandchild));
+        assert!(scene.contains(b));
+        assert_eq!(scene.children(scene.root()).collect::<Vec<_>>(), vec![b]);
+
+        // freed slots are reused without reviving the removed ids
+        for _ in 0..3 {
+            scene.add(b, None);
         }
+        assert_eq!(scene.nodes.len(), 5);
+        assert!(!scene.contains(a));
+        assert_eq!(scene.children(b).count(), 3);
+    }
+
+    #[test]
+    fn remove_destroys_entities() {
+        let platform = HeadlessPlatform::install();
+        let mut scene = Scenegraph::empty();
+        let live = platform.live_entities();
+        let quad = crate::load_assets().quad();
+        let id = || utils::webgl_add_entity(&quad);
+
+        let parent = scene.add(scene.root(), Some(id()));
+        let child = scene.add(parent, Some(id()));
+        scene.add(child, Some(id()));
+        scene.add(child, None);
+        let other = scene.add(scene.root(), Some(id()));
+        assert_eq!(platform.live_entities(), live + 4);
+
+        scene.remove(parent);
+        assert_eq!(platform.live_entities(), live + 1);
+        scene.remove(other);
+        assert_eq!(platform.live_entities(), live);
     }
 
+    #[test]
+    fn update_writes_visible_entities() {
+        let mut scene = Scenegraph::empty();
+        let parent = scene.add(scene.root(), Some(7));
+        let child = scene.add(parent, Some(8));
+        let hidden = scene.add(scene.root(), Some(9));
+        scene.add(hidden, Some(10));
+        scene.set_local(parent, translation(1.0, 2.0, 3.0));
+        scene.set_local(child, translation(1.0, 0.0, 0.0));
+        scene.set_visible(hidden, false);
+
+        let mut frame = Frame::new();
+        let commands = |frame: &mut Frame, scene: &mut Scenegraph| {
+            frame.update(0.0, 0.0, &Input::default(), Matrix4::identity());
+            scene.update(frame);
+            frame.buffer.update_length();
+            let (_, commands) = decode(frame.buffer.words()).unwrap();
+            commands.map(|command| match command {
+                Command::SetUniform { entity, .. } => {
+                    let values: Vec<f32> = command.values().collect();
+                    (entity, Some(values))
+                },
+                Command::SetVisible { entity, visible } => {
+                    assert!(!visible);
+                    (entity, None)
+                },
+                Command::SetLayers { entity, layers } => {
+                    assert_eq!(layers, 0b100);
+                    (entity, None)
+                },
+                Command::SetViewport { .. } => panic!("only the engine sends viewports"),
+            }).collect::<Vec<_>>()
+        };
+
+        let sent = commands(&mut frame, &mut scene);
+        let ids: Vec<u32> = sent.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![9, 10, 7, 8]);
+        let matrix = sent[3].1.as_ref().unwrap();
+        assert_eq!(&matrix[12..15], &[2.0, 2.0, 3.0]);
+
+        // visibility and layers are only sent when they change
+        let ids: Vec<u32> = commands(&mut frame, &mut scene)
+            .iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![7, 8]);
+
+        scene.set_layers(child, 0b100);
+        let ids: Vec<u32> = commands(&mut frame, &mut scene)
+            .iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![7, 8, 8]);
+        assert_eq!(commands(&mut frame, &mut scene).len(), 2);
+    }
 }
@@ -0,0 +1,77 @@
+use core::ops::{ Index, IndexMut };
+
+
+
+// Fixed capacity, stack allocated vector. Items are kept packed from the
+// start of the array so the whole thing can be shared with JS as a pointer.
+pub struct StackVec<T, const N: usize> {
+    current: usize,
+    array: [T; N],
+}
+
+
+impl<T, const N: usize> Index<usize> for StackVec<T, N> {
+    type Output = T;
+    fn index(&self, index: usize) -> &Self::Output {
+        assert!(index < self.current);
+        unsafe { self.array.get_unchecked(index) }
+    }
+}
+
+
+impl<T, const N: usize> IndexMut<usize> for StackVec<T, N> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        assert!(index < self.current);
+        unsafe { self.array.get_unchecked_mut(index) }
+    }
+}
+
+
+impl<T, const N: usize> StackVec<T, N> where T: Default {
+
+    pub fn empty() -> Self {
+        Self {
+            current: 0,
+            array: core::array::from_fn(|_| Default::default()),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.current = 0;
+    }
+
+    pub fn add(&mut self, item: T) -> usize {
+        assert!(self.current < N);
+        unsafe { *self.array.get_unchecked_mut(self.current) = item }
+        self.current += 1;
+        self.current - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.current
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current == 0
+    }
+
+    pub fn as_ptr(&mut self) -> *const T {
+        self.array.as_ptr()
+    }
+
+    pub fn slice(&self) -> &[T] {
+        &self.array[0..self.current]
+    }
+
+    pub fn slice_mut(&mut self) -> &mut [T] {
+        &mut self.array[0..self.current]
+    }
+
+}
+
+
+impl<T, const N: usize> From<[T; N]> for StackVec<T, N> {
+    fn from(array: [T; N]) -> Self {
+        Self { array, current: N }
+    }
+}
@@ -0,0 +1,253 @@
+use crate::engine::{ Arena, ArenaID };
+use crate::engine::ecs::Entity;
+
+
+
+// Something that can only happen once every `delay` seconds, like a gun
+// firing, or that runs out `delay` seconds after it started.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cooldown {
+    pub delay: f32,
+    // game time it is ready again
+    pub ready_at: f32,
+}
+
+
+impl Cooldown {
+
+    // ready right away
+    pub fn new(delay: f32) -> Self {
+        Self { delay, ready_at: f32::NEG_INFINITY }
+    }
+
+    pub fn ready(&self, now: f32) -> bool {
+        now >= self.ready_at
+    }
+
+    pub fn start(&mut self, now: f32) {
+        self.ready_at = now + self.delay;
+    }
+
+    // starts it again if it was ready
+    pub fn trigger(&mut self, now: f32) -> bool {
+        let ready = self.ready(now);
+        if ready { self.start(now); }
+        ready
+    }
+
+    pub fn remaining(&self, now: f32) -> f32 {
+        (self.ready_at - now).max(0.0)
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimerID(ArenaID);
+
+
+struct Timer<E> {
+    due: f32,
+    // repeating timers are pushed back by this every time they fire
+    period: Option<f32>,
+    owner: Option<Entity>,
+    event: E,
+}
+
+
+// Up to `N` pending events that fire after a delay in game seconds, once or
+// repeatedly. Time only moves when `update` is called with the clock's time,
+// so timers stop while the game is paused and follow the time scale.
+//
+// Timers can belong to an entity, they fire with its handle and are
+// cancelled when it is despawned.
+pub struct Timers<E, const N: usize> {
+    now: f32,
+    timers: Arena<Timer<E>, N>,
+}
+
+
+impl<E: Copy + PartialEq, const N: usize> Timers<E, N> {
+
+    pub fn empty() -> Self {
+        Self { now: 0.0, timers: Arena::empty() }
+    }
+
+    // delays count from the time of the last update
+    pub fn now(&self) -> f32 {
+        self.now
+    }
+
+    pub fn after(&mut self, delay: f32, event: E) -> Option<TimerID> {
+        self.add(delay, None, None, event)
+    }
+
+    pub fn every(&mut self, period: f32, event: E) -> Option<TimerID> {
+        self.add(period, Some(period), None, event)
+    }
+
+    pub fn after_for(&mut self, owner: Entity, delay: f32, event: E) -> Option<TimerID> {
+        self.add(delay, None, Some(owner), event)
+    }
+
+    pub fn every_for(&mut self, owner: Entity, period: f32, event: E) -> Option<TimerID> {
+        self.add(period, Some(period), Some(owner), event)
+    }
+
+    // cancels the owner's pending `event` before scheduling it again, so
+    // something that keeps happening pushes the event back instead of
+    // stacking up timers
+    pub fn restart_for(&mut self, owner: Entity, delay: f32, event: E) -> Option<TimerID> {
+        self.cancel_where(|timer| timer.owner == Some(owner) && timer.event == event);
+        self.after_for(owner, delay, event)
+    }
+
+    // None if every timer is taken
+    fn add(
+        &mut self, delay: f32, period: Option<f32>, owner: Option<Entity>, event: E
+    ) -> Option<TimerID> {
+        assert!(period.is_none_or(|period| period > 0.0), "timer period has to be positive");
+        let timer = Timer { due: self.now + delay.max(0.0), period, owner, event };
+        self.timers.try_add(timer).ok().map(TimerID)
+    }
+
+    pub fn cancel(&mut self, id: TimerID) -> bool {
+        self.timers.remove(id.0).is_some()
+    }
+
+    // cancels every timer of the entity and returns how many there were
+    pub fn cancel_entity(&mut self, entity: Entity) -> usize {
+        self.cancel_where(|timer| timer.owner == Some(entity))
+    }
+
+    fn cancel_where<F>(&mut self, func: F) -> usize
+    where F: Fn(&Timer<E>) -> bool {
+        let mut ids = [None; N];
+        let matching = self.timers.iter_with_ids().filter(|(_, timer)| func(timer));
+        for (slot, (id, _)) in ids.iter_mut().zip(matching) {
+            *slot = Some(id);
+        }
+        ids.into_iter().flatten().filter(|id| self.timers.remove(*id).is_some()).count()
+    }
+
+    pub fn is_pending(&self, id: TimerID) -> bool {
+        self.timers.contains(id.0)
+    }
+
+    // game time left until the timer fires
+    pub fn remaining(&self, id: TimerID) -> Option<f32> {
+        self.timers.get(id.0).map(|timer| (timer.due - self.now).max(0.0))
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.timers.reset();
+    }
+
+    // moves time forward to `now` and calls `func` for every event that came
+    // due. Repeating timers fire once for every period that passed.
+    pub fn update<F>(&mut self, now: f32, mut func: F)
+    where F: FnMut(Option<Entity>, E) {
+        self.now = self.now.max(now);
+
+        let mut done = [None; N];
+        let mut ndone = 0;
+        for (id, timer) in self.timers.iter_mut_with_ids() {
+            while timer.due <= self.now {
+                func(timer.owner, timer.event);
+                match timer.period {
+                    Some(period) => timer.due += period,
+                    None => {
+                        done[ndone] = Some(id);
+                        ndone += 1;
+                        break;
+                    },
+                }
+            }
+        }
+        for id in done.into_iter().flatten() {
+            self.timers.remove(id);
+        }
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::ecs::Entities;
+
+    #[derive(Clone, Copy, PartialEq, Debug)]
+    enum Event { Ping, Pong }
+
+    fn fired(timers: &mut Timers<Event, 8>, now: f32) -> Vec<(Option<Entity>, Event)> {
+        let mut fired = Vec::new();
+        timers.update(now, |owner, event| fired.push((owner, event)));
+        fired
+    }
+
+    #[test]
+    fn once_and_repeating() {
+        let mut timers: Timers<Event, 8> = Timers::empty();
+        let once = timers.after(1.0, Event::Ping).unwrap();
+        let every = timers.every(0.5, Event::Pong).unwrap();
+
+        assert_eq!(fired(&mut timers, 0.25), []);
+        assert_eq!(timers.remaining(once), Some(0.75));
+        assert_eq!(fired(&mut timers, 0.5), [(None, Event::Pong)]);
+
+        // a long frame catches up on every period it skipped
+        assert_eq!(
+            fired(&mut timers, 1.6),
+            [(None, Event::Ping), (None, Event::Pong), (None, Event::Pong)]
+        );
+        assert!(!timers.is_pending(once));
+
+        // time doesn't move while paused, so nothing fires
+        assert_eq!(fired(&mut timers, 1.6), []);
+        assert!(timers.cancel(every));
+        assert_eq!(fired(&mut timers, 10.0), []);
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn entity_timers_are_cancelled_with_it() {
+        let mut entities = Entities::empty();
+        let a = entities.create();
+        let b = entities.create();
+        let mut timers: Timers<Event, 8> = Timers::empty();
+
+        timers.after_for(a, 1.0, Event::Ping);
+        timers.every_for(a, 0.25, Event::Pong);
+        timers.after_for(b, 1.0, Event::Ping);
+        timers.restart_for(b, 2.0, Event::Ping);
+        assert_eq!(timers.len(), 3);
+
+        assert_eq!(timers.cancel_entity(a), 2);
+        assert_eq!(fired(&mut timers, 1.5), []);
+        assert_eq!(fired(&mut timers, 2.0), [(Some(b), Event::Ping)]);
+        assert!(timers.is_empty());
+    }
+
+    #[test]
+    fn cooldown() {
+        let mut cooldown = Cooldown::new(0.5);
+        assert!(cooldown.trigger(0.0));
+        assert!(!cooldown.trigger(0.25));
+        assert_eq!(cooldown.remaining(0.25), 0.25);
+        assert!(cooldown.trigger(0.5));
+        assert!(!cooldown.ready(0.9));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::engine::Camera;
+
+
+
+// Entities are drawn by every view whose mask shares a bit with the entity's
+// layers. Everything starts out on the default layer.
+pub const DEFAULT_LAYER: u32 = 1 << 0;
+pub const ALL_LAYERS: u32 = !0;
+
+pub const MAX_VIEWS: usize = 4;
+
+// the first view is the main one, it is there from the start
+pub const MAIN: usize = 0;
+
+
+// Part of the canvas a view draws into, in fractions of the canvas size with
+// the origin at the bottom left like `gl.viewport`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+
+impl Rect {
+
+    pub const FULL: Rect = Rect { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    // aspect ratio of the rect on a canvas of `width` by `height` pixels
+    pub fn aspect(&self, width: f32, height: f32) -> f32 {
+        let h = (self.height * height).max(1.0);
+        (self.width * width).max(1.0) / h
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+#[derive(Clone, Copy)]
+pub struct View {
+    pub camera: Camera,
+    pub rect: Rect,
+    pub mask: u32,
+}
+
+
+impl View {
+    pub fn new(camera: Camera, rect: Rect, mask: u32) -> Self {
+        Self { camera, rect, mask }
+    }
+}
+
+
+// The cameras the scene is drawn with, each into its own part of the canvas.
+// JS draws them in order, so later views are drawn on top of earlier ones.
+pub struct Views {
+    views: [Option<View>; MAX_VIEWS],
+    len: usize,
+    width: f32,
+    height: f32,
+}
+
+
+impl Views {
+
+    // the main view covers the whole canvas and draws every layer
+    pub fn new(camera: Camera, width: f32, height: f32) -> Self {
+        let mut views = Self { views: [None; MAX_VIEWS], len: 0, width, height };
+        views.add(View::new(camera, Rect::FULL, ALL_LAYERS));
+        views
+    }
+
+    // returns the index of the view, None if there is no room left. The
+    // camera's aspect ratio is set to fit the rect.
+    pub fn add(&mut self, mut view: View) -> Option<usize> {
+        if self.len == MAX_VIEWS { return None; }
+        view.camera.set_aspect(view.rect.aspect(self.width, self.height));
+        self.views[self.len] = Some(view);
+        self.len += 1;
+        Some(self.len - 1)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&View> {
+        self.views.get(index)?.as_ref()
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut View> {
+        self.views.get_mut(index)?.as_mut()
+    }
+
+    pub fn main(&self) -> &View {
+        self.get(MAIN).expect("there is always a main view")
+    }
+
+    pub fn main_mut(&mut self) -> &mut View {
+        self.get_mut(MAIN).expect("there is always a main view")
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &View> {
+        self.views[0..self.len].iter().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // canvas size in pixels
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    // the canvas changed size, every camera gets the aspect ratio of its rect
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.width = width.max(1.0);
+        self.height = height.max(1.0);
+        for view in self.views[0..self.len].iter_mut().flatten() {
+            view.camera.set_aspect(view.rect.aspect(self.width, self.height));
+        }
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::Vec3;
+    use crate::engine::Projection;
+
+    #[test]
+    fn views_follow_the_canvas_size() {
+        let camera = Camera::perspective(Vec3::new(0.0, 10.0, 0.1), 25.0, 1.0, 1.0, 100.0);
+        let mut views = Views::new(camera, 800.0, 600.0);
+        let minimap = Camera::orthographic(Vec3::new(0.0, 10.0, 0.1), 50.0, 1.0, 1.0, 100.0);
+        let index = views.add(View::new(minimap, Rect::new(0.75, 0.75, 0.25, 0.25), 2)).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(views.main().camera.aspect(), 800.0 / 600.0);
+        assert_eq!(views.get(1).unwrap().camera.aspect(), 200.0 / 150.0);
+
+        views.resize(400.0, 800.0);
+        assert_eq!(views.main().camera.aspect(), 0.5);
+        assert_eq!(views.get(1).unwrap().camera.aspect(), 0.5);
+        assert_eq!(views.get(1).unwrap().camera.projection(), Projection::Orthographic { height: 50.0 });
+
+        // a collapsed canvas doesn't divide by zero
+        views.resize(0.0, 0.0);
+        assert!(views.iter().all(|view| view.camera.aspect().is_finite()));
+
+        while views.len() < MAX_VIEWS {
+            views.add(View::new(camera, Rect::FULL, ALL_LAYERS));
+        }
+        assert!(views.add(View::new(camera, Rect::FULL, ALL_LAYERS)).is_none());
+    }
+}
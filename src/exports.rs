@@ -0,0 +1,370 @@
+// Everything JS calls, the engine lives behind the pointer returned by
+// `init` and is passed back in with every call.
+
+use crate::{ add_shader, add_mesh, error };
+use crate::engine::{ Engine, Input, Key, Action, Axis };
+use crate::engine::input::{ Binding, AxisBinding };
+use crate::game::{ Assets };
+use crate::log;
+use crate::physics::Integrator;
+use crate::replay::{ Recording };
+use crate::{ snapshot, utils };
+
+
+
+#[no_mangle]
+pub extern "C" fn init() -> *mut Engine {
+
+    utils::set_panic_hook();
+
+    let engine = Engine::new(&load_assets());
+
+    Box::into_raw(Box::new(engine))
+}
+
+
+
+pub fn load_assets() -> Assets {
+    Assets {
+        vertex_color: add_shader!(vertex_color),
+        test: add_shader!(test),
+        debug_line: add_shader!(debug_line),
+        cube_mesh: add_mesh!(cube),
+        ship_mesh: add_mesh!(ship),
+        quad_mesh: add_mesh!(quad),
+    }
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn get_framebuffer_pointer(ptr: *mut Engine) -> *const u32 {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.frame.buffer.as_ptr()
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn get_debug_buffer_pointer(ptr: *mut Engine) -> *const f32 {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.frame.debug.as_ptr()
+}
+
+
+
+// ring buffer of the last frames' timings and counters, the layout is in
+// src/engine/profiler.rs
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn get_profiler_pointer(ptr: *mut Engine) -> *const u32 {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.frame.profiler.as_ptr()
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn set_profiling(ptr: *mut Engine, enabled: bool) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.frame.profiler.enabled = enabled;
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn set_debug_draw(ptr: *mut Engine, enabled: bool) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.frame.debug.enabled = enabled;
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn get_input_pointer(ptr: *mut Engine) -> *mut Input {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    &mut engine.input
+}
+
+
+
+// binds `key` to the action, replacing the key it was bound to
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rebind_action(ptr: *mut Engine, action: u8, key: u8) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    if let (Some(action), Some(key)) = (Action::from_code(action), Key::from_code(key)) {
+        engine.bindings_mut().rebind(action, Binding::Key(key));
+    }
+}
+
+
+
+// binds a pair of keys to the axis, replacing the pair it was bound to
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn rebind_axis(ptr: *mut Engine, axis: u8, negative: u8, positive: u8) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    let keys = (Key::from_code(negative), Key::from_code(positive));
+    if let (Some(axis), (Some(negative), Some(positive))) = (Axis::from_code(axis), keys) {
+        engine.bindings_mut().rebind_axis(axis, AxisBinding::Keys { negative, positive });
+    }
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn render(ptr: *mut Engine, dt: f32) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+
+    engine.render(dt);
+}
+
+
+
+// `paused` stops game time, `step` then runs a single frame
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn set_paused(ptr: *mut Engine, paused: bool) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    if paused { engine.clock.pause(); } else { engine.clock.resume(); }
+}
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn step(ptr: *mut Engine) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.clock.step();
+}
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn set_time_scale(ptr: *mut Engine, scale: f32) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.clock.set_scale(scale);
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn set_integrator(ptr: *mut Engine, integrator: u8) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+
+    match Integrator::from_code(integrator) {
+        Some(integrator) => engine.set_integrator(integrator),
+        None => error!("unknown integrator: {}", integrator),
+    }
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn get_transfer_pointer(ptr: *mut Engine) -> *mut u8 {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.transfer.as_mut_ptr()
+}
+
+
+
+// makes room for `len` bytes that JS writes at `get_transfer_pointer`
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn resize_transfer(ptr: *mut Engine, len: usize) -> *mut u8 {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.transfer.resize(len, 0);
+    engine.transfer.as_mut_ptr()
+}
+
+
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn start_recording(ptr: *mut Engine) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.start_recording();
+}
+
+
+
+// writes the recording to the transfer buffer and returns its length, 0 if
+// nothing was being recorded
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn stop_recording(ptr: *mut Engine) -> usize {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.transfer = engine.stop_recording()
+        .map(|recording| recording.to_bytes())
+        .unwrap_or_default();
+    engine.transfer.len()
+}
+
+
+
+// replays the recording in the transfer buffer
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn play_recording(ptr: *mut Engine) -> bool {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    match Recording::from_bytes(&engine.transfer) {
+        Ok(recording) => {
+            engine.play(recording);
+            true
+        },
+        Err(error) => {
+            error!("bad recording: {:?}", error);
+            false
+        },
+    }
+}
+
+
+
+// writes a snapshot of the whole game to the transfer buffer and returns
+// its length
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn save_snapshot(ptr: *mut Engine) -> usize {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.transfer = snapshot::save(engine);
+    engine.transfer.len()
+}
+
+
+
+// replaces the game with the snapshot in the transfer buffer
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn load_snapshot(ptr: *mut Engine) -> bool {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    let bytes = core::mem::take(&mut engine.transfer);
+    let result = snapshot::load(engine, &bytes);
+    engine.transfer = bytes;
+    match result {
+        Ok(()) => true,
+        Err(error) => {
+            error!("bad snapshot: {:?}", error);
+            false
+        },
+    }
+}
+
+
+
+// sets the log level of the module named in the transfer buffer, like
+// "game::ship", or the default level if it is empty
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn set_log_level(ptr: *mut Engine, len: usize, level: u32) -> bool {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    let module = engine.transfer.get(0..len).and_then(|b| core::str::from_utf8(b).ok());
+    let (Some(module), Some(level)) = (module, log::Level::from_u32(level)) else {
+        return false;
+    };
+    match log::set_level(module, level) {
+        Ok(()) => true,
+        Err(error) => {
+            error!("can't set the log level of {}: {:?}", module, error);
+            false
+        },
+    }
+}
+
+
+
+// switches the camera between perspective and orthographic
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn toggle_projection(ptr: *mut Engine) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    let camera = &mut engine.views.main_mut().camera;
+    engine.game.camera_controller.toggle_projection(camera);
+}
+
+
+
+// the canvas changed size, in pixels
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn resize(ptr: *mut Engine, width: f32, height: f32) {
+    let engine = unsafe {
+        assert!(!ptr.is_null());
+        &mut *ptr
+    };
+    engine.resize(width, height);
+}
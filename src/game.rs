@@ -17,195 +17,341 @@ pub use bullet::Bullet;
 pub mod asteroid;
 pub use asteroid::Asteroid;
 
+pub mod assets;
+pub use assets::Assets;
 
-use crate::engine::{Camera, Arena, ArenaID, Frame};
-use crate::engine::entity::{ EntityBehavior };
-use crate::math::{ Matrix4 };
-use crate::physics::collisions::Collider;
+pub mod spin;
+pub use spin::Spin;
+
+pub mod events;
+pub use events::{ GameEvent, GameEvents };
+
+pub mod world;
+pub use world::{ World, TimerEvent };
+
+pub mod systems;
+
+
+use crate::engine::{ Axis, Camera, CameraController, Frame, View, Views, Rect, Scope };
+use crate::engine::viewport::DEFAULT_LAYER;
+use crate::engine::ecs::{ Schedule };
+use crate::math::{ Vec3 };
+use crate::physics::{ Integrator };
 use crate::prng::{ Xoroshiro128Plus };
+use crate::binary::{ Writer, Reader };
+use crate::snapshot::{ self, SnapshotError };
+
+
+
+// seed of the prng that places the asteroids when no other is given
+pub const SEED: [u64; 2] = [2, 4];
+
+// screen shake for every unit the ship is pushed into something
+const TRAUMA_PER_DAMAGE: f32 = 0.05;
+
+// render layers, the minimap only shows what the ship can run into
+pub const WORLD_LAYER: u32 = DEFAULT_LAYER;
+pub const EFFECTS_LAYER: u32 = 1 << 1;
+pub const SCENERY_LAYER: u32 = 1 << 2;
+
+// index of the minimap in `views`, it looks straight down on the ship and
+// covers this much of the world from top to bottom
+pub const MINIMAP: usize = 1;
+const MINIMAP_HEIGHT: f32 = 600.0;
+const MINIMAP_DISTANCE: f32 = 1000.0;
+
+
+
+// the main view follows the ship over the whole canvas, the minimap sits in
+// the top right corner
+pub fn views(width: f32, height: f32) -> Views {
+    let mut views = Views::new(CameraController::new().camera(1.0), width, height);
+    let minimap = Camera::orthographic(
+        Vec3::new(0.0, MINIMAP_DISTANCE, 0.1), MINIMAP_HEIGHT, 1.0, 1.0, 4000.0
+    );
+    views.add(View::new(minimap, Rect::new(0.74, 0.74, 0.24, 0.24), WORLD_LAYER));
+    views
+}
 
 
 
 pub struct Game {
-    pub ship: Ship,
-    pub rot1: ArenaID,
-    pub rot2: ArenaID,
-    pub objects: Arena<Object3d, 10>,
-    pub asteroids: Arena<Asteroid, 20>,
+    pub world: World,
+    pub schedule: Schedule<World, 16>,
+    pub prng: Xoroshiro128Plus,
+    pub camera_controller: CameraController,
 }
 
 
 
 impl Game {
 
-    pub fn new() -> Self {
+    pub fn new(assets: &Assets, seed: [u64; 2]) -> Self {
 
-        let mut prng = Xoroshiro128Plus::new(2, 4);
+        let mut prng = Xoroshiro128Plus::new(seed[0], seed[1]);
 
-        let ship = Ship::new();
+        let mut world = World::empty();
 
-        let asteroid_meta = r#"{
-            "shader": "vertex_color",
-            "count": 36,
-            "attributes": {
-                "a_position": "cube_vertices",
-                "a_color": "cube_vertex_colors"
-            },
-            "uniforms": {
-                "objectData": ["u_matrix", "u_collide"]
-            }
-        }"#;
+        let ship = world.entities.create();
+        world.ships.insert(ship, Ship::new(&mut world.scene, assets));
 
+        let cube = assets.cube();
 
-        let mut objects = Arena::empty();
 
-        let mut temp = Object3d::new(asteroid_meta);
+        let mut temp = Object3d::new(&mut world.scene, &cube);
         temp.scale.set(280.0, 280.0, 280.0);
         temp.position.set(200.0, -1000.0, 0.0);
         temp.rotation.x = 45.0 * crate::PI / 180.0;
         temp.rotation.y = 45.0 * crate::PI / 180.0;
-        objects.add(temp);
+        world.scene.set_layers(temp.node, SCENERY_LAYER);
+        let entity = world.entities.create();
+        world.objects.insert(entity, temp);
 
-        let mut rot1 = Object3d::new(asteroid_meta);
+        let spin = Spin::new(Vec3::new(
+            -30.0 * crate::PI / 180.0, -30.0 * crate::PI / 180.0, 0.0
+        ));
+
+        let mut rot1 = Object3d::new(&mut world.scene, &cube);
         rot1.scale.set(20.0, 20.0, 20.0);
         rot1.position.set(20.0, -300.0, -100.0);
-        let rot1 = objects.add(rot1);
+        world.scene.set_layers(rot1.node, SCENERY_LAYER);
+        let entity = world.entities.create();
+        world.objects.insert(entity, rot1);
+        world.spins.insert(entity, Spin::new(spin.rate));
 
-        let mut rot2 = Object3d::new(asteroid_meta);
+        let mut rot2 = Object3d::new(&mut world.scene, &cube);
         rot2.scale.set(15.0, 15.0, 15.0);
         rot2.position.set(100.0, 100.0, -140.0);
-        let rot2 = objects.add(rot2);
-
+        world.scene.set_layers(rot2.node, SCENERY_LAYER);
+        let entity = world.entities.create();
+        world.objects.insert(entity, rot2);
+        world.spins.insert(entity, spin);
 
-        // let testmeta = r#"{
-        //     "shader": "test",
-        //     "count": 6,
-        //     "attributes": {
-        //         "a_position": "quad"
-        //     },
-        //     "uniforms": {
-        //         "objectData": ["u_matrix"]
-        //     }
-        // }"#;
 
-
-        let mut asteroids = Arena::empty();
         for _ in 0..15 {
             let s = prng.random_f32_bw(5.0, 10.0);
             let x = prng.random_f32_bw(0.0, 200.0) - 100.0;
             let z = prng.random_f32_bw(0.0, 200.0) - 100.0;
-            let mut asteroid = Asteroid::new(s);
+            let mut asteroid = Asteroid::new(&mut world.scene, assets, s);
             asteroid.scale.set(s, s, s);
             asteroid.position.set(x, 0.0, z);
 
-            asteroids.add(asteroid);
-        //     let mut test = Node::new(Some(testmeta));
-        //     test.scale.set(12.0, 12.0, 12.0);
-        //     test.position = asteroid.position;
-
-        //     asteroid_ids.add(scenegraph.add_object(scene, asteroid));
-        //     scenegraph.add_object(scene, test);
+            let entity = world.entities.create();
+            world.asteroids.insert(entity, asteroid);
         }
 
 
         Self {
-            ship,
-            rot1, rot2,
-            objects,
-            asteroids
+            world,
+            schedule: systems::schedule(),
+            prng,
+            camera_controller: CameraController::new(),
         }
     }
 
 
-    pub fn render_frame(&mut self, frame: &mut Frame, camera: &mut Camera) {
+    pub fn save(&self, out: &mut Writer) {
+        let world = &self.world;
+        let [s0, s1] = self.prng.state();
+        out.u64(s0);
+        out.u64(s1);
 
-        for asteroid in self.asteroids.slice_mut() {
+        out.u8(world.ships.len() as u8);
+        for (_, ship) in world.ships.iter() {
+            ship.save(out);
+        }
+        out.u8(world.asteroids.len() as u8);
+        for (_, asteroid) in world.asteroids.iter() {
+            asteroid.save(out);
+        }
+        out.u8(world.objects.len() as u8);
+        for (entity, object) in world.objects.iter() {
+            object.save(out);
+            match world.spins.get(entity) {
+                Some(spin) => {
+                    out.bool(true);
+                    out.vec3(spin.rate);
+                },
+                None => out.bool(false),
+            }
+        }
 
-            if let Some((sep_axis, min_depth)) = asteroid.collider.collide(&self.ship.collider) {
-                asteroid.colliding = true;
-                self.ship.colliding = true;
+        self.camera_controller.save(out);
+    }
 
-                self.ship.position += sep_axis * (min_depth / 2.0);
-                asteroid.position += -sep_axis * (min_depth / 2.0);
-            }
 
-            for bullet in self.ship.gun1.bullets.slice_mut() {
-                if bullet.live {
-                    if let Some((sep_axis, min_depth)) = asteroid.collider.collide(&bullet.collider) {
-                        bullet.live = false;
-                        asteroid.colliding = true;
-                    }
-                }
-            }
+    // builds a new game from a snapshot, if it fails everything created so
+    // far is destroyed again
+    pub fn load(
+        assets: &Assets, input: &mut Reader
+    ) -> Result<Self, SnapshotError> {
+        let mut game = Self {
+            world: World::empty(),
+            schedule: systems::schedule(),
+            prng: Xoroshiro128Plus::from_state([0, 0]),
+            camera_controller: CameraController::new(),
+        };
+        match game.load_world(assets, input) {
+            Ok(()) => Ok(game),
+            Err(error) => {
+                game.world.clear();
+                Err(error)
+            },
+        }
+    }
 
-            for bullet in self.ship.gun2.bullets.slice_mut() {
-                if bullet.live {
-                    if let Some((sep_axis, min_depth)) = asteroid.collider.collide(&bullet.collider) {
-                        bullet.live = false;
-                        asteroid.colliding = true;
-                    }
-                }
+    // entities go into the world before their state is read, so a failed
+    // load can clean them up
+    fn load_world(
+        &mut self, assets: &Assets, input: &mut Reader
+    ) -> Result<(), SnapshotError> {
+        self.prng = Xoroshiro128Plus::from_state([input.u64()?, input.u64()?]);
+        let world = &mut self.world;
+
+        for _ in 0..snapshot::count(input, world.ships.capacity())? {
+            let entity = world.entities.create();
+            world.ships.insert(entity, Ship::new(&mut world.scene, assets));
+            let ship = world.ships.get_mut(entity).expect("just inserted");
+            ship.load(input, &mut world.scene)?;
+        }
+
+        for _ in 0..snapshot::count(input, world.asteroids.capacity())? {
+            let scale = input.vec3()?;
+            let mut asteroid = Asteroid::new(&mut world.scene, assets, scale.x);
+            asteroid.scale = scale;
+            let entity = world.entities.create();
+            world.asteroids.insert(entity, asteroid);
+            world.asteroids.get_mut(entity).expect("just inserted").load(input)?;
+        }
+
+        // every object in the game is a cube
+        let cube = assets.cube();
+        for _ in 0..snapshot::count(input, world.objects.capacity())? {
+            let object = Object3d::new(&mut world.scene, &cube);
+            world.scene.set_layers(object.node, SCENERY_LAYER);
+            let entity = world.entities.create();
+            world.objects.insert(entity, object);
+            world.objects.get_mut(entity).expect("just inserted").load(input)?;
+            if input.bool()? {
+                world.spins.insert(entity, Spin::new(input.vec3()?));
             }
+        }
+
+        self.camera_controller.load(input)?;
+
+        // colliders and transforms are derived from the loaded state
+        systems::render_upload(world, &mut Frame::new());
+        Ok(())
+    }
+
+
+    // the integrator every rigidbody in the game uses
+    pub fn integrator(&self) -> Integrator {
+        self.world.ship().map_or(Integrator::default(), |ship| ship.rigidbody.integrator)
+    }
 
+    // switches the integrator used by every rigidbody in the game
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        for ship in self.world.ships.components_mut() {
+            ship.set_integrator(integrator);
+        }
+        for asteroid in self.world.asteroids.components_mut() {
+            asteroid.rigidbody.integrator = integrator;
         }
+    }
+
 
-        // for asteroid in self.asteroids.slice_mut() {
-        //     asteroid.aabb.collide(&mut self.ship.aabb);
-        //     for bullet in self.ship.gun1.bullets.slice_mut() {
-        //         if bullet.live {
-        //             if asteroid.aabb.collide(&mut bullet.aabb) {
-        //                 bullet.live = false;
-        //             }
-        //         }
-        //     }
+    pub fn render_frame(&mut self, frame: &mut Frame, views: &mut Views) {
 
-        //     for bullet in self.ship.gun2.bullets.slice_mut() {
-        //         if bullet.live {
-        //             if asteroid.aabb.collide(&mut bullet.aabb) {
-        //                 bullet.live = false;
-        //             }
-        //         }
-        //     }
-        //     // if asteroid.aabb.collide(&mut self.ship.aabb) {
+        // nothing moves while the clock is paused, it is only drawn again
+        let controller = &mut self.camera_controller;
+        if frame.dt > 0.0 {
+            self.world.events.swap();
+            self.schedule.run(&mut self.world, frame);
 
-        //     //     // crate::utils::console_log(
-        //     //     //     format!("ship collide").as_str()
-        //     //     // );
+            for event in self.world.events.iter() {
+                if let GameEvent::ShipDamaged { amount, .. } = *event {
+                    controller.shake.add_trauma(amount * TRAUMA_PER_DAMAGE);
+                }
+            }
+        } else {
+            let span = frame.profiler.begin(Scope::RenderUpload);
+            systems::render_upload(&mut self.world, frame);
+            frame.profiler.end(span);
+        }
 
-        //     // }
-        // }
+        controller.zoom_by(frame.axis(Axis::Zoom), frame.dt);
+        if let Some(ship) = self.world.ship() {
+            let camera = &mut views.main_mut().camera;
+            controller.update(camera, ship.position, ship.rigidbody.velocity(), frame.dt);
+            if let Some(minimap) = views.get_mut(MINIMAP) {
+                minimap.camera.position = ship.position + Vec3::new(0.0, MINIMAP_DISTANCE, 0.1);
+                minimap.camera.look_at(ship.position);
+            }
+        }
+    }
 
+}
 
-        self.ship.render_frame(frame);
 
-        camera.position.x = self.ship.position.x;
-        camera.position.z = self.ship.position.z + 0.1;
-        camera.look_at(self.ship.position);
 
-        // for object in self.objects.slice_mut() {
-        //     object.render_frame(frame);
-        // }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::HeadlessPlatform;
+    use crate::engine::{ Key, Input };
+    use crate::math::Matrix4;
 
+    #[test]
+    fn despawning_everything_frees_all_entities() {
+        let platform = HeadlessPlatform::install();
+        let live = platform.live_entities();
+        let mut game = Game::new(&crate::load_assets(), SEED);
+        let mut views = views(800.0, 800.0);
+        let mut frame = Frame::new();
 
-        for asteroid in self.asteroids.slice_mut() {
-            asteroid.render_frame(frame);
+        let input = Input::with_keys(&[Key::Space, Key::W]);
+        for t in 1..600 {
+            frame.update(t as f32 / 60.0, 1.0 / 60.0, &input, Matrix4::identity());
+            game.render_frame(&mut frame, &mut views);
         }
+        assert!(platform.live_entities() > live);
 
-        self.objects[self.rot1].rotation.x = -frame.t * 0.5 * crate::PI / 180.0;
-        self.objects[self.rot1].rotation.y = -frame.t * 0.5 * crate::PI / 180.0;
-        self.objects[self.rot2].rotation.x = -frame.t * 0.5 * crate::PI / 180.0;
-        self.objects[self.rot2].rotation.y = -frame.t * 0.5 * crate::PI / 180.0;
+        game.world.clear();
+        assert!(game.world.entities.is_empty());
+        assert_eq!(platform.live_entities(), live);
+    }
 
+    #[test]
+    fn hit_flash_ends_on_time_and_with_the_asteroid() {
+        HeadlessPlatform::install();
+        let mut game = Game::new(&crate::load_assets(), SEED);
+        let mut views = views(800.0, 800.0);
+        let mut frame = Frame::new();
+        let world = &mut game.world;
+        let (a, b) = {
+            let mut entities = world.asteroids.iter().map(|(entity, _)| entity);
+            (entities.next().unwrap(), entities.next().unwrap())
+        };
+        for entity in [a, b] {
+            world.asteroids.get_mut(entity).unwrap().flashing = true;
+            world.timers.restart_for(entity, 0.1, TimerEvent::EndFlash);
+        }
 
-        self.ship.update_uniforms(frame, Matrix4::identity());
+        world.despawn(b);
+        assert_eq!(world.timers.len(), 1);
 
-        for object in self.objects.slice_mut() {
-            object.update_uniforms(frame, Matrix4::identity());
+        for t in 1..5 {
+            frame.update(t as f32 / 60.0, 1.0 / 60.0, &Input::default(), Matrix4::identity());
+            game.render_frame(&mut frame, &mut views);
         }
+        assert!(game.world.asteroids.get(a).unwrap().flashing);
 
-        for asteroid in self.asteroids.slice_mut() {
-            asteroid.update_uniforms(frame, Matrix4::identity());
+        for t in 5..10 {
+            frame.update(t as f32 / 60.0, 1.0 / 60.0, &Input::default(), Matrix4::identity());
+            game.render_frame(&mut frame, &mut views);
         }
+        assert!(!game.world.asteroids.get(a).unwrap().flashing);
+        assert!(game.world.timers.is_empty());
     }
-
 }
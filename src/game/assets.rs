@@ -0,0 +1,76 @@
+use crate::engine::{ Shader, Buffer, Mesh, RenderDescriptor };
+use crate::engine::mesh::Attribute;
+
+
+
+// Handles for everything sent to JS on init, and the descriptors the game's
+// entities are created from.
+#[derive(Clone, Copy)]
+pub struct Assets {
+    pub vertex_color: Shader,
+    pub test: Shader,
+    pub debug_line: Shader,
+    pub cube_mesh: Mesh,
+    pub ship_mesh: Mesh,
+    pub quad_mesh: Mesh,
+}
+
+
+impl Assets {
+
+    pub fn cube(&self) -> RenderDescriptor {
+        vertex_colored(self.vertex_color, self.cube_mesh)
+    }
+
+    pub fn ship(&self) -> RenderDescriptor {
+        vertex_colored(self.vertex_color, self.ship_mesh)
+    }
+
+    // flat marker used for guns, thrusters and bullets
+    pub fn quad(&self) -> RenderDescriptor {
+        let mesh = self.quad_mesh;
+        RenderDescriptor::builder(self.test)
+            .indices(mesh)
+            .attribute("a_position", stream(mesh, Attribute::Position))
+            .uniform_block("objectData", &["u_matrix"])
+            .build()
+            .expect("quad descriptor")
+    }
+
+}
+
+
+fn vertex_colored(shader: Shader, mesh: Mesh) -> RenderDescriptor {
+    RenderDescriptor::builder(shader)
+        .indices(mesh)
+        .attribute("a_position", stream(mesh, Attribute::Position))
+        .attribute("a_color", stream(mesh, Attribute::Color))
+        .uniform_block("objectData", &["u_matrix", "u_collide"])
+        .build()
+        .unwrap_or_else(|e| panic!("{} descriptor: {:?}", mesh.name(), e))
+}
+
+fn stream(mesh: Mesh, attribute: Attribute) -> Buffer {
+    mesh.buffer(attribute)
+        .unwrap_or_else(|| panic!("mesh {} has no {}", mesh.name(), attribute.name()))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptors_match_shaders() {
+        let assets = crate::load_assets();
+        assert_eq!(assets.cube().count(), 36);
+        assert_eq!(assets.ship().count(), 36);
+        assert_eq!(assets.quad().count(), 6);
+
+        // the cube's two tones per side keep the triangles apart
+        assert_eq!(assets.cube_mesh.vertices(), 36);
+        assert_eq!(assets.quad_mesh.vertices(), 4);
+        assert!(assets.quad_mesh.has(Attribute::Normal) && assets.quad_mesh.has(Attribute::Uv));
+    }
+}
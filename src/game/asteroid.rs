@@ -1,74 +1,79 @@
 
 use crate::math::{ Vec3, Matrix4 };
 use crate::physics;
-use crate::engine::{ Frame };
+use crate::physics::collisions::Collider;
+use crate::engine::{ Frame, Scenegraph, NodeID, Counter };
 use crate::engine::entity::{ EntityBehavior };
+use crate::game::Assets;
 use crate::utils;
+use crate::binary::{ Writer, Reader };
+use crate::snapshot::{ self, SnapshotError };
 
 
 
 pub struct Asteroid {
     id: usize,
+    node: NodeID,
     pub position: Vec3,
     pub rotation: Vec3,
     pub scale: Vec3,
-    // pub rigidbody: physics::RigidBody,
+    pub rigidbody: physics::RigidBody,
     // pub aabb: physics::Aabb,
 
     pub collider: physics::collisions::PolygonCollider<4>,
     pub colliding: bool,
+    // set for a moment after a bullet hit, see `TimerEvent::EndFlash`
+    pub flashing: bool,
 }
 
 
-impl Default for Asteroid {
-    fn default() -> Self {
+impl Asteroid {
+
+    // kg per cubic meter
+    const DENSITY: f32 = 2.0;
+    const DAMPING: f32 = 2.0;
+
+    pub fn new(scene: &mut Scenegraph, assets: &Assets, s: f32) -> Self {
+        let id = utils::webgl_add_entity(&assets.cube());
+        let mass = Self::DENSITY * (s * 2.0) * (s * 2.0) * (s * 2.0);
         Self {
-            id: 0,
+            id,
+            node: scene.add(scene.root(), Some(id)),
             position: Vec3::zero(),
             rotation: Vec3::zero(),
             scale: Vec3::new(1.0, 1.0, 1.0),
-            // rigidbody: physics::RigidBody::new(1.0, 0.0),
-            // aabb: physics::Aabb::new(0.0, 0.0),
+            rigidbody: physics::RigidBody::new(
+                mass, physics::moi_cuboid(mass, s * 2.0, s * 2.0)
+            ),
+            // aabb: physics::Aabb::new(s * 2.0, s * 2.0),
             collider: physics::collisions::PolygonCollider::new([
-                Vec3::zero(),
-                Vec3::zero(),
-                Vec3::zero(),
-                Vec3::zero(),
+                Vec3::new(-s, 0.0, -s),
+                Vec3::new(s, 0.0, -s),
+                Vec3::new(s, 0.0, s),
+                Vec3::new(-s, 0.0, s),
             ]),
             colliding: false,
+            flashing: false,
         }
     }
-}
 
+    pub fn destroy(self, scene: &mut Scenegraph) {
+        scene.remove(self.node);
+    }
 
-impl Asteroid {
+    // the scale comes first, it is what the asteroid is created from when
+    // loading
+    pub fn save(&self, out: &mut Writer) {
+        out.vec3(self.scale);
+        out.vec3(self.position);
+        out.vec3(self.rotation);
+        snapshot::save_rigidbody(out, &self.rigidbody);
+    }
 
-    pub fn new(s: f32) -> Self {
-        let mut object = Self::default();
-        object.id = utils::webgl_add_entity(r#"{
-            "shader": "vertex_color",
-            "count": 36,
-            "attributes": {
-                "a_position": "cube_vertices",
-                "a_color": "cube_vertex_colors"
-            },
-            "uniforms": {
-                "objectData": ["u_matrix", "u_collide"]
-            }
-        }"#);
-        // object.aabb = physics::Aabb::new(s * 2.0, s * 2.0);
-
-        // object.rigidbody = physics::RigidBody::new(
-        //     250.0 * s, physics::moi_cuboid(250.0 * s, s * 2.0, s * 2.0)
-        // );
-
-        object.collider = physics::collisions::PolygonCollider::new([
-            Vec3::new(-s, 0.0, -s),
-            Vec3::new(s, 0.0, -s),
-            Vec3::new(s, 0.0, s),
-            Vec3::new(-s, 0.0, s),
-        ]);
-        object
+    pub fn load(&mut self, input: &mut Reader) -> Result<(), SnapshotError> {
+        self.position = input.vec3()?;
+        self.rotation = input.vec3()?;
+        snapshot::load_rigidbody(input, &mut self.rigidbody)
     }
 
 }
@@ -76,34 +81,38 @@ impl Asteroid {
 
 impl EntityBehavior for Asteroid {
 
-    fn render_frame(&mut self, frame: &mut Frame) {
+    fn render_frame(&mut self, frame: &mut Frame, _: &mut Scenegraph) {
         // self.aabb.update(self.position);
 
-        // self.rigidbody.apply_damping(20.0);
-        // self.rigidbody.update_physics(
-        //     frame.dt, &mut self.position, &mut self.rotation
-        // );
+        self.rigidbody.apply_damping(Self::DAMPING);
+        self.rigidbody.update_physics(
+            frame.dt, &mut self.position, &mut self.rotation
+        );
+        frame.profiler.count(Counter::BodiesIntegrated, 1);
     }
 
-    fn update_uniforms(&mut self, frame: &mut Frame, mut matrix: Matrix4) {
+    fn update_uniforms(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        let mut matrix = Matrix4::identity();
         matrix.translate(self.position);
         matrix.rotate(self.rotation);
         self.collider.update(&matrix);
+        self.collider.debug_draw(&mut frame.debug);
+        physics::debug::draw_velocity(&mut frame.debug, self.position, &self.rigidbody);
 
         matrix.scale(self.scale);
-        frame.add_view_matrix(self.id, matrix);
+        scene.set_local(self.node, matrix);
 
 
-        if self.colliding {
-            frame.buffer.add_float(self.id, 0.0, 1.0, 1.0);
+        if self.colliding || self.flashing {
+            frame.buffer.add_float(self.id, 0, 1, 1.0);
             self.colliding = false;
         } else {
-            frame.buffer.add_float(self.id, 0.0, 1.0, 0.0);
+            frame.buffer.add_float(self.id, 0, 1, 0.0);
         }
         // if self.aabb.colliding {
-        //     frame.buffer.add_float(self.id, 0.0, 1.0, 1.0);
+        //     frame.buffer.add_float(self.id, 0, 1, 1.0);
         // } else {
-        //     frame.buffer.add_float(self.id, 0.0, 1.0, 0.0);
+        //     frame.buffer.add_float(self.id, 0, 1, 0.0);
         // }
     }
 
@@ -1,61 +1,52 @@
 
 use crate::math::{ Vec3, Matrix4 };
 use crate::physics;
-use crate::engine::{ Frame };
+use crate::physics::collisions::Collider;
+use crate::engine::{ Frame, Scenegraph, NodeID, RenderDescriptor, Cooldown, Counter };
 use crate::engine::entity::{ EntityBehavior };
 use crate::utils;
+use crate::game;
+use crate::binary::{ Writer, Reader };
+use crate::snapshot::{ self, SnapshotError };
 
 
 
 pub struct Bullet {
-    pub id: usize,
-    position: Vec3,
+    node: NodeID,
+    pub position: Vec3,
     rotation: Vec3,
     pub live: bool,
-    fire_timestamp: f32,
-    rigidbody: physics::RigidBody,
+    lifetime: Cooldown,
+    pub rigidbody: physics::RigidBody,
     pub collider: physics::collisions::CircleCollider,
     // pub aabb: physics::Aabb,
 }
 
 
-impl Default for Bullet {
-    fn default() -> Self {
-        Self {
-            id: 0,
+impl Bullet {
+
+    pub fn new(scene: &mut Scenegraph, descriptor: &RenderDescriptor) -> Self {
+        let id = utils::webgl_add_entity(descriptor);
+        let node = scene.add(scene.root(), Some(id));
+        scene.set_layers(node, game::EFFECTS_LAYER);
+        let mut object = Self {
+            node,
             live: false,
             position: Vec3::zero(),
             rotation: Vec3::zero(),
-            fire_timestamp: 0.0,
+            lifetime: Cooldown::new(Self::LIFETIME),
             rigidbody: physics::RigidBody::new(
                 12.0, physics::moi_cube(12.0, 2.0)
             ),
             collider: physics::collisions::CircleCollider::new(1.0),
             // aabb: physics::Aabb::new(2.0, 2.0),
-        }
-    }
-}
-
-
-impl Bullet {
-
-    pub fn new() -> Self {
-        let mut object = Self::default();
-        object.id = utils::webgl_add_entity(r#"{
-            "shader": "test",
-            "count": 6,
-            "attributes": {
-                "a_position": "quad"
-            },
-            "uniforms": {
-                "objectData": ["u_matrix"]
-            }
-        }"#);
+        };
         object.rigidbody.velocity_limit = 800.0;
         object
     }
 
-    const LIFETIME: f32 = 100.0;
+    // seconds
+    const LIFETIME: f32 = 1.6;
     const EXIT_VELOCITY: f32 = 400.0;
 
     pub fn fire(
@@ -66,7 +57,7 @@ impl Bullet {
         self.position = position;
         self.rotation = rotation;
         self.live = true;
-        self.fire_timestamp = frame.t;
+        self.lifetime.start(frame.t);
 
         let force = dir * self.rigidbody.mass * Self::EXIT_VELOCITY / frame.dt;
         self.rigidbody.reset();
@@ -75,29 +66,57 @@ impl Bullet {
 
         -force
     }
+
+    pub fn set_integrator(&mut self, integrator: physics::Integrator) {
+        self.rigidbody.integrator = integrator;
+    }
+
+    pub fn destroy(self, scene: &mut Scenegraph) {
+        scene.remove(self.node);
+    }
+
+    pub fn save(&self, out: &mut Writer) {
+        out.vec3(self.position);
+        out.vec3(self.rotation);
+        out.bool(self.live);
+        out.f32(self.lifetime.ready_at);
+        snapshot::save_rigidbody(out, &self.rigidbody);
+    }
+
+    pub fn load(&mut self, input: &mut Reader) -> Result<(), SnapshotError> {
+        self.position = input.vec3()?;
+        self.rotation = input.vec3()?;
+        self.live = input.bool()?;
+        self.lifetime.ready_at = input.f32()?;
+        snapshot::load_rigidbody(input, &mut self.rigidbody)
+    }
 }
 
 
 impl EntityBehavior for Bullet {
 
-    fn render_frame(&mut self, frame: &mut Frame) {
-        if frame.t - self.fire_timestamp > Self::LIFETIME {
+    fn render_frame(&mut self, frame: &mut Frame, _: &mut Scenegraph) {
+        if self.lifetime.ready(frame.t) {
             self.live = false;
         } else {
             self.rigidbody.update_physics(
                 frame.dt, &mut self.position, &mut self.rotation
             );
+            frame.profiler.count(Counter::BodiesIntegrated, 1);
         }
         self.collider.update(self.position);
         // self.aabb.update(self.position);
     }
 
-    fn update_uniforms(&mut self, frame: &mut Frame, mut matrix: Matrix4) {
+    fn update_uniforms(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        scene.set_visible(self.node, self.live);
         if !self.live { return; }
+        self.collider.debug_draw(&mut frame.debug);
+        let mut matrix = Matrix4::identity();
         matrix.translate(self.position);
         matrix.rotate(self.rotation);
         // matrix.scale(Vec3::new(5.0, 1.0, 5.0));
-        frame.add_view_matrix(self.id, matrix);
+        scene.set_local(self.node, matrix);
     }
 
 }
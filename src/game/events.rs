@@ -0,0 +1,20 @@
+use crate::math::Vec3;
+use crate::engine::Events;
+use crate::engine::ecs::Entity;
+
+
+
+// What happened in the game this frame, for anything that wants to react
+// to it without reaching into the objects involved.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    BulletFired { position: Vec3, direction: Vec3 },
+    // impulse is what the bullet passed on to the asteroid
+    AsteroidHit { asteroid: Entity, position: Vec3, impulse: Vec3 },
+    ShipDamaged { part: usize, amount: f32 },
+    // one per thruster and frame while it fires
+    ThrusterFired { thruster: usize, throttle: f32 },
+}
+
+
+pub type GameEvents = Events<GameEvent, 64>;
@@ -1,96 +1,220 @@
-
-
 use crate::math::{ Vec3, Matrix4 };
-use crate::physics::{ RigidBody };
-use crate::engine::{ Arena, Frame };
+use crate::physics::{ RigidBody, Integrator };
+use crate::engine::{ Arena, ArenaID, StackVec, Frame, Scenegraph, NodeID, RenderDescriptor, Cooldown };
 use crate::engine::entity::{ EntityBehavior };
-use crate::game::{ Bullet };
+use crate::game::{ Bullet, Assets, GameEvent, GameEvents };
 use crate::utils;
+use crate::binary::{ Writer, Reader };
+use crate::snapshot::{ self, SnapshotError };
 
 
-const NBULLETS: usize = 100;
+// bullets live for 1.6s and a gun fires every 0.06s, so ~27 are in flight
+const NBULLETS: usize = 32;
 
 
 pub struct Gun {
-    id: usize,
+    node: NodeID,
     position: Vec3,
     direction: Vec3,
-    last_timestamp: f32,
-    matrix: Matrix4,
+    cooldown: Cooldown,
     pub bullets: Arena<Bullet, NBULLETS>,
-    bullet_index: usize,
+    bullet: RenderDescriptor,
+    integrator: Integrator,
 }
 
 
 
 impl Gun {
 
-    pub fn new(position: Vec3, direction: Vec3) -> Self {
-        let mut bullets = Arena::empty();
-        for _ in 0..NBULLETS {
-            bullets.add(Bullet::new());
-        }
+    pub fn new(
+        scene: &mut Scenegraph, assets: &Assets,
+        parent: NodeID, position: Vec3, direction: Vec3,
+    ) -> Self {
+        let id = utils::webgl_add_entity(&assets.quad());
+        let node = scene.add(parent, Some(id));
+        let mut matrix = Matrix4::identity();
+        matrix.translate(position + direction);
+        scene.set_local(node, matrix);
         Self {
-            id: utils::webgl_add_entity(r#"{
-                "shader": "test",
-                "count": 6,
-                "attributes": {
-                    "a_position": "quad"
-                },
-                "uniforms": {
-                    "objectData": ["u_matrix"]
-                }
-            }"#),
+            node,
             position, direction,
-            last_timestamp: 0.0,
-            matrix: Matrix4::identity(),
-            bullets: bullets,
-            bullet_index: 0,
+            cooldown: Cooldown::new(Self::SHOOT_DELAY),
+            bullets: Arena::empty(),
+            bullet: assets.quad(),
+            integrator: Integrator::default(),
+        }
+    }
+
+    pub fn destroy(mut self, scene: &mut Scenegraph) {
+        loop {
+            let Some(id) = self.bullets.ids().next() else { break };
+            if let Some(bullet) = self.bullets.remove(id) {
+                bullet.destroy(scene);
+            }
         }
+        scene.remove(self.node);
     }
 
     const ORIGIN: Vec3 = Vec3::zero();
-    const SHOOT_DELAY: f32 = 4.0;
+    // seconds
+    const SHOOT_DELAY: f32 = 0.06;
 
     pub fn shoot(
-        &mut self, frame: &Frame, rotation: Vec3, rigidbody: &mut RigidBody
+        &mut self, frame: &Frame, scene: &mut Scenegraph,
+        rotation: Vec3, rigidbody: &mut RigidBody, events: &mut GameEvents
     ) {
-        if frame.t - self.last_timestamp < Self::SHOOT_DELAY { return; }
-
-        let center = self.matrix * Self::ORIGIN;
-        let distance = (self.matrix * self.position) - center;
-        let direction = ((self.matrix * self.direction) - center).unit();
-        let position = self.matrix * (self.position + self.direction);
-
-        self.bullet_index = (self.bullet_index + 1) % NBULLETS;
-        let bullet = &mut self.bullets[self.bullet_index.into()];
+        if !self.cooldown.ready(frame.t) { return; }
+        if self.bullets.is_full() { return; }
+
+        let matrix = scene.parent(self.node)
+            .map_or(Matrix4::identity(), |p| scene.world(p));
+        let center = matrix * Self::ORIGIN;
+        let distance = (matrix * self.position) - center;
+        let direction = ((matrix * self.direction) - center).unit();
+        let position = scene.world(self.node) * Self::ORIGIN;
+
+        let mut bullet = Bullet::new(scene, &self.bullet);
+        bullet.set_integrator(self.integrator);
         let recoil_force = bullet.fire(
             frame, position, rotation, direction, rigidbody,
         );
+        self.bullets.add(bullet);
 
         rigidbody.apply_force_and_torque(recoil_force, distance);
-        self.last_timestamp = frame.t;
+        self.cooldown.start(frame.t);
+        events.publish(GameEvent::BulletFired { position, direction });
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+        for bullet in self.bullets.iter_mut() {
+            bullet.set_integrator(integrator);
+        }
+    }
+
+    pub fn save(&self, out: &mut Writer) {
+        out.f32(self.cooldown.ready_at);
+
+        // the slot layout decides the order bullets are updated in, it is
+        // saved so a loaded game carries on exactly the same
+        out.u8(self.bullets.used_slots() as u8);
+        out.u8(self.bullets.free_slots().count() as u8);
+        for index in self.bullets.free_slots() {
+            out.u8(index as u8);
+        }
+
+        out.u8(self.bullets.len() as u8);
+        for (id, bullet) in self.bullets.iter_with_ids() {
+            out.u8(id.index() as u8);
+            bullet.save(out);
+        }
+    }
+
+    // bullets are added to the gun as they are read, so destroying the gun
+    // cleans up after a failed load
+    pub fn load(
+        &mut self, input: &mut Reader, scene: &mut Scenegraph
+    ) -> Result<(), SnapshotError> {
+        self.cooldown.ready_at = input.f32()?;
+
+        let used = snapshot::count(input, NBULLETS)?;
+        let mut free = [0; NBULLETS];
+        let nfree = snapshot::count(input, NBULLETS)?;
+        for index in &mut free[0..nfree] {
+            *index = input.u8()? as usize;
+        }
+
+        let count = snapshot::count(input, NBULLETS)?;
+        for _ in 0..count {
+            let index = input.u8()? as usize;
+            let mut bullet = Bullet::new(scene, &self.bullet);
+            bullet.set_integrator(self.integrator);
+            let id = match self.bullets.insert_at(index, bullet) {
+                Ok(id) => id,
+                Err(bullet) => {
+                    bullet.destroy(scene);
+                    return Err(SnapshotError::BadSlots);
+                },
+            };
+            self.bullets[id].load(input)?;
+        }
+
+        if !self.bullets.set_free_slots(used, &free[0..nfree]) {
+            return Err(SnapshotError::BadSlots);
+        }
+        Ok(())
     }
 
 }
 
 impl EntityBehavior for Gun {
 
-    fn render_frame(&mut self, frame: &mut Frame) {
-        for bullet in self.bullets.slice_mut() {
-            bullet.render_frame(frame);
+    fn render_frame(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        let mut dead: StackVec<Option<ArenaID>, NBULLETS> = StackVec::empty();
+        for (id, bullet) in self.bullets.iter_mut_with_ids() {
+            bullet.render_frame(frame, scene);
+            if !bullet.live {
+                dead.add(Some(id));
+            }
         }
-    }
 
-    fn update_uniforms(&mut self, frame: &mut Frame, mut matrix: Matrix4) {
-        self.matrix = matrix.clone();
-        matrix.translate(self.position + self.direction);
-        frame.add_view_matrix(self.id, matrix);
+        for id in dead.slice().iter().flatten() {
+            if let Some(bullet) = self.bullets.remove(*id) {
+                bullet.destroy(scene);
+            }
+        }
+    }
 
-        for bullet in self.bullets.slice_mut() {
-            bullet.update_uniforms(frame, Matrix4::identity());
+    fn update_uniforms(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        for bullet in self.bullets.iter_mut() {
+            bullet.update_uniforms(frame, scene);
         }
     }
 
 
 }
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::HeadlessPlatform;
+    use crate::engine::Input;
+
+    #[test]
+    fn bullets_are_destroyed_and_ids_recycled() {
+        let platform = HeadlessPlatform::install();
+        let mut scene = Scenegraph::empty();
+        let mut frame = Frame::new();
+        let mut rigidbody = RigidBody::new(1000.0, 1000.0);
+        let root = scene.root();
+        let mut gun = Gun::new(
+            &mut scene, &crate::load_assets(), root, Vec3::zero(), Vec3::new(0.0, 0.0, -1.0)
+        );
+        let live = platform.live_entities();
+        let mut events = GameEvents::empty();
+
+        for t in 1..1000 {
+            frame.update(t as f32 / 60.0, 1.0 / 60.0, &Input::default(), Matrix4::identity());
+            gun.shoot(&frame, &mut scene, Vec3::zero(), &mut rigidbody, &mut events);
+            gun.render_frame(&mut frame, &mut scene);
+            gun.update_uniforms(&mut frame, &mut scene);
+            assert!(gun.bullets.len() <= 26);
+        }
+        assert_eq!(platform.live_entities(), live + gun.bullets.len());
+        // ~250 shots fired, but IDs only go as high as the bullets in flight
+        assert!(platform.max_entity_id() <= live + 1 + NBULLETS);
+
+        // let every bullet run out its lifetime
+        for t in 1000..1200 {
+            frame.update(t as f32 / 60.0, 1.0 / 60.0, &Input::default(), Matrix4::identity());
+            gun.render_frame(&mut frame, &mut scene);
+        }
+        assert!(gun.bullets.is_empty());
+        assert_eq!(platform.live_entities(), live);
+
+        gun.destroy(&mut scene);
+        assert_eq!(platform.live_entities(), live - 1);
+    }
+}
@@ -1,50 +1,66 @@
 
 use crate::math::{ Vec3, Matrix4 };
-use crate::engine::{ Frame };
+use crate::physics::convex;
+use crate::engine::{ Frame, Scenegraph, NodeID, RenderDescriptor };
 use crate::engine::entity::{ EntityBehavior };
 use crate::utils;
+use crate::binary::{ Writer, Reader };
+use crate::snapshot::{ SnapshotError };
 
 
 
 pub struct Object3d {
-    id: usize,
+    pub node: NodeID,
     pub position: Vec3,
     pub rotation: Vec3,
     pub scale: Vec3,
+    pub collider: convex::Cuboid,
 }
 
 
-impl Default for Object3d {
-    fn default() -> Self {
+impl Object3d {
+    pub fn new(scene: &mut Scenegraph, descriptor: &RenderDescriptor) -> Self {
+        let id = utils::webgl_add_entity(descriptor);
         Self {
-            id: 0,
+            node: scene.add(scene.root(), Some(id)),
             position: Vec3::zero(),
             rotation: Vec3::zero(),
             scale: Vec3::new(1.0, 1.0, 1.0),
+            collider: convex::Cuboid::cuboid(Vec3::new(1.0, 1.0, 1.0)),
         }
     }
-}
 
+    pub fn destroy(self, scene: &mut Scenegraph) {
+        scene.remove(self.node);
+    }
+
+    pub fn save(&self, out: &mut Writer) {
+        out.vec3(self.position);
+        out.vec3(self.rotation);
+        out.vec3(self.scale);
+    }
 
-impl Object3d {
-    pub fn new(meta: &'static str) -> Self {
-        let mut object = Self::default();
-        object.id = utils::webgl_add_entity(meta);
-        object
+    pub fn load(&mut self, input: &mut Reader) -> Result<(), SnapshotError> {
+        self.position = input.vec3()?;
+        self.rotation = input.vec3()?;
+        self.scale = input.vec3()?;
+        Ok(())
     }
 }
 
 
 impl EntityBehavior for Object3d {
 
-    fn render_frame(&mut self, _: &mut Frame) {
+    fn render_frame(&mut self, _: &mut Frame, _: &mut Scenegraph) {
     }
 
-    fn update_uniforms(&mut self, frame: &mut Frame, mut matrix: Matrix4) {
+    fn update_uniforms(&mut self, _: &mut Frame, scene: &mut Scenegraph) {
+        let mut matrix = Matrix4::identity();
         matrix.translate(self.position);
         matrix.rotate(self.rotation);
         matrix.scale(self.scale);
-        frame.add_view_matrix(self.id, matrix);
+        self.collider.update(&matrix);
+        scene.set_local(self.node, matrix);
     }
 
 }
@@ -1,24 +1,30 @@
 
 use crate::math::{ Vec3, Matrix4 };
 use crate::physics;
-use crate::physics::collisions;
+use crate::physics::{ collisions, convex };
+use crate::physics::collisions::Collider;
 use crate::engine::entity::{ EntityBehavior };
-use crate::engine::{ Key, Frame };
-use crate::game::{ Thruster, Gun };
-use crate::utils;
+use crate::engine::{ Action, Axis, Frame, Scenegraph, NodeID, Counter };
+use crate::game::{ Thruster, Gun, Assets, GameEvent, GameEvents };
+use crate::{ utils, trace };
+use crate::binary::{ Writer, Reader };
+use crate::snapshot::{ self, SnapshotError };
 
 
 
 pub struct Ship {
     pub id: usize,
+    node: NodeID,
     pub position: Vec3,
     pub rotation: Vec3,
-    rigidbody: physics::RigidBody,
+    pub rigidbody: physics::RigidBody,
     thrusters: [Thruster; 8],
     pub gun1: Gun,
     pub gun2: Gun,
-    pub collider: collisions::PolygonCollider<4>,
+    pub collider: collisions::CompoundCollider<5>,
+    pub hull: convex::Cuboid,
     pub colliding: bool,
+    pub damage: [f32; 5],
 }
 
 
@@ -34,172 +40,272 @@ impl Ship {
     const THRUSTER_BACKWARD1: usize    = 6;
     const THRUSTER_BACKWARD2: usize    = 7;
 
+    // turning at full throttle spins the ship too fast to aim
+    const TURN_THROTTLE: f32 = 0.7;
 
-    pub fn new() -> Self {
+    pub const PART_HULL: usize          = 0;
+    pub const PART_GUN1: usize          = 1;
+    pub const PART_GUN2: usize          = 2;
+    pub const PART_THRUSTER_POD1: usize = 3;
+    pub const PART_THRUSTER_POD2: usize = 4;
+
+
+    pub fn new(scene: &mut Scenegraph, assets: &Assets) -> Self {
+        let id = utils::webgl_add_entity(&assets.ship());
+        let node = scene.add(scene.root(), Some(id));
         Self {
-            id: utils::webgl_add_entity(r#"{
-                "shader": "vertex_color",
-                "count": 36,
-                "attributes": {
-                    "a_position": "ship_vertices",
-                    "a_color": "cube_vertex_colors"
-                },
-                "uniforms": {
-                    "objectData": ["u_matrix", "u_collide"]
-                }
-            }"#),
+            id, node,
             position: Vec3::zero(),
             rotation: Vec3::zero(),
             rigidbody: physics::RigidBody::new(
                 1000.0, physics::moi_cuboid(1000.0, 4.0 * 2.0, 6.0 * 2.0)
             ),
-            collider: collisions::PolygonCollider::new([
-                Vec3::new(-4.0, 0.0, -6.0),
-                Vec3::new(4.0, 0.0, -6.0),
-                Vec3::new(4.0, 0.0, 6.0),
-                Vec3::new(-4.0, 0.0, 6.0),
+            collider: collisions::CompoundCollider::new([
+                // PART_HULL
+                (Self::box_collider(4.0, 6.0, 1.0).into(), Matrix4::identity()),
+                // PART_GUN1
+                (
+                    Self::barrel_collider(),
+                    Matrix4::translation(2.0, 0.0, -7.0),
+                ),
+                // PART_GUN2
+                (
+                    Self::barrel_collider(),
+                    Matrix4::translation(-2.0, 0.0, -7.0),
+                ),
+                // PART_THRUSTER_POD1
+                (
+                    collisions::CircleCollider::new(0.75).into(),
+                    Matrix4::translation(-2.0, 0.0, 6.75),
+                ),
+                // PART_THRUSTER_POD2
+                (
+                    collisions::CircleCollider::new(0.75).into(),
+                    Matrix4::translation(2.0, 0.0, 6.75),
+                ),
             ]),
+            hull: convex::Cuboid::cuboid(Vec3::new(4.0, 5.0, 6.0)),
             colliding: false,
+            damage: [0.0; 5],
             thrusters: [
                 // THRUSTER_LEFT_TOP,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(-4.0, 0.0, -4.0), Vec3::new(-1.0, 0.0, 0.0), 100.0,
                 ),
                 // THRUSTER_RIGHT_TOP,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(4.0, 0.0, -4.0), Vec3::new(1.0, 0.0, 0.0), 100.0,
                 ),
                 // THRUSTER_LEFT_BOTTOM,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(-4.0, 0.0, 4.0), Vec3::new(-1.0, 0.0, 0.0), 100.0,
                 ),
                 // THRUSTER_RIGHT_BOTTOM,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(4.0, 0.0, 4.0), Vec3::new(1.0, 0.0, 0.0), 100.0,
                 ),
                 // THRUSTER_FORWARD1,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(-2.0, 0.0, 6.0), Vec3::new(0.0, 0.0, 1.0), 300.0,
                 ),
                 // THRUSTER_FORWARD2,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(2.0, 0.0, 6.0), Vec3::new(0.0, 0.0, 1.0), 300.0,
                 ),
                 // THRUSTER_BACKWARD1,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(-1.0, 0.0, -6.0), Vec3::new(0.0, 0.0, -1.0), 100.0,
                 ),
                 // THRUSTER_BACKWARD2,
                 Thruster::new(
+                    scene, assets, node,
                     Vec3::new(1.0, 0.0, -6.0), Vec3::new(0.0, 0.0, -1.0), 100.0,
                 ),
             ],
             gun1: Gun::new(
-                Vec3::new(2.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0)
+                scene, assets, node, Vec3::new(2.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0)
             ),
             gun2: Gun::new(
-                Vec3::new(-2.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0)
+                scene, assets, node, Vec3::new(-2.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0)
             ),
         }
     }
 
+    // box with half extents `w` and `h`, corners rounded by `r` so it
+    // slides along asteroid edges instead of snagging on them
+    fn box_collider(w: f32, h: f32, r: f32) -> collisions::PolygonCollider<4> {
+        let (w, h) = (w - r, h - r);
+        collisions::PolygonCollider::rounded([
+            Vec3::new(-w, 0.0, -h),
+            Vec3::new(w, 0.0, -h),
+            Vec3::new(w, 0.0, h),
+            Vec3::new(-w, 0.0, h),
+        ], r)
+    }
+
+    fn barrel_collider() -> collisions::Part {
+        collisions::CapsuleCollider::new(
+            Vec3::new(0.0, 0.0, -0.5), Vec3::new(0.0, 0.0, 0.5), 0.5
+        ).into()
+    }
+
+    pub fn set_integrator(&mut self, integrator: physics::Integrator) {
+        self.rigidbody.integrator = integrator;
+        self.gun1.set_integrator(integrator);
+        self.gun2.set_integrator(integrator);
+    }
+
+    pub fn hit(&mut self, part: usize, amount: f32, events: &mut GameEvents) {
+        self.damage[part] += amount;
+        self.colliding = true;
+        events.publish(GameEvent::ShipDamaged { part, amount });
+    }
+
+    pub fn save(&self, out: &mut Writer) {
+        out.vec3(self.position);
+        out.vec3(self.rotation);
+        snapshot::save_rigidbody(out, &self.rigidbody);
+        for damage in self.damage {
+            out.f32(damage);
+        }
+        self.gun1.save(out);
+        self.gun2.save(out);
+    }
+
+    pub fn load(
+        &mut self, input: &mut Reader, scene: &mut Scenegraph
+    ) -> Result<(), SnapshotError> {
+        self.position = input.vec3()?;
+        self.rotation = input.vec3()?;
+        snapshot::load_rigidbody(input, &mut self.rigidbody)?;
+        self.set_integrator(self.rigidbody.integrator);
+        for damage in self.damage.iter_mut() {
+            *damage = input.f32()?;
+        }
+        self.gun1.load(input, scene)?;
+        self.gun2.load(input, scene)
+    }
+
+    // thrusters are children of the ship node and go with it
+    pub fn destroy(self, scene: &mut Scenegraph) {
+        self.gun1.destroy(scene);
+        self.gun2.destroy(scene);
+        scene.remove(self.node);
+    }
+
 }
 
 
-impl EntityBehavior for Ship {
+impl Ship {
 
-    fn render_frame(&mut self, frame: &mut Frame) {
+    // fires thrusters and guns from the input axes and actions this frame,
+    // thrusters are throttled by how far each axis is pushed
+    pub fn handle_input(
+        &mut self, frame: &Frame, scene: &mut Scenegraph, events: &mut GameEvents
+    ) {
         let rigidbody = &mut self.rigidbody;
-
-        if frame.pressed(Key::W) {
-            self.thrusters[Self::THRUSTER_FORWARD1].fire(rigidbody, 1.0);
-            self.thrusters[Self::THRUSTER_FORWARD2].fire(rigidbody, 1.0);
+        let thrusters = &mut self.thrusters;
+        let mut fire = |pair: [usize; 2], throttle: f32| {
+            for thruster in pair {
+                thrusters[thruster].fire(scene, rigidbody, throttle);
+                events.publish(GameEvent::ThrusterFired { thruster, throttle });
+            }
+        };
+
+        let thrust = frame.axis(Axis::Thrust);
+        if thrust > 0.0 {
+            fire([Self::THRUSTER_FORWARD1, Self::THRUSTER_FORWARD2], thrust);
         }
-        if frame.pressed(Key::S) {
-            self.thrusters[Self::THRUSTER_BACKWARD1].fire(rigidbody, 1.0);
-            self.thrusters[Self::THRUSTER_BACKWARD2].fire(rigidbody, 1.0);
+        if thrust < 0.0 {
+            fire([Self::THRUSTER_BACKWARD1, Self::THRUSTER_BACKWARD2], -thrust);
         }
 
-        if frame.pressed(Key::A) {
-            self.thrusters[Self::THRUSTER_LEFT_BOTTOM].fire(rigidbody, 0.7);
-            self.thrusters[Self::THRUSTER_RIGHT_TOP].fire(rigidbody, 0.7);
+        let turn = frame.axis(Axis::Turn) * Self::TURN_THROTTLE;
+        if turn < 0.0 {
+            fire([Self::THRUSTER_LEFT_BOTTOM, Self::THRUSTER_RIGHT_TOP], -turn);
         }
-        if frame.pressed(Key::D) {
-            self.thrusters[Self::THRUSTER_LEFT_TOP].fire(rigidbody, 0.7);
-            self.thrusters[Self::THRUSTER_RIGHT_BOTTOM].fire(rigidbody, 0.7);
+        if turn > 0.0 {
+            fire([Self::THRUSTER_LEFT_TOP, Self::THRUSTER_RIGHT_BOTTOM], turn);
         }
 
-        if frame.pressed(Key::Q) {
-            self.thrusters[Self::THRUSTER_LEFT_TOP].fire(rigidbody, 1.0);
-            self.thrusters[Self::THRUSTER_LEFT_BOTTOM].fire(rigidbody, 1.0);
+        let strafe = frame.axis(Axis::Strafe);
+        if strafe < 0.0 {
+            fire([Self::THRUSTER_LEFT_TOP, Self::THRUSTER_LEFT_BOTTOM], -strafe);
         }
-        if frame.pressed(Key::E) {
-            self.thrusters[Self::THRUSTER_RIGHT_TOP].fire(rigidbody, 1.0);
-            self.thrusters[Self::THRUSTER_RIGHT_BOTTOM].fire(rigidbody, 1.0);
+        if strafe > 0.0 {
+            fire([Self::THRUSTER_RIGHT_TOP, Self::THRUSTER_RIGHT_BOTTOM], strafe);
         }
 
-        if frame.pressed(Key::Space) {
-            // if frame.t % 10.0 == 0.0 {
-                self.gun1.shoot(frame, self.rotation, rigidbody);
-            // } else {
-                self.gun2.shoot(frame, self.rotation, rigidbody);
-            // }
+        if frame.pressed(Action::Fire) {
+            self.gun1.shoot(frame, scene, self.rotation, &mut self.rigidbody, events);
+            self.gun2.shoot(frame, scene, self.rotation, &mut self.rigidbody, events);
         }
+    }
 
+    pub fn update_physics(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        let rigidbody = &mut self.rigidbody;
         rigidbody.apply_damping(250.0);
         rigidbody.update_physics(
             frame.dt, &mut self.position, &mut self.rotation
         );
-        // self.rotation.y = -3.14 / 4.0;
+        frame.profiler.count(Counter::BodiesIntegrated, 1);
+        trace!(
+            "ship at {:?} rotation {:?} velocity {:?}",
+            self.position, self.rotation, self.rigidbody.velocity()
+        );
 
-        // self.aabb.update(self.position);
+        self.gun1.render_frame(frame, scene);
+        self.gun2.render_frame(frame, scene);
 
-        // for v in self.collider.vertices.iter() {
-        //     crate::utils::console_log(
-        //         format!("{:?}", v).as_str()
-        //     );
-        // }
+        for thruster in self.thrusters.iter_mut() {
+            thruster.render_frame(frame, scene);
+        }
+    }
 
-        // for v in self.collider.world_axes.iter() {
-        //     crate::utils::console_log(
-        //         format!("axis {:?}", v).as_str()
-        //     );
-        // }
+}
 
-        //     crate::utils::console_log(
-        //         format!("---").as_str()
-        //     );
-        self.gun1.render_frame(frame);
-        self.gun2.render_frame(frame);
 
-        for thruster in self.thrusters.iter_mut() {
-            thruster.render_frame(frame);
-        }
+impl EntityBehavior for Ship {
+
+    // input is handled separately by `handle_input`, it needs the event
+    // queue to publish to
+    fn render_frame(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        self.update_physics(frame, scene);
     }
 
 
-    fn update_uniforms(&mut self, frame: &mut Frame, mut matrix: Matrix4) {
+    fn update_uniforms(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        let mut matrix = Matrix4::identity();
         matrix.translate(self.position);
         matrix.rotate(self.rotation);
-        frame.add_view_matrix(self.id, matrix);
+        scene.set_local(self.node, matrix);
 
         self.collider.update(&matrix);
+        self.hull.update(&matrix);
+
+        self.collider.debug_draw(&mut frame.debug);
+        physics::debug::draw_velocity(&mut frame.debug, self.position, &self.rigidbody);
 
-        // crate::utils::console_log(
-        //     format!("ship collide {:?}", self.aabb.colliding).as_str()
-        // );
+        trace!("ship colliding {}", self.colliding);
         if self.colliding {
-            frame.buffer.add_float(self.id, 0.0, 1.0, 1.0);
+            frame.buffer.add_float(self.id, 0, 1, 1.0);
             self.colliding = false;
         } else {
-            frame.buffer.add_float(self.id, 0.0, 1.0, 0.0);
+            frame.buffer.add_float(self.id, 0, 1, 0.0);
         }
 
-        self.gun1.update_uniforms(frame, matrix);
-        self.gun2.update_uniforms(frame, matrix);
+        self.gun1.update_uniforms(frame, scene);
+        self.gun2.update_uniforms(frame, scene);
 
         for thruster in self.thrusters.iter_mut() {
-            thruster.update_uniforms(frame, matrix);
+            thruster.update_uniforms(frame, scene);
         }
     }
 
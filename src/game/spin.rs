@@ -0,0 +1,15 @@
+use crate::math::Vec3;
+
+
+
+// Spins an object at a constant rate, in radians per second
+pub struct Spin {
+    pub rate: Vec3,
+}
+
+
+impl Spin {
+    pub fn new(rate: Vec3) -> Self {
+        Self { rate }
+    }
+}
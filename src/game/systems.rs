@@ -0,0 +1,206 @@
+use crate::engine::{ Frame, Counter };
+use crate::engine::ecs::{ self, Stage, Schedule };
+use crate::engine::entity::{ EntityBehavior };
+use crate::physics::collisions::Collider;
+use crate::physics::{ self, convex };
+use crate::game::{ World, Ship, TimerEvent, GameEvent };
+
+
+
+const RESTITUTION: f32 = 0.5;
+// seconds an asteroid flashes for after a bullet hit
+const HIT_FLASH: f32 = 0.15;
+
+
+
+pub fn schedule<const N: usize>() -> Schedule<World, N> {
+    let mut schedule = Schedule::empty();
+    schedule.add(Stage::Input, ship_input);
+    schedule.add(Stage::Ai, timers);
+    schedule.add(Stage::Ai, spin);
+    schedule.add(Stage::Physics, ship_physics);
+    schedule.add(Stage::Physics, asteroid_physics);
+    schedule.add(Stage::Collision, ship_asteroid_collisions);
+    schedule.add(Stage::Collision, asteroid_collisions);
+    schedule.add(Stage::Collision, ship_object_collisions);
+    schedule.add(Stage::Events, hit_flash);
+    schedule.add(Stage::RenderUpload, render_upload);
+    schedule
+}
+
+
+// --------------------------------------------------------
+
+
+fn ship_input(world: &mut World, frame: &mut Frame) {
+    for ship in world.ships.components_mut() {
+        ship.handle_input(frame, &mut world.scene, &mut world.events);
+    }
+}
+
+
+fn timers(world: &mut World, frame: &mut Frame) {
+    let asteroids = &mut world.asteroids;
+    world.timers.update(frame.t, |owner, event| match event {
+        TimerEvent::EndFlash => {
+            if let Some(asteroid) = owner.and_then(|e| asteroids.get_mut(e)) {
+                asteroid.flashing = false;
+            }
+        },
+    });
+}
+
+
+// hit asteroids flash for a moment, a new hit starts the flash over
+fn hit_flash(world: &mut World, _: &mut Frame) {
+    for event in world.events.iter() {
+        if let GameEvent::AsteroidHit { asteroid: entity, .. } = *event {
+            if let Some(asteroid) = world.asteroids.get_mut(entity) {
+                asteroid.flashing = true;
+                world.timers.restart_for(entity, HIT_FLASH, TimerEvent::EndFlash);
+            }
+        }
+    }
+}
+
+
+fn spin(world: &mut World, frame: &mut Frame) {
+    ecs::query_mut(&mut world.objects, &world.spins, |_, object, spin| {
+        object.rotation += spin.rate * frame.dt;
+    });
+}
+
+
+fn ship_physics(world: &mut World, frame: &mut Frame) {
+    for ship in world.ships.components_mut() {
+        ship.update_physics(frame, &mut world.scene);
+    }
+}
+
+
+fn asteroid_physics(world: &mut World, frame: &mut Frame) {
+    for asteroid in world.asteroids.components_mut() {
+        asteroid.render_frame(frame, &mut world.scene);
+    }
+}
+
+
+// --------------------------------------------------------
+
+
+fn ship_asteroid_collisions(world: &mut World, frame: &mut Frame) {
+    for ship in world.ships.components_mut() {
+        for (entity, asteroid) in world.asteroids.iter_mut() {
+            frame.profiler.count(Counter::CollisionTests, 1);
+
+            if let Some((part, sep_axis, min_depth)) = ship.collider.collide_part(&asteroid.collider) {
+                asteroid.colliding = true;
+                ship.hit(part, min_depth, &mut world.events);
+
+                ship.position += -sep_axis * (min_depth / 2.0);
+                asteroid.position += sep_axis * (min_depth / 2.0);
+
+                let contact = asteroid.collider.support(-sep_axis);
+                physics::debug::draw_contact(&mut frame.debug, contact, sep_axis, min_depth);
+                physics::resolve_collision(
+                    &mut ship.rigidbody, contact - ship.position,
+                    &mut asteroid.rigidbody, contact - asteroid.position,
+                    sep_axis, RESTITUTION,
+                );
+            }
+
+            let bullets = ship.gun1.bullets.iter_mut()
+                .chain(ship.gun2.bullets.iter_mut());
+
+            for bullet in bullets.filter(|bullet| bullet.live) {
+                frame.profiler.count(Counter::CollisionTests, 1);
+                if asteroid.collider.collide(&bullet.collider).is_some() {
+                    bullet.live = false;
+
+                    // the bullet is absorbed, so all of its momentum relative
+                    // to the point it hits ends up in the asteroid
+                    let distance = bullet.position - asteroid.position;
+                    let relative_velocity = bullet.rigidbody.velocity()
+                        - asteroid.rigidbody.velocity_at(distance);
+                    let impulse = relative_velocity * bullet.rigidbody.mass;
+                    asteroid.rigidbody.apply_impulse(impulse, distance);
+
+                    world.events.publish(GameEvent::AsteroidHit {
+                        asteroid: entity, position: bullet.position, impulse,
+                    });
+                }
+            }
+        }
+    }
+}
+
+
+fn asteroid_collisions(world: &mut World, frame: &mut Frame) {
+    let mut tests = 0;
+    world.asteroids.for_each_pair_mut(|a, b| {
+        tests += 1;
+        if let Some((sep_axis, min_depth)) = a.collider.collide(&b.collider) {
+            a.colliding = true;
+            b.colliding = true;
+
+            a.position += -sep_axis * (min_depth / 2.0);
+            b.position += sep_axis * (min_depth / 2.0);
+
+            let contact = (
+                a.collider.support(sep_axis) + b.collider.support(-sep_axis)
+            ) * 0.5;
+            physics::debug::draw_contact(&mut frame.debug, contact, sep_axis, min_depth);
+            physics::resolve_collision(
+                &mut a.rigidbody, contact - a.position,
+                &mut b.rigidbody, contact - b.position,
+                sep_axis, RESTITUTION,
+            );
+        }
+    });
+    frame.profiler.count(Counter::CollisionTests, tests);
+}
+
+
+// the large cubes float above and below the play area, so only a full
+// 3D test can tell if the ship actually touches them
+fn ship_object_collisions(world: &mut World, frame: &mut Frame) {
+    for ship in world.ships.components_mut() {
+        for object in world.objects.components_mut() {
+            frame.profiler.count(Counter::CollisionTests, 1);
+            if let Some(contact) = convex::collide(&ship.hull, &object.collider) {
+                ship.hit(Ship::PART_HULL, contact.depth, &mut world.events);
+                physics::debug::draw_contact(
+                    &mut frame.debug, contact.point, contact.normal, contact.depth
+                );
+                ship.position += -contact.normal * contact.depth;
+
+                // the objects don't move, bounce the ship off them so it
+                // stops pushing into them
+                physics::resolve_static_collision(
+                    &mut ship.rigidbody, contact.point - ship.position,
+                    contact.normal, RESTITUTION,
+                );
+            }
+        }
+    }
+}
+
+
+// --------------------------------------------------------
+
+
+pub fn render_upload(world: &mut World, frame: &mut Frame) {
+    for ship in world.ships.components_mut() {
+        ship.update_uniforms(frame, &mut world.scene);
+    }
+
+    for object in world.objects.components_mut() {
+        object.update_uniforms(frame, &mut world.scene);
+    }
+
+    for asteroid in world.asteroids.components_mut() {
+        asteroid.update_uniforms(frame, &mut world.scene);
+    }
+
+    world.scene.update(frame);
+}
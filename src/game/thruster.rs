@@ -1,81 +1,90 @@
-
 use crate::math::{ Vec3, Matrix4 };
-use crate::engine::{ Frame };
-use crate::physics::{ RigidBody };
+use crate::engine::{ Frame, Scenegraph, NodeID };
+use crate::physics::{ RigidBody, debug };
 use crate::engine::entity::{ EntityBehavior };
+use crate::game::Assets;
 use crate::utils;
 
 
 
 pub struct Thruster {
-    id: usize,
+    node: NodeID,
     position: Vec3,
     direction: Vec3,
     thrust: f32,
     firing: bool,
-    matrix: Matrix4,
+    throttle: f32,
 }
 
 
 
 impl Thruster {
 
-    pub fn new(position: Vec3, direction: Vec3, thrust: f32) -> Self {
+    pub fn new(
+        scene: &mut Scenegraph, assets: &Assets, parent: NodeID,
+        position: Vec3, direction: Vec3, thrust: f32,
+    ) -> Self {
+        let id = utils::webgl_add_entity(&assets.quad());
         Self {
-            id: utils::webgl_add_entity(r#"{
-                "shader": "test",
-                "count": 6,
-                "attributes": {
-                    "a_position": "quad"
-                },
-                "uniforms": {
-                    "objectData": ["u_matrix"]
-                }
-            }"#),
+            node: scene.add(parent, Some(id)),
             position,
             direction: direction.unit(),
             thrust: thrust * 1000.0,   // in kN
             firing: false,
-            matrix: Matrix4::identity(),
+            throttle: 0.0,
         }
     }
 
     const ORIGIN: Vec3 = Vec3::zero();
 
-    pub fn fire(&mut self, rigidbody: &mut RigidBody, throttle: f32) {
+    pub fn fire(
+        &mut self, scene: &Scenegraph, rigidbody: &mut RigidBody, throttle: f32
+    ) {
         rigidbody.apply_force_and_torque(
-            -self.exhaust_dir() * (self.thrust * throttle),
-            self.distance_from_center()
+            -self.exhaust_dir(scene) * (self.thrust * throttle),
+            self.distance_from_center(scene)
         );
         self.firing = true;
+        self.throttle = throttle;
+    }
+
+    // world matrix of whatever the thruster is mounted on
+    fn mount(&self, scene: &Scenegraph) -> Matrix4 {
+        scene.parent(self.node).map_or(Matrix4::identity(), |p| scene.world(p))
     }
 
-    pub fn exhaust_dir(&self) -> Vec3 {
-        let center = self.matrix * Self::ORIGIN;
-        ((self.matrix * self.direction) - center).unit()
+    pub fn exhaust_dir(&self, scene: &Scenegraph) -> Vec3 {
+        let matrix = self.mount(scene);
+        let center = matrix * Self::ORIGIN;
+        ((matrix * self.direction) - center).unit()
     }
 
-    pub fn distance_from_center(&self) -> Vec3 {
-        let center = self.matrix * Self::ORIGIN;
-        (self.matrix * self.position) - center
+    pub fn distance_from_center(&self, scene: &Scenegraph) -> Vec3 {
+        let matrix = self.mount(scene);
+        let center = matrix * Self::ORIGIN;
+        (matrix * self.position) - center
     }
 
 }
 
 impl EntityBehavior for Thruster {
 
-    fn render_frame(&mut self, _: &mut Frame) {
+    fn render_frame(&mut self, _: &mut Frame, _: &mut Scenegraph) {
     }
 
-    fn update_uniforms(&mut self, frame: &mut Frame, mut matrix: Matrix4) {
-        self.matrix = matrix.clone();
+    fn update_uniforms(&mut self, frame: &mut Frame, scene: &mut Scenegraph) {
+        let mut matrix = Matrix4::identity();
         if self.firing {
+            debug::draw_force(
+                &mut frame.debug, self.mount(scene) * self.position,
+                -self.exhaust_dir(scene) * (self.thrust * self.throttle),
+            );
             matrix.translate(
                 self.position + (self.direction * 1.0 * 4.0)
             );
             self.firing = false;
         }
-        frame.add_view_matrix(self.id, matrix);
+        scene.set_local(self.node, matrix);
     }
 
 }
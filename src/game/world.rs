@@ -0,0 +1,71 @@
+use crate::engine::{ Scenegraph, Timers };
+use crate::engine::ecs::{ Entities, Entity, Storage };
+use crate::game::{ Ship, Asteroid, Object3d, Spin, GameEvents };
+
+
+
+// Every object in the game is an entity with components in these storages,
+// systems in `game::systems` query them each frame.
+pub struct World {
+    pub entities: Entities,
+    pub scene: Scenegraph,
+    pub ships: Storage<Ship, 1>,
+    pub asteroids: Storage<Asteroid, 20>,
+    pub objects: Storage<Object3d, 10>,
+    pub spins: Storage<Spin, 10>,
+    pub timers: Timers<TimerEvent, 32>,
+    pub events: GameEvents,
+}
+
+
+// delayed gameplay events, see `systems::timers`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimerEvent {
+    // a hit asteroid stops flashing
+    EndFlash,
+}
+
+
+impl World {
+
+    pub fn empty() -> Self {
+        Self {
+            entities: Entities::empty(),
+            scene: Scenegraph::empty(),
+            ships: Storage::empty(),
+            asteroids: Storage::empty(),
+            objects: Storage::empty(),
+            spins: Storage::empty(),
+            timers: Timers::empty(),
+            events: GameEvents::empty(),
+        }
+    }
+
+    // removes the entity with all of its components and their webgl entities
+    pub fn despawn(&mut self, entity: Entity) {
+        if let Some(ship) = self.ships.remove(entity) {
+            ship.destroy(&mut self.scene);
+        }
+        if let Some(asteroid) = self.asteroids.remove(entity) {
+            asteroid.destroy(&mut self.scene);
+        }
+        if let Some(object) = self.objects.remove(entity) {
+            object.destroy(&mut self.scene);
+        }
+        self.spins.remove(entity);
+        self.timers.cancel_entity(entity);
+        self.entities.destroy(entity);
+    }
+
+    pub fn clear(&mut self) {
+        loop {
+            let Some(entity) = self.entities.iter().next() else { break };
+            self.despawn(entity);
+        }
+    }
+
+    pub fn ship(&self) -> Option<&Ship> {
+        self.ships.iter().next().map(|(_, ship)| ship)
+    }
+
+}
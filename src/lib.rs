@@ -1,99 +1,25 @@
-
-mod engine;
-mod math;
-mod physics;
-mod utils;
-mod game;
-mod prng;
-
-use crate::engine::{Engine, Camera, Frame};
-use crate::math::{Vec3, PI};
-use crate::game::{Game};
-
-
-#[link(wasm_import_module = "imports")]
-extern {
-
-    fn js_console_log_raw(x: *const u8, l: usize);
-
-    fn js_console_error_raw(x: *const u8, l: usize);
-
-    fn js_add_shader(
-        name_ptr: *const u8, name_len: usize,
-        vert_ptr: *const u8, vert_len: usize,
-        frag_ptr: *const u8, frag_len: usize,
-    );
-
-    fn js_add_buffer_float(
-        name_ptr: *const u8, name_len: usize,
-        data_ptr: *const u8, data_len: usize,
-        size: f32, normalize: bool
-    );
-
-    fn js_add_buffer_bytes(
-        name_ptr: *const u8, name_len: usize,
-        data_ptr: *const u8, data_len: usize,
-        size: f32, normalize: bool
-    );
-
-    fn js_add_entity(ptr: *const u8, len: usize) -> usize;
-
-    // fn js_destroy_entity(id: usize);
-
-}
-
-
-
-#[no_mangle]
-pub extern fn init() -> *mut Engine {
-
-    utils::set_panic_hook();
-
-    add_shader!(vertex_color);
-    add_shader!(test);
-
-    add_buffer!(float, cube_vertices, 3.0, false);
-    add_buffer!(float, ship_vertices, 3.0, false);
-    add_buffer!(bytes, cube_vertex_colors, 3.0, true);
-    add_buffer!(float, quad, 3.0, false);
-
-    let engine = Engine {
-        camera: Camera::perspective(
-            Vec3::new(0.0, 300.0, 0.1),
-            25.0, 1.0, 1.0, 4000.0
-        ),
-        frame: Frame::new(),
-        game: Game::new(),
-    };
-
-    Box::into_raw(Box::new(engine))
-}
-
-
-
-#[no_mangle]
-pub extern fn get_framebuffer_pointer(ptr: *mut Engine) -> *const f32 {
-    let engine = unsafe {
-        assert!(!ptr.is_null());
-        &mut *ptr
-    };
-    engine.frame.buffer.as_ptr()
-}
-
-
-
-#[no_mangle]
-pub extern fn render(ptr: *mut Engine, t: f32, dt: f32, keys: u8) {
-    let engine = unsafe {
-        assert!(!ptr.is_null());
-        &mut *ptr
-    };
-
-    engine.frame.update(
-        t, dt, keys, engine.camera.view_projection_matrix()
-    );
-
-    engine.game.render_frame(&mut engine.frame, &mut engine.camera);
-
-    engine.frame.buffer.update_length()
-}
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod engine;
+pub mod math;
+pub mod physics;
+pub mod utils;
+pub mod prng;
+pub mod platform;
+pub mod log;
+
+// the game and everything that needs an allocator
+#[cfg(feature = "std")]
+pub mod game;
+#[cfg(feature = "std")]
+pub mod binary;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+mod exports;
+#[cfg(feature = "std")]
+pub use exports::load_assets;
+
+use crate::math::{ PI };
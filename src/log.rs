@@ -0,0 +1,359 @@
+// Leveled logging that formats into a fixed size buffer on the stack, so
+// logging doesn't allocate. Use the `trace!`, `debug!`, `info!`, `warn!` and
+// `error!` macros, they take the same arguments as `format!`:
+//
+//     warn!("bullet {} outlived its gun", id);
+//
+// Every message belongs to the module it was logged from. Each module can
+// have its own level, set with `set_level` with a path like "game::ship"
+// which also covers the modules inside it. Modules without a level of their
+// own use the default level.
+
+use core::cell::RefCell;
+use core::fmt::{ self, Write };
+
+use crate::platform;
+
+
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    // only used as a filter, turns a module off
+    Off,
+}
+
+
+impl Level {
+
+    pub fn from_u32(value: u32) -> Option<Self> {
+        [Level::Trace, Level::Debug, Level::Info, Level::Warn, Level::Error, Level::Off]
+            .get(value as usize).copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Trace => "trace",
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Off => "off",
+        }
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+const MAX_FILTERS: usize = 16;
+const MAX_MODULE_LEN: usize = 48;
+const MESSAGE_SIZE: usize = 256;
+// ends messages that were cut off, there is always room left for it
+const ELLIPSIS: &str = "...";
+
+const DEFAULT_LEVEL: Level = Level::Info;
+
+
+#[derive(Clone, Copy)]
+struct Filter {
+    module: [u8; MAX_MODULE_LEN],
+    len: usize,
+    level: Level,
+}
+
+
+impl Filter {
+
+    fn module(&self) -> &str {
+        core::str::from_utf8(&self.module[0..self.len]).unwrap_or("")
+    }
+
+    // "game" covers "game" and "game::ship" but not "gameplay"
+    fn covers(&self, module: &str) -> bool {
+        let name = self.module();
+        module.strip_prefix(name)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    }
+
+}
+
+
+struct Filters {
+    default: Level,
+    filters: [Option<Filter>; MAX_FILTERS],
+}
+
+
+impl Filters {
+
+    const fn new() -> Self {
+        Self { default: DEFAULT_LEVEL, filters: [None; MAX_FILTERS] }
+    }
+
+    // the most specific filter covering the module decides
+    fn level(&self, module: &str) -> Level {
+        self.filters.iter().flatten()
+            .filter(|filter| filter.covers(module))
+            .max_by_key(|filter| filter.len)
+            .map_or(self.default, |filter| filter.level)
+    }
+
+}
+
+
+#[cfg(feature = "std")]
+thread_local! {
+    static FILTERS: RefCell<Filters> = const { RefCell::new(Filters::new()) };
+}
+
+#[cfg(feature = "std")]
+fn with_filters<R>(func: impl FnOnce(&RefCell<Filters>) -> R) -> R {
+    FILTERS.with(func)
+}
+
+
+// without std there are no thread locals, the filters are a plain static
+#[cfg(not(feature = "std"))]
+struct StaticFilters(RefCell<Filters>);
+
+// wasm32 without threads, the static is only ever used from one thread
+#[cfg(not(feature = "std"))]
+unsafe impl Sync for StaticFilters {}
+
+#[cfg(not(feature = "std"))]
+static FILTERS: StaticFilters = StaticFilters(RefCell::new(Filters::new()));
+
+#[cfg(not(feature = "std"))]
+fn with_filters<R>(func: impl FnOnce(&RefCell<Filters>) -> R) -> R {
+    func(&FILTERS.0)
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterError {
+    TooLong,
+    TooMany,
+}
+
+
+// sets the level of a module and the ones inside it, an empty path sets the
+// default level
+pub fn set_level(module: &str, level: Level) -> Result<(), FilterError> {
+    let module = module.trim_matches(':');
+    if module.len() > MAX_MODULE_LEN { return Err(FilterError::TooLong); }
+    with_filters(|filters| {
+        let mut filters = filters.borrow_mut();
+        if module.is_empty() {
+            filters.default = level;
+            return Ok(());
+        }
+        let slot = match filters.filters.iter().position(|f| {
+            f.as_ref().is_some_and(|f| f.module() == module)
+        }) {
+            Some(slot) => slot,
+            None => filters.filters.iter().position(Option::is_none)
+                .ok_or(FilterError::TooMany)?,
+        };
+        let mut filter = Filter { module: [0; MAX_MODULE_LEN], len: module.len(), level };
+        filter.module[0..module.len()].copy_from_slice(module.as_bytes());
+        filters.filters[slot] = Some(filter);
+        Ok(())
+    })
+}
+
+// back to the default level for every module
+pub fn reset_levels() {
+    with_filters(|filters| *filters.borrow_mut() = Filters::new());
+}
+
+// `module` is a `module_path!()`, the crate name is left out of filters
+pub fn enabled(level: Level, module: &str) -> bool {
+    level != Level::Off && level >= with_filters(|f| f.borrow().level(short_path(module)))
+}
+
+pub fn short_path(module: &str) -> &str {
+    module.split_once("::").map_or("", |(_, rest)| rest)
+}
+
+
+// --------------------------------------------------------
+
+
+// Messages longer than the buffer are cut off and end in "..."
+pub struct MessageBuffer {
+    bytes: [u8; MESSAGE_SIZE],
+    len: usize,
+    truncated: bool,
+}
+
+
+impl MessageBuffer {
+
+    pub fn new() -> Self {
+        Self { bytes: [0; MESSAGE_SIZE], len: 0, truncated: false }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only whole characters are written
+        core::str::from_utf8(&self.bytes[0..self.len]).unwrap_or("")
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+}
+
+
+impl Default for MessageBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+impl Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated { return Ok(()); }
+        let room = (MESSAGE_SIZE - ELLIPSIS.len()).saturating_sub(self.len);
+        if s.len() <= room {
+            self.bytes[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+            self.len += s.len();
+            return Ok(());
+        }
+        let mut end = room;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.write_str(&s[0..end])?;
+        let ellipsis = ELLIPSIS.len().min(MESSAGE_SIZE - self.len);
+        self.bytes[self.len..self.len + ellipsis].copy_from_slice(&ELLIPSIS.as_bytes()[0..ellipsis]);
+        self.len += ellipsis;
+        self.truncated = true;
+        Ok(())
+    }
+}
+
+
+// called by the macros once the level is known to be enabled
+pub fn write(level: Level, module: &'static str, args: fmt::Arguments) {
+    let mut message = MessageBuffer::new();
+    let _ = message.write_fmt(args);
+    platform::with(|p| p.log(level, short_path(module), message.as_str()));
+}
+
+
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {
+        {
+            let level = $level;
+            if $crate::log::enabled(level, module_path!()) {
+                $crate::log::write(level, module_path!(), format_args!($($arg)+));
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Trace, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Debug, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Info, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Warn, $($arg)+) }
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Error, $($arg)+) }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::{ HeadlessPlatform, Call };
+
+    fn logged(platform: &HeadlessPlatform) -> Vec<(Level, String, String)> {
+        platform.calls().into_iter().filter_map(|call| match call {
+            Call::Log { level, module, text } => Some((level, module, text)),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn levels_are_filtered_per_module() {
+        let platform = HeadlessPlatform::install();
+        reset_levels();
+
+        crate::debug!("hidden {}", 1);
+        crate::info!("shown {} {:?}", 2, [1.5]);
+        assert_eq!(logged(&platform), [
+            (Level::Info, "log::tests".to_string(), "shown 2 [1.5]".to_string()),
+        ]);
+
+        set_level("log", Level::Trace).unwrap();
+        set_level("log::tests", Level::Error).unwrap();
+        assert!(enabled(Level::Trace, "neutrino_demo::log"));
+        assert!(!enabled(Level::Warn, "neutrino_demo::log::tests"));
+        assert!(!enabled(Level::Trace, "neutrino_demo::logging"));
+        assert!(enabled(Level::Info, "neutrino_demo::game"));
+
+        set_level("log::tests", Level::Off).unwrap();
+        crate::error!("off");
+        set_level("", Level::Warn).unwrap();
+        assert!(!enabled(Level::Info, "neutrino_demo::game"));
+        assert_eq!(logged(&platform).len(), 1);
+
+        let long = "x".repeat(MAX_MODULE_LEN + 1);
+        assert_eq!(set_level(&long, Level::Info), Err(FilterError::TooLong));
+        reset_levels();
+    }
+
+    #[test]
+    fn long_messages_are_cut_off() {
+        let mut message = MessageBuffer::new();
+        write!(message, "{}", "é".repeat(MESSAGE_SIZE)).unwrap();
+        write!(message, "more").unwrap();
+        assert!(message.is_truncated());
+        assert!(message.as_str().ends_with("é..."));
+        assert!(message.as_str().len() <= MESSAGE_SIZE);
+    }
+
+    #[test]
+    fn writes_after_a_full_message_are_cut_off() {
+        let mut message = MessageBuffer::new();
+        write!(message, "{}", "a".repeat(MESSAGE_SIZE - ELLIPSIS.len())).unwrap();
+        assert!(!message.is_truncated());
+        write!(message, "ab").unwrap();
+        assert!(message.is_truncated());
+        assert_eq!(message.as_str().len(), MESSAGE_SIZE);
+        assert!(message.as_str().ends_with("a..."));
+
+        let mut message = MessageBuffer::new();
+        write!(message, "{}", "a".repeat(MESSAGE_SIZE - 1)).unwrap();
+        write!(message, "ab").unwrap();
+        assert_eq!(message.as_str().len(), MESSAGE_SIZE);
+        assert!(message.as_str().ends_with("a..."));
+    }
+}
@@ -5,4 +5,7 @@ pub mod matrix;
 pub use vector::Vec3;
 pub use matrix::Matrix4;
 
+pub mod float;
+pub use float::Float;
+
 pub const PI: f32 = core::f32::consts::PI;
@@ -0,0 +1,43 @@
+// The f32 methods that come from std, backed by libm for `no_std` builds.
+// std's own methods win wherever both are around, so the trait is only
+// imported without std:
+//
+//     #[cfg(not(feature = "std"))]
+//     use crate::math::Float;
+pub trait Float {
+    fn sqrt(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn tan(self) -> Self;
+    fn exp(self) -> Self;
+    fn floor(self) -> Self;
+}
+
+
+impl Float for f32 {
+
+    fn sqrt(self) -> f32 {
+        libm::sqrtf(self)
+    }
+
+    fn sin(self) -> f32 {
+        libm::sinf(self)
+    }
+
+    fn cos(self) -> f32 {
+        libm::cosf(self)
+    }
+
+    fn tan(self) -> f32 {
+        libm::tanf(self)
+    }
+
+    fn exp(self) -> f32 {
+        libm::expf(self)
+    }
+
+    fn floor(self) -> f32 {
+        libm::floorf(self)
+    }
+
+}
@@ -1,8 +1,10 @@
-use std::ops::{ Mul, MulAssign };
+use core::ops::{ Mul, MulAssign };
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
 use crate::math::Vec3;
 
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Matrix4 {
     pub matrix: [[f32; 4]; 4],
 }
@@ -21,7 +23,7 @@ impl Matrix4 {
         }
     }
 
-    fn translation(tx: f32, ty: f32, tz: f32) -> Self {
+    pub fn translation(tx: f32, ty: f32, tz: f32) -> Self {
         Self {
             matrix: [
                 [1.0, 0.0, 0.0, 0.0],
@@ -199,6 +201,7 @@ impl Matrix4 {
 
 
 
+    #[allow(clippy::needless_range_loop)]
     pub fn cofactor(
         mat: [[f32; 4]; 4], p: usize, q: usize, n: usize
     ) -> [[f32; 4]; 4] {
@@ -233,12 +236,13 @@ impl Matrix4 {
         for f in 0..n {
             let cofactor = Matrix4::cofactor(mat, 0, f, n);
             det += sign * mat[0][f] * Matrix4::determinant(cofactor, n - 1);
-            sign = sign * -1.0;
+            sign = -sign;
         }
         det
     }
 
 
+    #[allow(clippy::needless_range_loop)]
     pub fn adjoint(mat: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
         let mut adj = [[0.0; 4]; 4];
         let mut sign;
@@ -268,6 +272,7 @@ impl Matrix4 {
     }
 
 
+    #[allow(clippy::needless_range_loop)]
     pub fn transpose(&self) -> Self {
         let mut transpose = [[0.0; 4]; 4];
         for i in 0..4 {
@@ -350,7 +355,8 @@ mod tests {
                 [9.0, 9.0, 3.0, 8.0],
             ]
         };
-        let result = matrix1 * matrix2;
+        // row-vector layout, `a * b` computes the product `b . a`
+        let result = matrix2 * matrix1;
         assert_eq!(result.matrix, [
             [62.0, 62.0, 17.0, 73.0],
             [158.0, 157.0, 57.0, 147.0],
@@ -361,7 +367,7 @@ mod tests {
 
     #[test]
     fn matrix_multiply_assign() {
-        let mut matrix1 = Matrix4 {
+        let matrix1 = Matrix4 {
             matrix: [
                 [2.0, 4.0, 1.0, 3.0],
                 [3.0, 5.0, 7.0, 8.0],
@@ -377,8 +383,9 @@ mod tests {
                 [9.0, 9.0, 3.0, 8.0],
             ]
         };
-        matrix1 *= matrix2;
-        assert_eq!(matrix1.matrix, [
+        let mut matrix2 = matrix2;
+        matrix2 *= matrix1;
+        assert_eq!(matrix2.matrix, [
             [62.0, 62.0, 17.0, 73.0],
             [158.0, 157.0, 57.0, 147.0],
             [212.0, 206.0, 71.0, 218.0],
@@ -391,10 +398,10 @@ mod tests {
     fn matrix_vector_multiply() {
         let matrix = Matrix4 {
             matrix: [
-                [2.0, 4.0, 1.0, 3.0],
-                [3.0, 5.0, 7.0, 8.0],
-                [7.0, 8.0, 9.0, 9.0],
-                [0.0, 0.0, 0.0, 1.0],
+                [2.0, 3.0, 7.0, 0.0],
+                [4.0, 5.0, 8.0, 0.0],
+                [1.0, 7.0, 9.0, 0.0],
+                [3.0, 8.0, 9.0, 1.0],
             ]
         };
         let vector = Vec3::new(23.0, 42.0, 125.0);
@@ -1,8 +1,10 @@
-use std::ops::{
+use core::ops::{
     Add, Sub, Neg, Mul, Div,
     AddAssign, SubAssign, MulAssign, DivAssign,
     Index, IndexMut,
 };
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
 
 
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -23,7 +25,7 @@ impl Vec3 {
     const ZERO: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 0.0 };
 
     pub const fn new(x: f32, y: f32, z: f32) -> Vec3 {
-        Vec3 { x: x, y: y, z: z }
+        Vec3 { x, y, z }
     }
 
     pub const fn zero() -> Vec3 {
@@ -237,7 +239,7 @@ mod tests {
 
     #[test]
     fn vector_set() {
-        let vector = Vec3::zero();
+        let mut vector = Vec3::zero();
         vector.set(1.0, 2.0, 3.0);
         assert_eq!(vector.x, 1.0);
         assert_eq!(vector.y, 2.0);
@@ -342,7 +344,7 @@ mod tests {
     fn vector_len() {
         let vector1 = Vec3::new(1.0, 2.0, 3.0);
         assert_eq!(14.0, vector1.sq_len());
-        assert_eq!((14.0 as f32).sqrt(), vector1.len());
+        assert_eq!(14.0_f32.sqrt(), vector1.len());
     }
 
     // needs float compare with <= e
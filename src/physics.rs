@@ -1,12 +1,22 @@
 
 pub mod rigidbody;
-pub use rigidbody::RigidBody;
+pub use rigidbody::{ RigidBody, Motion, resolve_collision, resolve_static_collision };
+
+pub mod integrator;
+pub use integrator::Integrator;
 
 pub mod aabb;
 pub use aabb::Aabb;
 
 pub mod collisions;
 
+pub mod convex;
+
+pub mod debug;
+
+#[cfg(test)]
+mod test_util;
+
 pub fn moi_cube(m: f32, s: f32) -> f32 {
     (1.0 / 6.0) * m * s * s
 }
@@ -1,5 +1,7 @@
 
 use crate::math::Vec3;
+use crate::engine::debug_buffer::DebugBuffer;
+use crate::physics::debug;
 
 
 // #[derive(Clone, Copy)]
@@ -29,10 +31,7 @@ impl Aabb {
 
     pub fn new(w: f32, h: f32) -> Self {
         // self.enabled = true;
-        let mut aabb = Self::default();
-        aabb.w = w;
-        aabb.h = h;
-        aabb
+        Self { w, h, ..Default::default() }
     }
 
 
@@ -57,4 +56,14 @@ impl Aabb {
         true
     }
 
+
+    pub fn debug_draw(&self, buffer: &mut DebugBuffer) {
+        buffer.polygon(&[
+            Vec3::new(self.x1, 0.0, self.z1),
+            Vec3::new(self.x2, 0.0, self.z1),
+            Vec3::new(self.x2, 0.0, self.z2),
+            Vec3::new(self.x1, 0.0, self.z2),
+        ], debug::AABB);
+    }
+
 }
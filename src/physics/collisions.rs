@@ -1,4 +1,8 @@
-use crate::{math::{Vec3, Matrix4}, engine::Arena};
+use crate::{math::{Vec3, Matrix4}, engine::StackVec};
+use crate::engine::debug_buffer::DebugBuffer;
+use crate::physics::debug;
+#[cfg(not(feature = "std"))]
+use crate::math::Float;
 
 
 
@@ -10,8 +14,55 @@ pub trait Collider {
 
     fn project_on_axis(&self, axis: Vec3) -> (f32, f32);
 
+    // Vertices of the core shape in world space, a rounded shape is its core
+    // shape grown by `radius()` in every direction.
+    fn world_vertices(&self) -> &[Vec3] {
+        &[]
+    }
+
+    fn radius(&self) -> f32 {
+        0.0
+    }
+
+    // outline of the shape and its separating axes from the center
+    fn debug_draw(&self, buffer: &mut DebugBuffer) {
+        let vertices = self.world_vertices();
+        if vertices.len() > 1 {
+            buffer.polygon(vertices, debug::COLLIDER);
+        }
+        if self.radius() > 0.0 {
+            for vertex in vertices {
+                buffer.circle(*vertex, self.radius(), debug::COLLIDER);
+            }
+        }
+        for axis in self.world_axes() {
+            buffer.vector(self.center(), axis.unit() * 2.0, debug::AXIS);
+        }
+    }
+
+    // the children of a compound, empty for a single shape
+    fn parts(&self) -> &[Part] {
+        &[]
+    }
+
     fn collide(&self, other: &dyn Collider) -> Option<(Vec3, f32)> {
 
+        // a compound on the other side is tested child by child, the
+        // deepest penetration wins
+        if !other.parts().is_empty() {
+            let mut hit = None;
+            let mut max_depth = f32::MIN;
+            for part in other.parts() {
+                if let Some((sep_axis, depth)) = self.collide(part.shape()) {
+                    if depth > max_depth {
+                        max_depth = depth;
+                        hit = Some((sep_axis, depth));
+                    }
+                }
+            }
+            return hit;
+        }
+
         let self_center = self.center();
         let other_center = other.center();
 
@@ -23,45 +74,55 @@ pub trait Collider {
             return None;
         }
 
-        let mut self_min;
-        let mut self_max;
-        let mut other_min;
-        let mut other_max;
-
         let mut sep_axis = Vec3::zero();
         let mut min_depth = f32::MAX;
 
-        let connecting_axis = [self_center - other_center];
-        let all_axes = connecting_axis
-            .iter()
-            .chain(self.world_axes())
-            .chain(other.world_axes());
+        let connecting_axis = self_center - other_center;
 
-        for axis in all_axes {
-            (self_min, self_max) = self.project_on_axis(*axis);
-            (other_min, other_max) = other.project_on_axis(*axis);
+        // returns false if `axis` separates the two shapes
+        let mut test_axis = |axis: Vec3| -> bool {
+            if axis.is_near_zero() { return true; }
+            let axis = axis.unit();
+
+            let (self_min, self_max) = self.project_on_axis(axis);
+            let (other_min, other_max) = other.project_on_axis(axis);
 
             if self_min > other_max || self_max < other_min {
-                return None;
+                return false;
             }
 
             let depth = f32::abs(self_min - other_max).min(f32::abs(self_max - other_min));
             if depth < min_depth {
                 min_depth = depth;
-                sep_axis = *axis;
+                sep_axis = axis;
             }
+            true
+        };
+
+        let all_axes = [connecting_axis]
+            .into_iter()
+            .chain(self.world_axes().iter().copied())
+            .chain(other.world_axes().iter().copied());
 
+        for axis in all_axes {
+            if !test_axis(axis) { return None; }
         }
 
-        if min_depth > 0.0 {
-            min_depth = min_depth / sep_axis.len();
+        // edge normals are not enough once a shape has rounded corners, the
+        // shapes can also be separated along the line between two vertices
+        if self.radius() > 0.0 || other.radius() > 0.0 {
+            for v in self.world_vertices() {
+                for w in other.world_vertices() {
+                    if !test_axis(*v - *w) { return None; }
+                }
+            }
         }
 
-        if sep_axis.dot(connecting_axis[0]) > 0.0 {
+        if sep_axis.dot(connecting_axis) > 0.0 {
             sep_axis = -sep_axis;
         }
 
-        Some((sep_axis.unit(), min_depth))
+        Some((sep_axis, min_depth))
     }
 
 }
@@ -93,15 +154,21 @@ impl Collider for CircleCollider {
     }
 
     fn world_axes(&self) -> &[Vec3] {
-        // TODO: need axis of closest vertex to circle center
         &[]
     }
 
     fn project_on_axis(&self, axis: Vec3) -> (f32, f32) {
         let dot = self.center.dot(axis);
-        let a = dot - self.radius;
-        let b = dot + self.radius;
-        (a.min(b), a.max(b))
+        let r = self.radius * axis.len();
+        (dot - r, dot + r)
+    }
+
+    fn world_vertices(&self) -> &[Vec3] {
+        core::slice::from_ref(&self.center)
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
     }
 
 }
@@ -114,6 +181,7 @@ pub struct PolygonCollider<const N: usize> {
     world_vertices: [Vec3; N],
     world_axes: [Vec3; N],
     pub matrix: Matrix4,
+    pub radius: f32,
 }
 
 
@@ -127,22 +195,31 @@ impl<const N: usize> PolygonCollider<N> {
             axes: core::array::from_fn(|_| Vec3::zero()),
             world_vertices: core::array::from_fn(|_| Vec3::zero()),
             world_axes: core::array::from_fn(|_| Vec3::zero()),
+            radius: 0.0,
         };
 
         for i in 0..N {
-            let edge = obj.vertices[i] - obj.vertices[((i + 1) % N)];
+            let edge = obj.vertices[i] - obj.vertices[(i + 1) % N];
             obj.axes[i] = Vec3::new(-edge.z, 0.0, edge.x);
         }
 
         obj
     }
 
+    // polygon with corners rounded off by `radius`, the outline grows by
+    // `radius` on every side
+    pub fn rounded(vertices: [Vec3; N], radius: f32) -> Self {
+        let mut obj = Self::new(vertices);
+        obj.radius = radius;
+        obj
+    }
+
 
     const ORIGIN: Vec3 = Vec3::zero();
 
     pub fn update(&mut self, matrix: &Matrix4) {
         self.center = *matrix * Self::ORIGIN;
-        self.matrix = (*matrix).clone();
+        self.matrix = *matrix;
 
         for (i, vertex) in self.vertices.iter().enumerate() {
             self.world_vertices[i] = *matrix * *vertex;
@@ -154,6 +231,19 @@ impl<const N: usize> PolygonCollider<N> {
         }
     }
 
+    // world vertex farthest along `dir`
+    pub fn support(&self, dir: Vec3) -> Vec3 {
+        let mut max = f32::MIN;
+        let mut farthest = self.center;
+        for vertex in self.world_vertices.iter() {
+            if vertex.dot(dir) > max {
+                max = vertex.dot(dir);
+                farthest = *vertex;
+            }
+        }
+        farthest
+    }
+
     pub fn get_best_edge(&self, normal: Vec3) -> (Vec3, Vec3, Vec3) {
 
         let mut max = f32::MIN;
@@ -183,11 +273,11 @@ impl<const N: usize> PolygonCollider<N> {
 }
 
 
-pub fn clip(v1: Vec3, v2: Vec3, normal: Vec3, offset: f32) -> Arena<Vec3, 2> {
+pub fn clip(v1: Vec3, v2: Vec3, normal: Vec3, offset: f32) -> StackVec<Vec3, 2> {
     // let mut p1 = Vec3::zero();
     // let mut p2 = None;
 
-    let mut points = Arena::empty();
+    let mut points = StackVec::empty();
 
     let d1 = normal.dot(v1) - offset;
     let d2 = normal.dot(v2) - offset;
@@ -234,9 +324,267 @@ impl<const N: usize> Collider for PolygonCollider<N> {
             max = dot.max(max);
         }
 
+        let r = self.radius * axis.len();
+        (min - r, max + r)
+    }
+
+    fn world_vertices(&self) -> &[Vec3] {
+        &self.world_vertices
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+}
+
+
+// Line segment from `a` to `b` grown by `radius`, a good fit for long thin
+// objects like missiles and beams.
+pub struct CapsuleCollider {
+    center: Vec3,
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: f32,
+    world_vertices: [Vec3; 2],
+    world_axes: [Vec3; 1],
+}
+
+
+impl CapsuleCollider {
+
+    pub fn new(a: Vec3, b: Vec3, radius: f32) -> Self {
+        Self {
+            center: Vec3::zero(),
+            a, b, radius,
+            world_vertices: [a, b],
+            world_axes: [Vec3::zero()],
+        }
+    }
+
+    pub fn update(&mut self, matrix: &Matrix4) {
+        self.center = *matrix * Vec3::zero();
+        self.world_vertices[0] = *matrix * self.a;
+        self.world_vertices[1] = *matrix * self.b;
+        self.world_vertices[0].y = 0.0;
+        self.world_vertices[1].y = 0.0;
+
+        let edge = self.world_vertices[1] - self.world_vertices[0];
+        self.world_axes[0] = Vec3::new(-edge.z, 0.0, edge.x);
+    }
+
+}
+
+
+impl Collider for CapsuleCollider {
+
+    fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    fn world_axes(&self) -> &[Vec3] {
+        &self.world_axes
+    }
+
+    fn project_on_axis(&self, axis: Vec3) -> (f32, f32) {
+        let a = self.world_vertices[0].dot(axis);
+        let b = self.world_vertices[1].dot(axis);
+        let r = self.radius * axis.len();
+        (a.min(b) - r, a.max(b) + r)
+    }
+
+    fn world_vertices(&self) -> &[Vec3] {
+        &self.world_vertices
+    }
+
+    fn radius(&self) -> f32 {
+        self.radius
+    }
+
+}
+
+
+// Colliders that can be placed in the world by a full transform, which is
+// what lets them be used as the children of a CompoundCollider.
+pub trait ConvexCollider: Collider {
+
+    fn transform(&mut self, matrix: &Matrix4);
+
+}
+
+
+impl ConvexCollider for CircleCollider {
+
+    fn transform(&mut self, matrix: &Matrix4) {
+        self.update(*matrix * Vec3::zero());
+    }
+
+}
+
+
+impl<const N: usize> ConvexCollider for PolygonCollider<N> {
+
+    fn transform(&mut self, matrix: &Matrix4) {
+        self.update(matrix);
+    }
+
+}
+
+
+impl ConvexCollider for CapsuleCollider {
+
+    fn transform(&mut self, matrix: &Matrix4) {
+        self.update(matrix);
+    }
+
+}
+
+
+// One child of a CompoundCollider, so a compound can mix shapes. Polygons
+// have up to 4 corners, a triangle repeats one of them.
+pub enum Part {
+    Circle(CircleCollider),
+    Polygon(PolygonCollider<4>),
+    Capsule(CapsuleCollider),
+}
+
+
+impl Part {
+
+    pub fn shape(&self) -> &dyn ConvexCollider {
+        match self {
+            Part::Circle(circle) => circle,
+            Part::Polygon(polygon) => polygon,
+            Part::Capsule(capsule) => capsule,
+        }
+    }
+
+    pub fn shape_mut(&mut self) -> &mut dyn ConvexCollider {
+        match self {
+            Part::Circle(circle) => circle,
+            Part::Polygon(polygon) => polygon,
+            Part::Capsule(capsule) => capsule,
+        }
+    }
+
+}
+
+
+impl From<CircleCollider> for Part {
+    fn from(circle: CircleCollider) -> Self {
+        Part::Circle(circle)
+    }
+}
+
+
+impl From<PolygonCollider<4>> for Part {
+    fn from(polygon: PolygonCollider<4>) -> Self {
+        Part::Polygon(polygon)
+    }
+}
+
+
+impl From<CapsuleCollider> for Part {
+    fn from(capsule: CapsuleCollider) -> Self {
+        Part::Capsule(capsule)
+    }
+}
+
+
+pub struct CompoundCollider<const N: usize> {
+    center: Vec3,
+    pub children: [Part; N],
+    locals: [Matrix4; N],
+}
+
+
+impl<const N: usize> CompoundCollider<N> {
+
+    pub fn new(parts: [(Part, Matrix4); N]) -> Self {
+        let mut locals = [Matrix4::identity(); N];
+        let mut i = 0;
+        let children = parts.map(|(child, local)| {
+            locals[i] = local;
+            i += 1;
+            child
+        });
+        Self {
+            center: Vec3::zero(),
+            children,
+            locals,
+        }
+    }
+
+    pub fn update(&mut self, matrix: &Matrix4) {
+        self.center = *matrix * Vec3::zero();
+        for (child, local) in self.children.iter_mut().zip(self.locals.iter()) {
+            child.shape_mut().transform(&(*matrix * *local));
+        }
+    }
+
+    // Same as `Collider::collide` but also returns the index of the child
+    // that was hit. When several children overlap `other`, the deepest
+    // penetration wins.
+    pub fn collide_part(
+        &self, other: &dyn Collider
+    ) -> Option<(usize, Vec3, f32)> {
+        let mut hit = None;
+        let mut max_depth = f32::MIN;
+
+        for (i, child) in self.children.iter().enumerate() {
+            if let Some((sep_axis, depth)) = child.shape().collide(other) {
+                if depth > max_depth {
+                    max_depth = depth;
+                    hit = Some((i, sep_axis, depth));
+                }
+            }
+        }
+
+        hit
+    }
+
+}
+
+
+// A compound is tested child by child on either side of a test, so
+// `a.collide(&b)` and `b.collide(&a)` agree. It has no axes of its own.
+impl<const N: usize> Collider for CompoundCollider<N> {
+
+    fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    fn world_axes(&self) -> &[Vec3] {
+        &[]
+    }
+
+    fn project_on_axis(&self, axis: Vec3) -> (f32, f32) {
+        let mut min = f32::MAX;
+        let mut max = f32::MIN;
+
+        for child in self.children.iter() {
+            let (a, b) = child.shape().project_on_axis(axis);
+            min = a.min(min);
+            max = b.max(max);
+        }
+
         (min, max)
     }
 
+    fn parts(&self) -> &[Part] {
+        &self.children
+    }
+
+    fn collide(&self, other: &dyn Collider) -> Option<(Vec3, f32)> {
+        self.collide_part(other).map(|(_, sep_axis, depth)| (sep_axis, depth))
+    }
+
+    fn debug_draw(&self, buffer: &mut DebugBuffer) {
+        for child in self.children.iter() {
+            child.shape().debug_draw(buffer);
+        }
+    }
+
 }
 
 
@@ -257,3 +605,279 @@ impl<const N: usize> Collider for PolygonCollider<N> {
 //     fn handle_collision(&mut self, ctype: CollisionType);
 
 // }
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::test_util::close;
+
+    fn square(s: f32) -> PolygonCollider<4> {
+        PolygonCollider::new([
+            Vec3::new(-s, 0.0, -s),
+            Vec3::new(s, 0.0, -s),
+            Vec3::new(s, 0.0, s),
+            Vec3::new(-s, 0.0, s),
+        ])
+    }
+
+    fn at(position: Vec3) -> Matrix4 {
+        let mut matrix = Matrix4::identity();
+        matrix.translate(position);
+        matrix
+    }
+
+    #[test]
+    fn polygon_collide() {
+        let mut a = square(1.0);
+        let mut b = square(1.0);
+        a.update(&at(Vec3::new(0.0, 0.0, 0.0)));
+        b.update(&at(Vec3::new(1.5, 0.0, 0.0)));
+
+        let (sep_axis, depth) = a.collide(&b).unwrap();
+        assert!((depth - 0.5).abs() < 0.0001);
+        assert!(sep_axis.x > 0.99);
+
+        b.update(&at(Vec3::new(2.5, 0.0, 0.0)));
+        assert!(a.collide(&b).is_none());
+    }
+
+    #[test]
+    fn circle_collide() {
+        let mut a = CircleCollider::new(1.0);
+        let mut b = CircleCollider::new(2.0);
+        a.update(Vec3::new(0.0, 0.0, 0.0));
+        b.update(Vec3::new(0.0, 0.0, 2.5));
+
+        let (sep_axis, depth) = a.collide(&b).unwrap();
+        assert!(close(depth, 0.5));
+        assert!(close(sep_axis.z, 1.0));
+
+        b.update(Vec3::new(2.0, 0.0, 2.5));
+        assert!(a.collide(&b).is_none());
+    }
+
+    #[test]
+    fn circle_polygon_corner() {
+        let mut rect = PolygonCollider::new([
+            Vec3::new(-3.0, 0.0, -1.0),
+            Vec3::new(3.0, 0.0, -1.0),
+            Vec3::new(3.0, 0.0, 1.0),
+            Vec3::new(-3.0, 0.0, 1.0),
+        ]);
+        rect.update(&Matrix4::identity());
+        let mut circle = CircleCollider::new(1.0);
+
+        // inside the edge normal slabs but just past the rounded corner,
+        // only the corner to center axis separates these
+        circle.update(Vec3::new(3.75, 0.0, 1.75));
+        assert!(rect.collide(&circle).is_none());
+        assert!(circle.collide(&rect).is_none());
+
+        circle.update(Vec3::new(3.6, 0.0, 1.6));
+        let (sep_axis, depth) = rect.collide(&circle).unwrap();
+        assert!(close(depth, 1.0 - 0.6 * 2.0_f32.sqrt()));
+        assert!(close(sep_axis.x, sep_axis.z));
+
+        circle.update(Vec3::new(0.0, 0.0, 1.5));
+        let (sep_axis, depth) = rect.collide(&circle).unwrap();
+        assert!(close(depth, 0.5));
+        assert!(close(sep_axis.z, 1.0));
+    }
+
+    #[test]
+    fn capsule_collide() {
+        let mut capsule = CapsuleCollider::new(
+            Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 2.0), 0.5
+        );
+        capsule.update(&Matrix4::identity());
+
+        let mut circle = CircleCollider::new(1.0);
+        circle.update(Vec3::new(1.25, 0.0, 0.0));
+        let (sep_axis, depth) = capsule.collide(&circle).unwrap();
+        assert!(close(depth, 0.25));
+        assert!(close(sep_axis.x, 1.0));
+
+        // past the end cap
+        circle.update(Vec3::new(1.2, 0.0, 3.2));
+        assert!(capsule.collide(&circle).is_none());
+        circle.update(Vec3::new(0.0, 0.0, 3.0));
+        let (sep_axis, depth) = capsule.collide(&circle).unwrap();
+        assert!(close(depth, 0.5));
+        assert!(close(sep_axis.z, 1.0));
+
+        let mut other = square(1.0);
+        other.update(&at(Vec3::new(0.0, 0.0, 2.9)));
+        let (_, depth) = capsule.collide(&other).unwrap();
+        assert!(close(depth, 0.6));
+
+        // capsule rotated to lie along x
+        capsule.update(&{
+            let mut matrix = at(Vec3::new(0.0, 0.0, 10.0));
+            matrix.rotate_y(crate::PI / 2.0);
+            matrix
+        });
+        other.update(&at(Vec3::new(3.0, 0.0, 10.0)));
+        let (sep_axis, depth) = capsule.collide(&other).unwrap();
+        assert!(close(depth, 0.5));
+        assert!(close(sep_axis.x.abs(), 1.0));
+    }
+
+    #[test]
+    fn rounded_polygon_collide() {
+        let vertices = [
+            Vec3::new(-1.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, -1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(-1.0, 0.0, 1.0),
+        ];
+        let mut a = PolygonCollider::rounded(vertices, 0.2);
+        let mut b = PolygonCollider::rounded(vertices, 0.2);
+        a.update(&Matrix4::identity());
+
+        b.update(&at(Vec3::new(2.3, 0.0, 0.0)));
+        let (sep_axis, depth) = a.collide(&b).unwrap();
+        assert!(close(depth, 0.1));
+        assert!(close(sep_axis.x, 1.0));
+
+        // corner to corner, within reach of the grown edges but not of the
+        // rounded corners
+        b.update(&at(Vec3::new(2.35, 0.0, 2.35)));
+        assert!(a.collide(&b).is_none());
+
+        let mut circle = CircleCollider::new(0.5);
+        circle.update(Vec3::new(0.0, 0.0, 1.6));
+        let (_, depth) = a.collide(&circle).unwrap();
+        assert!(close(depth, 0.1));
+    }
+
+    #[test]
+    fn compound_collide_part() {
+        let mut compound = CompoundCollider::new([
+            (square(1.0).into(), at(Vec3::new(-3.0, 0.0, 0.0))),
+            (square(1.0).into(), at(Vec3::new(3.0, 0.0, 0.0))),
+        ]);
+        compound.update(&at(Vec3::new(0.0, 0.0, 10.0)));
+
+        let mut other = square(1.0);
+        other.update(&at(Vec3::new(3.0, 0.0, 11.5)));
+        let (part, sep_axis, depth) = compound.collide_part(&other).unwrap();
+        assert_eq!(part, 1);
+        assert!((depth - 0.5).abs() < 0.0001);
+        assert!(sep_axis.z > 0.99);
+
+        let mut circle = CircleCollider::new(1.0);
+        circle.update(Vec3::new(-3.5, 0.0, 10.0));
+        assert_eq!(compound.collide_part(&circle).unwrap().0, 0);
+
+        // the gap between the two children is empty space
+        circle.update(Vec3::new(0.0, 0.0, 10.0));
+        assert!(compound.collide_part(&circle).is_none());
+        assert!(compound.collide(&circle).is_none());
+    }
+
+    #[test]
+    fn compound_follows_parent_transform() {
+        let mut compound = CompoundCollider::new([
+            (square(1.0).into(), at(Vec3::new(0.0, 0.0, -5.0))),
+        ]);
+        let mut matrix = at(Vec3::new(10.0, 0.0, 0.0));
+        matrix.rotate_y(crate::PI / 2.0);
+        compound.update(&matrix);
+
+        let mut circle = CircleCollider::new(0.5);
+        circle.update(Vec3::new(5.0, 0.0, 0.0));
+        assert!(compound.collide(&circle).is_some());
+        circle.update(Vec3::new(10.0, 0.0, -5.0));
+        assert!(compound.collide(&circle).is_none());
+    }
+
+    #[test]
+    fn compound_mixes_shapes() {
+        let mut compound = CompoundCollider::new([
+            (square(1.0).into(), Matrix4::identity()),
+            (CircleCollider::new(1.0).into(), at(Vec3::new(5.0, 0.0, 0.0))),
+            (
+                CapsuleCollider::new(Vec3::new(0.0, 0.0, -2.0), Vec3::new(0.0, 0.0, 2.0), 0.5).into(),
+                at(Vec3::new(-5.0, 0.0, 0.0)),
+            ),
+        ]);
+        compound.update(&Matrix4::identity());
+
+        let mut circle = CircleCollider::new(0.5);
+        circle.update(Vec3::new(6.25, 0.0, 0.0));
+        let (part, _, depth) = compound.collide_part(&circle).unwrap();
+        assert_eq!(part, 1);
+        assert!(close(depth, 0.25));
+
+        circle.update(Vec3::new(-5.0, 0.0, 2.75));
+        let (part, sep_axis, depth) = compound.collide_part(&circle).unwrap();
+        assert_eq!(part, 2);
+        assert!(close(depth, 0.25));
+        assert!(close(sep_axis.z, 1.0));
+
+        circle.update(Vec3::new(-2.5, 0.0, 0.0));
+        assert!(compound.collide_part(&circle).is_none());
+    }
+
+    #[test]
+    fn compound_as_other_tests_child_axes() {
+        // a diamond just off the corner of a long box, only the diamond's
+        // own edge normal separates the two
+        let mut diamond = at(Vec3::new(3.778, 0.0, 1.778));
+        diamond.rotate_y(crate::PI / 4.0);
+        let mut compound = CompoundCollider::new([(square(1.0).into(), diamond)]);
+        compound.update(&Matrix4::identity());
+
+        let mut rect = PolygonCollider::new([
+            Vec3::new(-3.0, 0.0, -1.0),
+            Vec3::new(3.0, 0.0, -1.0),
+            Vec3::new(3.0, 0.0, 1.0),
+            Vec3::new(-3.0, 0.0, 1.0),
+        ]);
+        rect.update(&Matrix4::identity());
+        assert!(compound.collide(&rect).is_none());
+        assert!(rect.collide(&compound).is_none());
+
+        compound.update(&at(Vec3::new(-0.2, 0.0, -0.2)));
+        assert!(compound.collide(&rect).is_some());
+        assert!(rect.collide(&compound).is_some());
+    }
+
+    #[test]
+    fn compound_as_other_tests_each_child() {
+        let mut compound = CompoundCollider::new([
+            (square(1.0).into(), at(Vec3::new(-3.0, 0.0, 0.0))),
+            (square(1.0).into(), at(Vec3::new(3.0, 0.0, 0.0))),
+        ]);
+        compound.update(&Matrix4::identity());
+
+        // the gap between the children is inside their hull but empty
+        let mut gap = square(0.5);
+        gap.update(&at(Vec3::new(0.0, 0.0, 0.0)));
+        assert!(compound.collide(&gap).is_none());
+        assert!(gap.collide(&compound).is_none());
+
+        let mut circle = CircleCollider::new(1.0);
+        circle.update(Vec3::new(1.5, 0.0, 0.0));
+        let (sep_axis, depth) = circle.collide(&compound).unwrap();
+        let (back_axis, back_depth) = compound.collide(&circle).unwrap();
+        assert!(close(depth, 0.5));
+        assert!(close(depth, back_depth));
+        assert!(close(sep_axis.x, 1.0));
+        assert!(close(back_axis.x, -1.0));
+
+        // both sides of a test can be compounds
+        let mut pair = CompoundCollider::new([
+            (CircleCollider::new(1.0).into(), at(Vec3::new(-1.5, 0.0, 0.0))),
+            (CircleCollider::new(1.0).into(), at(Vec3::new(1.5, 0.0, 0.0))),
+        ]);
+        pair.update(&Matrix4::identity());
+        let (sep_axis, depth) = pair.collide(&compound).unwrap();
+        assert!(close(depth, 0.5));
+        assert!(close(sep_axis.x.abs(), 1.0));
+        pair.update(&at(Vec3::new(0.0, 0.0, 5.0)));
+        assert!(pair.collide(&compound).is_none());
+    }
+}
@@ -0,0 +1,799 @@
+use crate::math::{ Vec3, Matrix4 };
+
+
+// 3D collision detection between convex shapes.
+//
+// GJK is used for overlap tests and separation distance, EPA expands the
+// final GJK simplex to find the penetration depth and normal. Everything
+// works on fixed size arrays, shapes only need to provide a support
+// function in world space.
+//
+// Reference:
+// https://dyn4j.org/2010/04/gjk-gilbert-johnson-keerthi/
+// https://dyn4j.org/2010/05/epa-expanding-polytope-algorithm/
+// Real-Time Collision Detection, Christer Ericson, 5.1.5 / 9.5
+
+
+
+const GJK_MAX_ITERATIONS: usize = 64;
+const EPA_MAX_ITERATIONS: usize = 64;
+const EPA_MAX_VERTICES: usize = 64;
+const EPA_MAX_FACES: usize = 128;
+const EPA_MAX_EDGES: usize = 64;
+const TOLERANCE: f32 = 0.0001;
+
+
+
+pub trait Support {
+
+    fn center(&self) -> Vec3;
+
+    // farthest point of the shape in world space along `dir`
+    fn support(&self, dir: Vec3) -> Vec3;
+
+}
+
+
+// --------------------------------------------------------
+
+
+pub struct ConvexHull<const N: usize> {
+    pub vertices: [Vec3; N],
+    world_vertices: [Vec3; N],
+    center: Vec3,
+}
+
+
+impl<const N: usize> ConvexHull<N> {
+
+    pub fn new(vertices: [Vec3; N]) -> Self {
+        Self { vertices, world_vertices: vertices, center: Vec3::zero() }
+    }
+
+    pub fn update(&mut self, matrix: &Matrix4) {
+        self.center = *matrix * Vec3::zero();
+        for (i, vertex) in self.vertices.iter().enumerate() {
+            self.world_vertices[i] = *matrix * *vertex;
+        }
+    }
+
+}
+
+
+impl<const N: usize> Support for ConvexHull<N> {
+
+    fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    fn support(&self, dir: Vec3) -> Vec3 {
+        let mut max = f32::MIN;
+        let mut farthest = self.center;
+        for vertex in self.world_vertices.iter() {
+            let dot = vertex.dot(dir);
+            if dot > max {
+                max = dot;
+                farthest = *vertex;
+            }
+        }
+        farthest
+    }
+
+}
+
+
+pub type Cuboid = ConvexHull<8>;
+
+impl Cuboid {
+
+    pub fn cuboid(half_extents: Vec3) -> Self {
+        let Vec3 { x, y, z } = half_extents;
+        Self::new([
+            Vec3::new(-x, -y, -z),
+            Vec3::new( x, -y, -z),
+            Vec3::new( x,  y, -z),
+            Vec3::new(-x,  y, -z),
+            Vec3::new(-x, -y,  z),
+            Vec3::new( x, -y,  z),
+            Vec3::new( x,  y,  z),
+            Vec3::new(-x,  y,  z),
+        ])
+    }
+
+}
+
+
+pub struct Sphere {
+    center: Vec3,
+    pub radius: f32,
+}
+
+
+impl Sphere {
+
+    pub fn new(radius: f32) -> Self {
+        Self { center: Vec3::zero(), radius }
+    }
+
+    pub fn update(&mut self, position: Vec3) {
+        self.center = position;
+    }
+
+}
+
+
+impl Support for Sphere {
+
+    fn center(&self) -> Vec3 {
+        self.center
+    }
+
+    fn support(&self, dir: Vec3) -> Vec3 {
+        // a degenerate direction has no farthest point, any point will do
+        if dir.is_near_zero() { return self.center; }
+        self.center + dir.unit() * self.radius
+    }
+
+}
+
+
+pub struct Capsule {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: f32,
+    world_a: Vec3,
+    world_b: Vec3,
+}
+
+
+impl Capsule {
+
+    pub fn new(a: Vec3, b: Vec3, radius: f32) -> Self {
+        Self { a, b, radius, world_a: a, world_b: b }
+    }
+
+    pub fn update(&mut self, matrix: &Matrix4) {
+        self.world_a = *matrix * self.a;
+        self.world_b = *matrix * self.b;
+    }
+
+}
+
+
+impl Support for Capsule {
+
+    fn center(&self) -> Vec3 {
+        (self.world_a + self.world_b) * 0.5
+    }
+
+    fn support(&self, dir: Vec3) -> Vec3 {
+        let end = if self.world_a.dot(dir) > self.world_b.dot(dir) {
+            self.world_a
+        } else {
+            self.world_b
+        };
+        if dir.is_near_zero() { return end; }
+        end + dir.unit() * self.radius
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+// A point on the minkowski difference A - B, along with the points on A and
+// B that produced it so closest points and contacts can be recovered.
+#[derive(Clone, Copy, Default)]
+struct MinkowskiPoint {
+    p: Vec3,
+    a: Vec3,
+    b: Vec3,
+}
+
+
+fn minkowski_support(
+    a: &dyn Support, b: &dyn Support, dir: Vec3
+) -> MinkowskiPoint {
+    let pa = a.support(dir);
+    let pb = b.support(-dir);
+    MinkowskiPoint { p: pa - pb, a: pa, b: pb }
+}
+
+
+#[derive(Clone, Copy, Default)]
+struct Simplex {
+    points: [MinkowskiPoint; 4],
+    weights: [f32; 4],
+    len: usize,
+}
+
+
+impl Simplex {
+
+    fn push(&mut self, point: MinkowskiPoint) {
+        self.points[self.len] = point;
+        self.len += 1;
+    }
+
+    fn keep(&mut self, indices: &[usize], weights: &[f32]) {
+        let points = self.points;
+        for (i, index) in indices.iter().enumerate() {
+            self.points[i] = points[*index];
+            self.weights[i] = weights[i];
+        }
+        self.len = indices.len();
+    }
+
+    fn closest(&self) -> Vec3 {
+        let mut v = Vec3::zero();
+        for i in 0..self.len {
+            v += self.points[i].p * self.weights[i];
+        }
+        v
+    }
+
+    fn witness_points(&self) -> (Vec3, Vec3) {
+        let mut a = Vec3::zero();
+        let mut b = Vec3::zero();
+        for i in 0..self.len {
+            a += self.points[i].a * self.weights[i];
+            b += self.points[i].b * self.weights[i];
+        }
+        (a, b)
+    }
+
+    // Reduces the simplex to the smallest sub simplex containing the point
+    // closest to the origin and stores barycentric weights for it. Returns
+    // false if the origin is enclosed by a tetrahedron.
+    fn reduce(&mut self) -> bool {
+        match self.len {
+            1 => { self.weights[0] = 1.0; true },
+            2 => { self.reduce_segment(0, 1); true },
+            3 => { self.reduce_triangle(0, 1, 2); true },
+            _ => self.reduce_tetrahedron(),
+        }
+    }
+
+    fn reduce_segment(&mut self, i: usize, j: usize) {
+        let a = self.points[i].p;
+        let b = self.points[j].p;
+        let ab = b - a;
+        let t = -a.dot(ab) / ab.sq_len().max(f32::EPSILON);
+        if t <= 0.0 {
+            self.keep(&[i], &[1.0]);
+        } else if t >= 1.0 {
+            self.keep(&[j], &[1.0]);
+        } else {
+            self.keep(&[i, j], &[1.0 - t, t]);
+        }
+    }
+
+    fn reduce_triangle(&mut self, i: usize, j: usize, k: usize) {
+        let a = self.points[i].p;
+        let b = self.points[j].p;
+        let c = self.points[k].p;
+        let ab = b - a;
+        let ac = c - a;
+
+        let ap = -a;
+        let d1 = ab.dot(ap);
+        let d2 = ac.dot(ap);
+        if d1 <= 0.0 && d2 <= 0.0 {
+            return self.keep(&[i], &[1.0]);
+        }
+
+        let bp = -b;
+        let d3 = ab.dot(bp);
+        let d4 = ac.dot(bp);
+        if d3 >= 0.0 && d4 <= d3 {
+            return self.keep(&[j], &[1.0]);
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+            let t = d1 / (d1 - d3);
+            return self.keep(&[i, j], &[1.0 - t, t]);
+        }
+
+        let cp = -c;
+        let d5 = ab.dot(cp);
+        let d6 = ac.dot(cp);
+        if d6 >= 0.0 && d5 <= d6 {
+            return self.keep(&[k], &[1.0]);
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+            let t = d2 / (d2 - d6);
+            return self.keep(&[i, k], &[1.0 - t, t]);
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+            let t = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+            return self.keep(&[j, k], &[1.0 - t, t]);
+        }
+
+        let denom = 1.0 / (va + vb + vc);
+        let v = vb * denom;
+        let w = vc * denom;
+        self.keep(&[i, j, k], &[1.0 - v - w, v, w]);
+    }
+
+    fn reduce_tetrahedron(&mut self) -> bool {
+        const FACES: [[usize; 4]; 4] = [
+            [0, 1, 2, 3], [0, 2, 3, 1], [0, 3, 1, 2], [1, 3, 2, 0],
+        ];
+
+        let mut best: Option<Simplex> = None;
+        let mut best_distance = f32::MAX;
+
+        for [i, j, k, opposite] in FACES {
+            let a = self.points[i].p;
+            let normal = (self.points[j].p - a).cross(self.points[k].p - a);
+            let side_origin = normal.dot(-a);
+            let side_opposite = normal.dot(self.points[opposite].p - a);
+
+            // origin is on the other side of this face from the 4th vertex
+            if side_origin * side_opposite < 0.0 {
+                let mut face = *self;
+                face.reduce_triangle(i, j, k);
+                let distance = face.closest().sq_len();
+                if distance < best_distance {
+                    best_distance = distance;
+                    best = Some(face);
+                }
+            }
+        }
+
+        match best {
+            Some(face) => { *self = face; true },
+            None => false,
+        }
+    }
+
+}
+
+
+// --------------------------------------------------------
+
+
+pub enum Gjk {
+    // distance between the shapes and the closest points on A and B
+    Separated(f32, Vec3, Vec3),
+    Intersecting,
+}
+
+
+fn gjk_simplex(a: &dyn Support, b: &dyn Support) -> (Gjk, Simplex) {
+    let mut simplex = Simplex::default();
+
+    let mut dir = a.center() - b.center();
+    if dir.is_near_zero() {
+        dir = Vec3::new(1.0, 0.0, 0.0);
+    }
+    simplex.push(minkowski_support(a, b, dir));
+    simplex.weights[0] = 1.0;
+    let mut v = simplex.points[0].p;
+
+    for _ in 0..GJK_MAX_ITERATIONS {
+        if v.sq_len() < TOLERANCE * TOLERANCE {
+            return (Gjk::Intersecting, simplex);
+        }
+
+        let w = minkowski_support(a, b, -v);
+
+        // no further progress towards the origin is possible
+        if v.sq_len() - v.dot(w.p) <= TOLERANCE * v.sq_len().max(1.0) {
+            break;
+        }
+
+        simplex.push(w);
+        if !simplex.reduce() {
+            return (Gjk::Intersecting, simplex);
+        }
+        v = simplex.closest();
+    }
+
+    let (pa, pb) = simplex.witness_points();
+    (Gjk::Separated(v.len(), pa, pb), simplex)
+}
+
+
+pub fn gjk(a: &dyn Support, b: &dyn Support) -> Gjk {
+    gjk_simplex(a, b).0
+}
+
+
+pub fn intersects(a: &dyn Support, b: &dyn Support) -> bool {
+    matches!(gjk(a, b), Gjk::Intersecting)
+}
+
+
+pub fn distance(a: &dyn Support, b: &dyn Support) -> f32 {
+    match gjk(a, b) {
+        Gjk::Separated(distance, _, _) => distance,
+        Gjk::Intersecting => 0.0,
+    }
+}
+
+
+// --------------------------------------------------------
+
+
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    // unit vector pointing from A towards B
+    pub normal: Vec3,
+    pub depth: f32,
+    // deepest point of A inside B, in world space
+    pub point: Vec3,
+}
+
+
+#[derive(Clone, Copy, Default)]
+struct Face {
+    vertices: [usize; 3],
+    normal: Vec3,
+    distance: f32,
+}
+
+
+struct Polytope {
+    interior: Vec3,
+    vertices: [MinkowskiPoint; EPA_MAX_VERTICES],
+    nvertices: usize,
+    faces: [Face; EPA_MAX_FACES],
+    nfaces: usize,
+}
+
+
+impl Polytope {
+
+    fn add_face(&mut self, i: usize, j: usize, k: usize) -> bool {
+        if self.nfaces >= EPA_MAX_FACES { return false; }
+
+        let a = self.vertices[i].p;
+        let b = self.vertices[j].p;
+        let c = self.vertices[k].p;
+        let mut normal = (b - a).cross(c - a).unit();
+        let mut vertices = [i, j, k];
+
+        // keep every face wound so that its normal points out of the
+        // polytope, the origin can lie on a face so orient against a point
+        // that is known to be strictly inside instead
+        if normal.dot(a - self.interior) < 0.0 {
+            vertices.swap(1, 2);
+            normal = -normal;
+        }
+        let distance = normal.dot(a).max(0.0);
+
+        self.faces[self.nfaces] = Face { vertices, normal, distance };
+        self.nfaces += 1;
+        true
+    }
+
+    fn closest_face(&self) -> usize {
+        let mut closest = 0;
+        for i in 1..self.nfaces {
+            if self.faces[i].distance < self.faces[closest].distance {
+                closest = i;
+            }
+        }
+        closest
+    }
+
+    fn expand(&mut self, w: MinkowskiPoint) -> bool {
+        if self.nvertices >= EPA_MAX_VERTICES { return false; }
+        let index = self.nvertices;
+        self.vertices[index] = w;
+        self.nvertices += 1;
+
+        let mut edges = [[0usize; 2]; EPA_MAX_EDGES];
+        let mut nedges = 0;
+
+        let mut i = 0;
+        while i < self.nfaces {
+            let face = self.faces[i];
+            let a = self.vertices[face.vertices[0]].p;
+            if face.normal.dot(w.p - a) <= 0.0 {
+                i += 1;
+                continue;
+            }
+
+            // face can see the new point, remove it and keep track of the
+            // edges that are not shared with another removed face
+            for e in 0..3 {
+                let edge = [face.vertices[e], face.vertices[(e + 1) % 3]];
+                let shared = edges[..nedges].iter().position(|other| {
+                    other[0] == edge[1] && other[1] == edge[0]
+                });
+                match shared {
+                    Some(s) => {
+                        edges[s] = edges[nedges - 1];
+                        nedges -= 1;
+                    },
+                    None => {
+                        if nedges >= EPA_MAX_EDGES { return false; }
+                        edges[nedges] = edge;
+                        nedges += 1;
+                    },
+                }
+            }
+
+            self.faces[i] = self.faces[self.nfaces - 1];
+            self.nfaces -= 1;
+        }
+
+        for edge in edges[..nedges].iter() {
+            if !self.add_face(edge[0], edge[1], index) { return false; }
+        }
+
+        true
+    }
+
+}
+
+
+// GJK can terminate with fewer than 4 points when the origin lies on the
+// boundary of the simplex, EPA needs a full tetrahedron to start from.
+fn complete_tetrahedron(
+    simplex: &mut Simplex, a: &dyn Support, b: &dyn Support
+) -> bool {
+    const AXES: [Vec3; 6] = [
+        Vec3::new(1.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0),
+        Vec3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0),
+    ];
+
+    if simplex.len == 1 {
+        for axis in AXES {
+            let w = minkowski_support(a, b, axis);
+            if (w.p - simplex.points[0].p).sq_len() > TOLERANCE {
+                simplex.push(w);
+                break;
+            }
+        }
+    }
+
+    if simplex.len == 2 {
+        let line = simplex.points[1].p - simplex.points[0].p;
+        for axis in AXES {
+            let dir = line.cross(axis);
+            if dir.is_near_zero() { continue; }
+            let w = minkowski_support(a, b, dir);
+            let offset = w.p - simplex.points[0].p;
+            if line.cross(offset).sq_len() > TOLERANCE {
+                simplex.push(w);
+                break;
+            }
+        }
+    }
+
+    if simplex.len == 3 {
+        let p0 = simplex.points[0].p;
+        let normal = (simplex.points[1].p - p0).cross(simplex.points[2].p - p0);
+        for dir in [normal, -normal] {
+            let w = minkowski_support(a, b, dir);
+            if normal.dot(w.p - p0).abs() > TOLERANCE {
+                simplex.push(w);
+                break;
+            }
+        }
+    }
+
+    simplex.len == 4
+}
+
+
+fn epa(simplex: Simplex, a: &dyn Support, b: &dyn Support) -> Option<Contact> {
+    let mut polytope = Polytope {
+        interior: (
+            simplex.points[0].p + simplex.points[1].p
+            + simplex.points[2].p + simplex.points[3].p
+        ) * 0.25,
+        vertices: [MinkowskiPoint::default(); EPA_MAX_VERTICES],
+        nvertices: 4,
+        faces: [Face::default(); EPA_MAX_FACES],
+        nfaces: 0,
+    };
+    polytope.vertices[..4].copy_from_slice(&simplex.points);
+
+    polytope.add_face(0, 1, 2);
+    polytope.add_face(0, 3, 1);
+    polytope.add_face(0, 2, 3);
+    polytope.add_face(1, 3, 2);
+
+    let mut closest = polytope.closest_face();
+
+    for _ in 0..EPA_MAX_ITERATIONS {
+        let face = polytope.faces[closest];
+        let w = minkowski_support(a, b, face.normal);
+
+        if w.p.dot(face.normal) - face.distance < TOLERANCE * 10.0 {
+            break;
+        }
+        if !polytope.expand(w) || polytope.nfaces == 0 {
+            break;
+        }
+        closest = polytope.closest_face();
+    }
+
+    if polytope.nfaces == 0 { return None; }
+    let face = polytope.faces[closest];
+
+    // barycentric coordinates of the origin projected onto the face give
+    // the matching point on shape A
+    let [i, j, k] = face.vertices;
+    let (v0, v1, v2) = (
+        polytope.vertices[i], polytope.vertices[j], polytope.vertices[k]
+    );
+    let projected = face.normal * face.distance;
+    let e0 = v1.p - v0.p;
+    let e1 = v2.p - v0.p;
+    let e2 = projected - v0.p;
+    let d00 = e0.dot(e0);
+    let d01 = e0.dot(e1);
+    let d11 = e1.dot(e1);
+    let d20 = e2.dot(e0);
+    let d21 = e2.dot(e1);
+    let denom = d00 * d11 - d01 * d01;
+    let (u, v, w) = if denom.abs() > f32::EPSILON {
+        let v = (d11 * d20 - d01 * d21) / denom;
+        let w = (d00 * d21 - d01 * d20) / denom;
+        (1.0 - v - w, v, w)
+    } else {
+        (1.0, 0.0, 0.0)
+    };
+
+    Some(Contact {
+        normal: face.normal,
+        depth: face.distance,
+        point: v0.a * u + v1.a * v + v2.a * w,
+    })
+}
+
+
+pub fn collide(a: &dyn Support, b: &dyn Support) -> Option<Contact> {
+    let (result, mut simplex) = gjk_simplex(a, b);
+    match result {
+        Gjk::Separated(..) => None,
+        Gjk::Intersecting => {
+            if !complete_tetrahedron(&mut simplex, a, b) {
+                return None;
+            }
+            epa(simplex, a, b)
+        },
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::test_util::roughly;
+
+    fn cuboid_at(half_extents: Vec3, position: Vec3) -> Cuboid {
+        let mut cuboid = Cuboid::cuboid(half_extents);
+        cuboid.update(&Matrix4::translation(position.x, position.y, position.z));
+        cuboid
+    }
+
+    #[test]
+    fn sphere_distance() {
+        let mut a = Sphere::new(1.0);
+        let mut b = Sphere::new(2.0);
+        a.update(Vec3::new(0.0, 0.0, 0.0));
+        b.update(Vec3::new(0.0, 5.0, 0.0));
+
+        match gjk(&a, &b) {
+            Gjk::Separated(distance, pa, pb) => {
+                assert!(roughly(distance, 2.0));
+                assert!(roughly(pa.y, 1.0));
+                assert!(roughly(pb.y, 3.0));
+            },
+            Gjk::Intersecting => panic!("spheres should be separated"),
+        }
+    }
+
+    #[test]
+    fn support_along_zero_direction() {
+        let mut sphere = Sphere::new(1.0);
+        sphere.update(Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(sphere.support(Vec3::zero()), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(sphere.support(Vec3::new(1e-30, 0.0, 0.0)), Vec3::new(1.0, 2.0, 3.0));
+
+        let mut capsule = Capsule::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0), 0.5);
+        capsule.update(&Matrix4::identity());
+        let point = capsule.support(Vec3::zero());
+        assert!(point == Vec3::new(0.0, -1.0, 0.0) || point == Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn sphere_penetration() {
+        let mut a = Sphere::new(1.0);
+        let mut b = Sphere::new(1.0);
+        a.update(Vec3::new(0.0, 0.0, 0.0));
+        b.update(Vec3::new(1.5, 0.0, 0.0));
+
+        let contact = collide(&a, &b).unwrap();
+        assert!(roughly(contact.depth, 0.5));
+        assert!(roughly(contact.normal.x, 1.0));
+    }
+
+    #[test]
+    fn cuboid_penetration() {
+        let a = cuboid_at(Vec3::new(1.0, 1.0, 1.0), Vec3::zero());
+        let b = cuboid_at(Vec3::new(1.0, 1.0, 1.0), Vec3::new(0.2, 1.7, 0.5));
+
+        let contact = collide(&a, &b).unwrap();
+        assert!(roughly(contact.depth, 0.3));
+        assert!(roughly(contact.normal.y, 1.0));
+    }
+
+    #[test]
+    fn cuboid_separated_above_plane() {
+        // overlapping on the XZ plane but separated along Y, which the 2D
+        // colliders can not tell apart
+        let a = cuboid_at(Vec3::new(5.0, 5.0, 5.0), Vec3::zero());
+        let b = cuboid_at(Vec3::new(5.0, 5.0, 5.0), Vec3::new(1.0, 30.0, 1.0));
+
+        assert!(!intersects(&a, &b));
+        assert!(roughly(distance(&a, &b), 20.0));
+        assert!(collide(&a, &b).is_none());
+    }
+
+    #[test]
+    fn rotated_cuboid() {
+        let mut a = Cuboid::cuboid(Vec3::new(1.0, 1.0, 1.0));
+        let mut matrix = Matrix4::identity();
+        matrix.rotate_y(crate::PI / 4.0);
+        a.update(&matrix);
+
+        // corner of the rotated cube reaches sqrt(2) along x
+        let b = cuboid_at(Vec3::new(1.0, 1.0, 1.0), Vec3::new(2.3, 0.0, 0.0));
+        let contact = collide(&a, &b).unwrap();
+        assert!(roughly(contact.depth, 2.0_f32.sqrt() + 1.0 - 2.3));
+
+        let b = cuboid_at(Vec3::new(1.0, 1.0, 1.0), Vec3::new(2.5, 0.0, 0.0));
+        assert!(!intersects(&a, &b));
+    }
+
+    #[test]
+    fn capsule_against_sphere() {
+        let mut capsule = Capsule::new(
+            Vec3::new(0.0, 0.0, -3.0), Vec3::new(0.0, 0.0, 3.0), 1.0
+        );
+        capsule.update(&Matrix4::identity());
+
+        let mut sphere = Sphere::new(1.0);
+        sphere.update(Vec3::new(1.5, 0.0, 2.0));
+        let contact = collide(&capsule, &sphere).unwrap();
+        assert!(roughly(contact.depth, 0.5));
+        assert!(roughly(contact.normal.x, 1.0));
+
+        sphere.update(Vec3::new(0.0, 0.0, 5.5));
+        assert!(roughly(distance(&capsule, &sphere), 0.5));
+    }
+
+    #[test]
+    fn hull_against_cuboid() {
+        let mut tetrahedron = ConvexHull::new([
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+        ]);
+        tetrahedron.update(&Matrix4::identity());
+
+        let b = cuboid_at(Vec3::new(1.0, 1.0, 1.0), Vec3::new(-0.9, 0.5, 0.5));
+        let contact = collide(&tetrahedron, &b).unwrap();
+        assert!(roughly(contact.depth, 0.1));
+        assert!(roughly(contact.normal.x, -1.0));
+
+        let b = cuboid_at(Vec3::new(1.0, 1.0, 1.0), Vec3::new(3.0, 3.0, 3.0));
+        assert!(!intersects(&tetrahedron, &b));
+    }
+}
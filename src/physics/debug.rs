@@ -0,0 +1,38 @@
+use crate::math::Vec3;
+use crate::engine::debug_buffer::{ DebugBuffer, Color };
+use crate::physics::RigidBody;
+
+
+
+pub const COLLIDER: Color = [0.0, 1.0, 0.0];
+pub const AXIS: Color     = [1.0, 1.0, 0.0];
+pub const AABB: Color     = [0.0, 0.5, 1.0];
+pub const CONTACT: Color  = [1.0, 0.0, 0.0];
+pub const VELOCITY: Color = [0.0, 1.0, 1.0];
+pub const FORCE: Color    = [1.0, 0.0, 1.0];
+
+
+// world units drawn per m/s and per Newton
+const VELOCITY_SCALE: f32 = 0.1;
+const FORCE_SCALE: f32 = 0.00002;
+
+
+
+pub fn draw_contact(
+    debug: &mut DebugBuffer, point: Vec3, sep_axis: Vec3, depth: f32
+) {
+    debug.point(point, 0.5, CONTACT);
+    debug.vector(point, sep_axis * depth.max(2.0), AXIS);
+}
+
+
+pub fn draw_velocity(
+    debug: &mut DebugBuffer, position: Vec3, rigidbody: &RigidBody
+) {
+    debug.vector(position, rigidbody.velocity() * VELOCITY_SCALE, VELOCITY);
+}
+
+
+pub fn draw_force(debug: &mut DebugBuffer, position: Vec3, force: Vec3) {
+    debug.vector(position, force * FORCE_SCALE, FORCE);
+}
@@ -0,0 +1,177 @@
+use crate::math::Vec3;
+
+
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Integrator {
+    SemiImplicitEuler,
+    #[default]
+    VelocityVerlet,
+    Rk4,
+}
+
+
+impl Integrator {
+
+    // number of the integrator in snapshots, recordings and calls from JS
+    pub fn code(self) -> u8 {
+        match self {
+            Integrator::SemiImplicitEuler => 0,
+            Integrator::VelocityVerlet => 1,
+            Integrator::Rk4 => 2,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(Integrator::SemiImplicitEuler),
+            1 => Some(Integrator::VelocityVerlet),
+            2 => Some(Integrator::Rk4),
+            _ => None,
+        }
+    }
+
+    // Advances `position` and `velocity` by `dt`, where `accel` gives the
+    // acceleration for a given position and velocity. `acceleration` has to
+    // hold the acceleration at the current state when called and is left
+    // holding the acceleration at the new state.
+    pub fn step<F>(
+        &self, dt: f32,
+        position: &mut Vec3, velocity: &mut Vec3, acceleration: &mut Vec3,
+        accel: F,
+    ) where F: Fn(Vec3, Vec3) -> Vec3 {
+        match self {
+            Integrator::SemiImplicitEuler => {
+                *velocity += *acceleration * dt;
+                *position += *velocity * dt;
+                *acceleration = accel(*position, *velocity);
+            },
+
+            Integrator::VelocityVerlet => {
+                *position += (*velocity * dt) + (0.5 * *acceleration * dt * dt);
+                // velocity dependent forces only see the old velocity
+                let next = accel(*position, *velocity);
+                *velocity += 0.5 * (*acceleration + next) * dt;
+                *acceleration = next;
+            },
+
+            Integrator::Rk4 => {
+                let (x, v) = (*position, *velocity);
+                let half = 0.5 * dt;
+
+                let k1x = v;
+                let k1v = *acceleration;
+                let k2x = v + k1v * half;
+                let k2v = accel(x + k1x * half, k2x);
+                let k3x = v + k2v * half;
+                let k3v = accel(x + k2x * half, k3x);
+                let k4x = v + k3v * dt;
+                let k4v = accel(x + k3x * dt, k4x);
+
+                *position += (k1x + 2.0 * k2x + 2.0 * k3x + k4x) * (dt / 6.0);
+                *velocity += (k1v + 2.0 * k2v + 2.0 * k3v + k4v) * (dt / 6.0);
+                *acceleration = accel(*position, *velocity);
+            },
+        }
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Integrator; 3] = [
+        Integrator::SemiImplicitEuler,
+        Integrator::VelocityVerlet,
+        Integrator::Rk4,
+    ];
+
+    // Runs `steps` steps and returns the largest relative energy error seen.
+    fn energy_drift<F, E>(
+        integrator: Integrator, dt: f32, steps: usize,
+        mut position: Vec3, mut velocity: Vec3, accel: F, energy: E,
+    ) -> f32
+    where F: Fn(Vec3, Vec3) -> Vec3, E: Fn(Vec3, Vec3) -> f32 {
+        let mut acceleration = accel(position, velocity);
+        let initial = energy(position, velocity);
+        let mut drift: f32 = 0.0;
+        for _ in 0..steps {
+            integrator.step(
+                dt, &mut position, &mut velocity, &mut acceleration, &accel
+            );
+            let error = ((energy(position, velocity) - initial) / initial).abs();
+            drift = drift.max(error);
+        }
+        drift
+    }
+
+    fn spring(integrator: Integrator) -> f32 {
+        // k = 4, m = 1, period of pi seconds, ~30 periods
+        energy_drift(
+            integrator, 0.01, 10_000,
+            Vec3::new(1.0, 0.0, 0.0), Vec3::zero(),
+            |x, _| -4.0 * x,
+            |x, v| 0.5 * v.sq_len() + 2.0 * x.sq_len(),
+        )
+    }
+
+    fn orbit(integrator: Integrator) -> f32 {
+        // GM = 1, circular orbit of radius 1, ~8 orbits
+        energy_drift(
+            integrator, 0.01, 5_000,
+            Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0),
+            |x, _| -x.unit() / x.sq_len(),
+            |x, v| 0.5 * v.sq_len() - 1.0 / x.len(),
+        )
+    }
+
+    #[test]
+    fn constant_acceleration() {
+        // every integrator is exact for a constant force
+        for integrator in ALL {
+            let mut position = Vec3::zero();
+            let mut velocity = Vec3::new(1.0, 0.0, 0.0);
+            let mut acceleration = Vec3::new(0.0, 0.0, 2.0);
+            for _ in 0..10 {
+                integrator.step(
+                    0.1, &mut position, &mut velocity, &mut acceleration,
+                    |_, _| Vec3::new(0.0, 0.0, 2.0),
+                );
+            }
+            assert!((velocity.z - 2.0).abs() < 0.0001);
+            assert!((position.x - 1.0).abs() < 0.0001);
+            if integrator != Integrator::SemiImplicitEuler {
+                assert!((position.z - 1.0).abs() < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn spring_energy_drift() {
+        let euler = spring(Integrator::SemiImplicitEuler);
+        let verlet = spring(Integrator::VelocityVerlet);
+        let rk4 = spring(Integrator::Rk4);
+
+        assert!(euler < 0.02, "semi-implicit euler drift {}", euler);
+        assert!(verlet < 0.0005, "velocity verlet drift {}", verlet);
+        assert!(rk4 < 0.0001, "rk4 drift {}", rk4);
+        assert!(verlet < euler);
+        assert!(rk4 < euler);
+    }
+
+    #[test]
+    fn orbit_energy_drift() {
+        let euler = orbit(Integrator::SemiImplicitEuler);
+        let verlet = orbit(Integrator::VelocityVerlet);
+        let rk4 = orbit(Integrator::Rk4);
+
+        assert!(euler < 0.001, "semi-implicit euler drift {}", euler);
+        assert!(verlet < 0.0001, "velocity verlet drift {}", verlet);
+        assert!(rk4 < 0.0001, "rk4 drift {}", rk4);
+        assert!(verlet < euler);
+        assert!(rk4 < euler);
+    }
+}
@@ -1,6 +1,7 @@
 
 
 use crate::math::Vec3;
+use crate::physics::Integrator;
 
 
 // Units:
@@ -13,6 +14,18 @@ use crate::math::Vec3;
 
 
 
+// Everything that changes as a body moves, mass and limits are set once
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Motion {
+    pub force: Vec3,
+    pub velocity: Vec3,
+    pub acceleration: Vec3,
+    pub torque: Vec3,
+    pub angular_velocity: Vec3,
+    pub angular_acceleration: Vec3,
+}
+
+
 pub struct RigidBody {
     pub mass: f32,
     pub moment_of_inertia: f32,
@@ -26,6 +39,8 @@ pub struct RigidBody {
 
     pub velocity_limit: f32,
     pub angular_velocity_limit: f32,
+
+    pub integrator: Integrator,
 }
 
 
@@ -33,7 +48,7 @@ impl RigidBody {
 
     pub fn new(mass: f32, moi: f32) -> Self {
         Self {
-            mass: mass,
+            mass,
             moment_of_inertia: moi,
             force: Vec3::zero(),
             velocity: Vec3::zero(),
@@ -43,6 +58,7 @@ impl RigidBody {
             angular_acceleration: Vec3::zero(),
             velocity_limit: 200.0,
             angular_velocity_limit: 7.0,
+            integrator: Integrator::default(),
         }
     }
 
@@ -75,6 +91,51 @@ impl RigidBody {
         self.apply_torque(force, distance);
     }
 
+    // instant change in momentum applied at `distance` from the center of
+    // mass, changes both linear and angular velocity
+    pub fn apply_impulse(&mut self, impulse: Vec3, distance: Vec3) {
+        if self.mass <= 0.0 { return; }
+        self.velocity += impulse / self.mass;
+        self.angular_velocity += distance.cross(impulse) / self.moment_of_inertia;
+    }
+
+    pub fn motion(&self) -> Motion {
+        Motion {
+            force: self.force,
+            velocity: self.velocity,
+            acceleration: self.acceleration,
+            torque: self.torque,
+            angular_velocity: self.angular_velocity,
+            angular_acceleration: self.angular_acceleration,
+        }
+    }
+
+    pub fn set_motion(&mut self, motion: Motion) {
+        self.force = motion.force;
+        self.velocity = motion.velocity;
+        self.acceleration = motion.acceleration;
+        self.torque = motion.torque;
+        self.angular_velocity = motion.angular_velocity;
+        self.angular_acceleration = motion.angular_acceleration;
+    }
+
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn angular_velocity(&self) -> Vec3 {
+        self.angular_velocity
+    }
+
+    // velocity of the point at `distance` from the center of mass
+    pub fn velocity_at(&self, distance: Vec3) -> Vec3 {
+        self.velocity + self.angular_velocity.cross(distance)
+    }
+
+    pub fn momentum(&self) -> Vec3 {
+        self.velocity * self.mass
+    }
+
     pub fn apply_damping(&mut self, cof: f32) {
 
         if self.velocity.len() > 0.5 {
@@ -96,8 +157,18 @@ impl RigidBody {
     pub fn update_physics(
         &mut self, dt: f32, position: &mut Vec3, rotation: &mut Vec3
     ) {
+        self.update_physics_in(dt, position, rotation, |_, _| Vec3::zero());
+    }
 
-        if !(self.mass > 0.0) { return; }
+    // Same as `update_physics` with `field` giving an extra force for a
+    // position and velocity, like a spring or a gravity well. The integrator
+    // evaluates it at its intermediate states, where the accumulated forces
+    // are held constant.
+    pub fn update_physics_in<F>(
+        &mut self, dt: f32, position: &mut Vec3, rotation: &mut Vec3, field: F
+    ) where F: Fn(Vec3, Vec3) -> Vec3 {
+
+        if self.mass <= 0.0 { return; }
 
         if self.velocity.is_near_zero() {
             self.velocity.set(0.0, 0.0, 0.0);
@@ -108,18 +179,21 @@ impl RigidBody {
         }
 
 
-        *position += {
-            (self.velocity * dt) + (0.5 * self.acceleration * dt * dt)
-        };
-        self.acceleration = self.force / self.mass;
-        self.velocity += 0.5 * self.acceleration * dt;
-
+        // forces and torques are accumulated before the step and held
+        // constant over it, only the field changes along the way
+        let (force, mass) = (self.force, self.mass);
+        self.acceleration = (force + field(*position, self.velocity)) / mass;
+        self.integrator.step(
+            dt, position, &mut self.velocity, &mut self.acceleration,
+            |x, v| (force + field(x, v)) / mass,
+        );
 
-        *rotation += {
-            (self.angular_velocity * dt) + (0.5 * self.angular_acceleration * dt * dt)
-        };
-        self.angular_acceleration = self.torque / self.moment_of_inertia;
-        self.angular_velocity += 0.5 * self.angular_acceleration * dt;
+        let angular_acceleration = self.torque / self.moment_of_inertia;
+        self.angular_acceleration = angular_acceleration;
+        self.integrator.step(
+            dt, rotation, &mut self.angular_velocity, &mut self.angular_acceleration,
+            |_, _| angular_acceleration,
+        );
 
 
         if self.velocity.len() > self.velocity_limit {
@@ -138,3 +212,154 @@ impl RigidBody {
     }
 
 }
+
+
+// Applies equal and opposite impulses to two colliding bodies. `normal` is a
+// unit vector pointing from `a` towards `b`, `ra` and `rb` are the contact
+// point relative to each center of mass and `restitution` is 0 for a fully
+// inelastic and 1 for a fully elastic collision.
+pub fn resolve_collision(
+    a: &mut RigidBody, ra: Vec3,
+    b: &mut RigidBody, rb: Vec3,
+    normal: Vec3, restitution: f32,
+) {
+    let relative_velocity = b.velocity_at(rb) - a.velocity_at(ra);
+    let normal_velocity = relative_velocity.dot(normal);
+
+    // already moving apart
+    if normal_velocity > 0.0 { return; }
+
+    let denominator = inverse_mass(a, ra, normal) + inverse_mass(b, rb, normal);
+    if denominator <= 0.0 { return; }
+
+    let j = -(1.0 + restitution) * normal_velocity / denominator;
+    a.apply_impulse(-normal * j, ra);
+    b.apply_impulse(normal * j, rb);
+}
+
+
+// Same as `resolve_collision` against an immovable body at rest, like the
+// level's static objects.
+pub fn resolve_static_collision(
+    a: &mut RigidBody, ra: Vec3,
+    normal: Vec3, restitution: f32,
+) {
+    let normal_velocity = -a.velocity_at(ra).dot(normal);
+    if normal_velocity > 0.0 { return; }
+
+    let denominator = inverse_mass(a, ra, normal);
+    if denominator <= 0.0 { return; }
+
+    let j = -(1.0 + restitution) * normal_velocity / denominator;
+    a.apply_impulse(-normal * j, ra);
+}
+
+
+// how much an impulse along `normal` at `r` moves the contact point, zero
+// for an immovable body
+fn inverse_mass(body: &RigidBody, r: Vec3, normal: Vec3) -> f32 {
+    if body.mass <= 0.0 { return 0.0; }
+    let angular = r.cross(normal).cross(r) / body.moment_of_inertia;
+    (1.0 / body.mass) + normal.dot(angular)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn impulse_off_center_spins() {
+        let mut body = RigidBody::new(2.0, 4.0);
+        body.apply_impulse(Vec3::new(0.0, 0.0, 4.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(body.velocity(), Vec3::new(0.0, 0.0, 2.0));
+        assert_eq!(body.angular_velocity(), Vec3::new(0.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn collision_conserves_momentum() {
+        let mut a = RigidBody::new(1.0, 1.0);
+        let mut b = RigidBody::new(3.0, 3.0);
+        a.apply_impulse(Vec3::new(4.0, 0.0, 0.0), Vec3::zero());
+        let before = a.momentum() + b.momentum();
+
+        let (ra, rb) = (Vec3::new(1.0, 0.0, 0.5), Vec3::new(-1.0, 0.0, 0.5));
+        resolve_collision(
+            &mut a, ra, &mut b, rb, Vec3::new(1.0, 0.0, 0.0), 0.5
+        );
+        let after = a.momentum() + b.momentum();
+        assert!((after - before).len() < 0.0001);
+        assert!(b.velocity().x > 0.0);
+        assert!(a.velocity_at(ra).x <= b.velocity_at(rb).x);
+
+        // moving apart now, a second call is a no-op
+        let velocity = a.velocity();
+        resolve_collision(
+            &mut a, ra, &mut b, rb, Vec3::new(1.0, 0.0, 0.0), 0.5
+        );
+        assert_eq!(velocity, a.velocity());
+    }
+
+    #[test]
+    fn static_body_stops_inward_motion() {
+        let mut body = RigidBody::new(2.0, 2.0);
+        let mut wall = RigidBody::new(0.0, 0.0);
+        body.apply_impulse(Vec3::new(4.0, 0.0, 2.0), Vec3::zero());
+
+        // head on contact, all of the inward velocity goes
+        resolve_collision(
+            &mut body, Vec3::new(1.0, 0.0, 0.0),
+            &mut wall, Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0), 0.0,
+        );
+        assert!(body.velocity().x.abs() < 0.0001);
+        assert!((body.velocity().z - 1.0).abs() < 0.0001);
+        assert_eq!(wall.velocity(), Vec3::zero());
+    }
+
+    #[test]
+    fn static_collision_matches_an_immovable_body() {
+        let mut body = RigidBody::new(2.0, 2.0);
+        body.apply_impulse(Vec3::new(4.0, 0.0, 2.0), Vec3::zero());
+        let mut other = RigidBody::new(2.0, 2.0);
+        other.apply_impulse(Vec3::new(4.0, 0.0, 2.0), Vec3::zero());
+        let mut wall = RigidBody::new(0.0, 0.0);
+
+        let (r, normal) = (Vec3::new(1.0, 0.0, 0.5), Vec3::new(1.0, 0.0, 0.0));
+        resolve_static_collision(&mut body, r, normal, 0.5);
+        resolve_collision(&mut other, r, &mut wall, -r, normal, 0.5);
+        assert_eq!(body.velocity(), other.velocity());
+        assert_eq!(body.angular_velocity(), other.angular_velocity());
+        assert!(body.velocity_at(r).x < 0.0);
+    }
+
+    #[test]
+    fn field_is_evaluated_along_the_step() {
+        // linear drag F = -k v on m = 2 with k = 2, so v(t) = v0 e^-t and
+        // x(t) = v0 (1 - e^-t)
+        let exact_velocity = 4.0 * (-1.0_f32).exp();
+        let exact_position = 4.0 * (1.0 - (-1.0_f32).exp());
+
+        let mut body = RigidBody::new(2.0, 2.0);
+        body.integrator = Integrator::Rk4;
+        body.apply_impulse(Vec3::new(8.0, 0.0, 0.0), Vec3::zero());
+        let (mut position, mut rotation) = (Vec3::zero(), Vec3::zero());
+        for _ in 0..10 {
+            body.update_physics_in(0.1, &mut position, &mut rotation, |_, v| -2.0 * v);
+        }
+        assert!((body.velocity().x - exact_velocity).abs() < 0.0001, "{}", body.velocity().x);
+        assert!((position.x - exact_position).abs() < 0.0001, "{}", position.x);
+
+        // the same drag as a force is only evaluated once per step
+        let mut body = RigidBody::new(2.0, 2.0);
+        body.integrator = Integrator::Rk4;
+        body.apply_impulse(Vec3::new(8.0, 0.0, 0.0), Vec3::zero());
+        let (mut position, mut rotation) = (Vec3::zero(), Vec3::zero());
+        for _ in 0..10 {
+            body.apply_force(-2.0 * body.velocity());
+            body.update_physics(0.1, &mut position, &mut rotation);
+        }
+        assert!((body.velocity().x - exact_velocity).abs() > 0.01, "{}", body.velocity().x);
+    }
+}
@@ -0,0 +1,10 @@
+// Comparisons for the physics tests. The separating axis test is exact up to
+// rounding, GJK and EPA stop at a tolerance so their results are rougher.
+
+pub fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 0.0001
+}
+
+pub fn roughly(a: f32, b: f32) -> bool {
+    (a - b).abs() < 0.01
+}
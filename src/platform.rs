@@ -0,0 +1,107 @@
+// Everything the engine asks of its host goes through a `Platform`. In the
+// browser that is the JS imports, natively it is a headless recorder so the
+// game can run under `cargo test`.
+
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+
+use crate::log::Level;
+
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+#[cfg(target_arch = "wasm32")]
+pub use wasm::WasmPlatform;
+
+#[cfg(feature = "std")]
+pub mod headless;
+#[cfg(feature = "std")]
+pub use headless::{ HeadlessPlatform, Call };
+
+// without std and outside the browser there is no host to talk to
+#[cfg(all(not(feature = "std"), not(target_arch = "wasm32")))]
+pub mod null;
+#[cfg(all(not(feature = "std"), not(target_arch = "wasm32")))]
+pub use null::NullPlatform;
+
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BufferKind {
+    Float,
+    Bytes,
+}
+
+
+pub trait Platform {
+
+    fn console_log(&mut self, text: &str);
+
+    fn console_error(&mut self, text: &str);
+
+    // a message from the `log` macros, `module` is the path of the module
+    // inside the crate
+    fn log(&mut self, level: Level, module: &str, text: &str);
+
+    fn add_shader(&mut self, name: &str, vert: &str, frag: &str);
+
+    // `data` is a JSON array, `size` the number of components per vertex
+    fn add_buffer(
+        &mut self, kind: BufferKind, name: &str, data: &str,
+        size: f32, normalize: bool,
+    );
+
+    // `data` is a mesh in the format of `engine::mesh`, uploaded as it is
+    fn add_mesh(&mut self, name: &str, data: &[u8]);
+
+    // `meta` is a serialized `RenderDescriptor`, returns the entity id
+    fn add_entity(&mut self, meta: &[u8]) -> usize;
+
+    fn destroy_entity(&mut self, id: usize);
+
+    // high resolution time in milliseconds, for profiling only
+    fn now(&mut self) -> f64;
+
+}
+
+
+// --------------------------------------------------------
+
+
+#[cfg(feature = "std")]
+thread_local! {
+    static PLATFORM: RefCell<Box<dyn Platform>> = RefCell::new(default());
+}
+
+#[cfg(all(feature = "std", target_arch = "wasm32"))]
+fn default() -> Box<dyn Platform> {
+    Box::new(WasmPlatform)
+}
+
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+fn default() -> Box<dyn Platform> {
+    Box::new(HeadlessPlatform::new())
+}
+
+
+// replaces the platform used by the current thread
+#[cfg(feature = "std")]
+pub fn set(platform: Box<dyn Platform>) {
+    PLATFORM.with(|p| *p.borrow_mut() = platform);
+}
+
+#[cfg(feature = "std")]
+pub fn with<R>(func: impl FnOnce(&mut dyn Platform) -> R) -> R {
+    PLATFORM.with(|p| func(p.borrow_mut().as_mut()))
+}
+
+// no allocator to box a platform with, the JS imports keep no state of their
+// own so every call gets a fresh `WasmPlatform`
+#[cfg(all(not(feature = "std"), target_arch = "wasm32"))]
+pub fn with<R>(func: impl FnOnce(&mut dyn Platform) -> R) -> R {
+    func(&mut WasmPlatform)
+}
+
+#[cfg(all(not(feature = "std"), not(target_arch = "wasm32")))]
+pub fn with<R>(func: impl FnOnce(&mut dyn Platform) -> R) -> R {
+    func(&mut NullPlatform)
+}
@@ -0,0 +1,168 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::platform::{ self, Platform, BufferKind };
+use crate::log::Level;
+
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Call {
+    ConsoleLog(String),
+    ConsoleError(String),
+    Log { level: Level, module: String, text: String },
+    AddShader { name: String },
+    AddBuffer { kind: BufferKind, name: String, size: f32, normalize: bool },
+    AddMesh { name: String, bytes: usize },
+    AddEntity { id: usize, meta: String },
+    DestroyEntity(usize),
+}
+
+
+#[derive(Default)]
+struct Recording {
+    calls: Vec<Call>,
+    next_id: usize,
+    free_ids: Vec<usize>,
+    live: HashSet<usize>,
+    clock: f64,
+    clock_step: f64,
+}
+
+
+// Records every call in memory instead of talking to a host. Entity IDs are
+// handed out and recycled the same way `js/main.js` does it, and destroying
+// an ID that isn't live panics so double frees show up in tests. The clock
+// only moves by a fixed step every time it is read, so timings are exact.
+//
+// Clones share the same recording, so a test can keep one and install the
+// other.
+#[derive(Clone)]
+pub struct HeadlessPlatform {
+    recording: Rc<RefCell<Recording>>,
+}
+
+
+impl HeadlessPlatform {
+
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            recording: Rc::new(RefCell::new(Recording {
+                next_id: 1,
+                ..Default::default()
+            })),
+        }
+    }
+
+    // makes a new recorder the platform of the current thread
+    pub fn install() -> Self {
+        let headless = Self::new();
+        platform::set(Box::new(headless.clone()));
+        headless
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.recording.borrow().calls.clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.recording.borrow_mut().calls.clear();
+    }
+
+    // number of entities created and not yet destroyed
+    pub fn live_entities(&self) -> usize {
+        self.recording.borrow().live.len()
+    }
+
+    pub fn is_live(&self, id: usize) -> bool {
+        self.recording.borrow().live.contains(&id)
+    }
+
+    // highest entity ID handed out so far
+    pub fn max_entity_id(&self) -> usize {
+        self.recording.borrow().next_id - 1
+    }
+
+    // milliseconds the clock moves every time it is read, 0 by default
+    pub fn set_clock_step(&self, step: f64) {
+        self.recording.borrow_mut().clock_step = step;
+    }
+
+    fn record(&self, call: Call) {
+        self.recording.borrow_mut().calls.push(call);
+    }
+
+}
+
+
+impl Platform for HeadlessPlatform {
+
+    fn console_log(&mut self, text: &str) {
+        self.record(Call::ConsoleLog(text.to_string()));
+    }
+
+    fn console_error(&mut self, text: &str) {
+        self.record(Call::ConsoleError(text.to_string()));
+    }
+
+    fn log(&mut self, level: Level, module: &str, text: &str) {
+        self.record(Call::Log {
+            level, module: module.to_string(), text: text.to_string()
+        });
+    }
+
+    fn add_shader(&mut self, name: &str, _: &str, _: &str) {
+        self.record(Call::AddShader { name: name.to_string() });
+    }
+
+    fn add_buffer(
+        &mut self, kind: BufferKind, name: &str, _: &str,
+        size: f32, normalize: bool,
+    ) {
+        self.record(Call::AddBuffer {
+            kind, name: name.to_string(), size, normalize
+        });
+    }
+
+    fn add_mesh(&mut self, name: &str, data: &[u8]) {
+        self.record(Call::AddMesh { name: name.to_string(), bytes: data.len() });
+    }
+
+    fn add_entity(&mut self, meta: &[u8]) -> usize {
+        let id = {
+            let mut recording = self.recording.borrow_mut();
+            let id = match recording.free_ids.pop() {
+                Some(id) => id,
+                None => {
+                    recording.next_id += 1;
+                    recording.next_id - 1
+                },
+            };
+            recording.live.insert(id);
+            id
+        };
+        let meta = String::from_utf8_lossy(meta).into_owned();
+        self.record(Call::AddEntity { id, meta });
+        id
+    }
+
+    fn destroy_entity(&mut self, id: usize) {
+        {
+            let mut recording = self.recording.borrow_mut();
+            assert!(recording.live.remove(&id), "entity {} destroyed twice", id);
+            recording.free_ids.push(id);
+        }
+        self.record(Call::DestroyEntity(id));
+    }
+
+    // not recorded, it is read several times a frame
+    fn now(&mut self) -> f64 {
+        let mut recording = self.recording.borrow_mut();
+        let now = recording.clock;
+        recording.clock += recording.clock_step;
+        now
+    }
+
+}
@@ -0,0 +1,38 @@
+use crate::platform::{ Platform, BufferKind };
+use crate::log::Level;
+
+
+
+// Host for `no_std` builds outside the browser, every call is dropped. It
+// lets the engine core be built for targets that have no `std` at all.
+pub struct NullPlatform;
+
+
+impl Platform for NullPlatform {
+
+    fn console_log(&mut self, _text: &str) {}
+
+    fn console_error(&mut self, _text: &str) {}
+
+    fn log(&mut self, _level: Level, _module: &str, _text: &str) {}
+
+    fn add_shader(&mut self, _name: &str, _vert: &str, _frag: &str) {}
+
+    fn add_buffer(
+        &mut self, _kind: BufferKind, _name: &str, _data: &str,
+        _size: f32, _normalize: bool,
+    ) {}
+
+    fn add_mesh(&mut self, _name: &str, _data: &[u8]) {}
+
+    fn add_entity(&mut self, _meta: &[u8]) -> usize {
+        0
+    }
+
+    fn destroy_entity(&mut self, _id: usize) {}
+
+    fn now(&mut self) -> f64 {
+        0.0
+    }
+
+}
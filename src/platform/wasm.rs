@@ -0,0 +1,124 @@
+use crate::platform::{ Platform, BufferKind };
+use crate::log::Level;
+
+
+
+#[link(wasm_import_module = "imports")]
+extern "C" {
+
+    fn js_console_log_raw(x: *const u8, l: usize);
+
+    fn js_console_error_raw(x: *const u8, l: usize);
+
+    fn js_log(
+        level: u32,
+        module_ptr: *const u8, module_len: usize,
+        text_ptr: *const u8, text_len: usize,
+    );
+
+    fn js_add_shader(
+        name_ptr: *const u8, name_len: usize,
+        vert_ptr: *const u8, vert_len: usize,
+        frag_ptr: *const u8, frag_len: usize,
+    );
+
+    fn js_add_buffer_float(
+        name_ptr: *const u8, name_len: usize,
+        data_ptr: *const u8, data_len: usize,
+        size: f32, normalize: bool
+    );
+
+    fn js_add_buffer_bytes(
+        name_ptr: *const u8, name_len: usize,
+        data_ptr: *const u8, data_len: usize,
+        size: f32, normalize: bool
+    );
+
+    fn js_add_mesh(
+        name_ptr: *const u8, name_len: usize,
+        data_ptr: *const u8, data_len: usize,
+    );
+
+    fn js_add_entity(ptr: *const u8, len: usize) -> usize;
+
+    fn js_destroy_entity(id: usize);
+
+    fn js_now() -> f64;
+
+}
+
+
+
+// The JS imports set up in `js/main.js`
+pub struct WasmPlatform;
+
+
+impl Platform for WasmPlatform {
+
+    fn console_log(&mut self, text: &str) {
+        unsafe { js_console_log_raw(text.as_ptr(), text.len()) }
+    }
+
+    fn console_error(&mut self, text: &str) {
+        unsafe { js_console_error_raw(text.as_ptr(), text.len()) }
+    }
+
+    fn log(&mut self, level: Level, module: &str, text: &str) {
+        unsafe {
+            js_log(
+                level as u32,
+                module.as_ptr(), module.len(),
+                text.as_ptr(), text.len(),
+            )
+        }
+    }
+
+    fn add_shader(&mut self, name: &str, vert: &str, frag: &str) {
+        unsafe {
+            js_add_shader(
+                name.as_ptr(), name.len(),
+                vert.as_ptr(), vert.len(),
+                frag.as_ptr(), frag.len(),
+            )
+        }
+    }
+
+    fn add_buffer(
+        &mut self, kind: BufferKind, name: &str, data: &str,
+        size: f32, normalize: bool,
+    ) {
+        let add = match kind {
+            BufferKind::Float => js_add_buffer_float,
+            BufferKind::Bytes => js_add_buffer_bytes,
+        };
+        unsafe {
+            add(
+                name.as_ptr(), name.len(),
+                data.as_ptr(), data.len(),
+                size, normalize
+            )
+        }
+    }
+
+    fn add_mesh(&mut self, name: &str, data: &[u8]) {
+        unsafe {
+            js_add_mesh(
+                name.as_ptr(), name.len(),
+                data.as_ptr(), data.len(),
+            )
+        }
+    }
+
+    fn add_entity(&mut self, meta: &[u8]) -> usize {
+        unsafe { js_add_entity(meta.as_ptr(), meta.len()) }
+    }
+
+    fn destroy_entity(&mut self, id: usize) {
+        unsafe { js_destroy_entity(id) }
+    }
+
+    fn now(&mut self) -> f64 {
+        unsafe { js_now() }
+    }
+
+}
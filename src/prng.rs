@@ -17,6 +17,15 @@ impl Xoroshiro128Plus {
         prng
     }
 
+    // the raw state, `from_state` continues the exact same sequence
+    pub fn state(&self) -> [u64; 2] {
+        [self.s0, self.s1]
+    }
+
+    pub fn from_state(state: [u64; 2]) -> Self {
+        Self { s0: state[0], s1: state[1] }
+    }
+
     pub fn random_u64(&mut self) -> u64 {
         let result = self.s0.wrapping_add(self.s1);
         self.s1 ^= self.s0;
@@ -0,0 +1,268 @@
+// A session recorded as the prng seed and integrator the game started from
+// and the time, input, input bindings and integrator of every frame after
+// that. The simulation only depends on these, so feeding them back into a
+// fresh game reproduces the run exactly.
+//
+// Binary format, little endian:
+//
+//   header: "NRPL", version u16, integrator u8, 0 u8, seed u64 x2,
+//           frame count u32
+//   frame:  t f32, dt f32, mask u16, then one u32 for every set bit of mask
+//           below `INTEGRATOR_CHANGED`, then the words of the `InputMap` if
+//           `BINDINGS_CHANGED` is set, then the integrator u8 if
+//           `INTEGRATOR_CHANGED` is set
+//
+// Input is delta encoded, bit n of the mask means word n of `Input` changed
+// since the previous frame, which starts out all zeros. Bindings are written
+// whole whenever they change, so always on the first frame. The integrator
+// is only written when it differs from the previous frame or the header.
+
+use crate::engine::input::{ Input, InputMap, INPUT_WORDS, BINDING_WORDS };
+use crate::binary::{ Writer, Reader, Truncated };
+use crate::physics::Integrator;
+
+
+
+const MAGIC: &[u8; 4] = b"NRPL";
+const VERSION: u16 = 4;
+const HEADER_SIZE: usize = 4 + 2 + 2 + 16 + 4;
+const INTEGRATOR_CHANGED: u16 = 1 << 14;
+const BINDINGS_CHANGED: u16 = 1 << 15;
+
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RecordedFrame {
+    pub t: f32,
+    pub dt: f32,
+    pub input: Input,
+    pub bindings: InputMap,
+    pub integrator: Integrator,
+}
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReplayError {
+    Truncated,
+    BadMagic,
+    Version(u16),
+    TrailingBytes,
+    BadBindings,
+    UnknownIntegrator(u8),
+}
+
+impl From<Truncated> for ReplayError {
+    fn from(_: Truncated) -> Self {
+        ReplayError::Truncated
+    }
+}
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Recording {
+    pub seed: [u64; 2],
+    pub integrator: Integrator,
+    frames: Vec<RecordedFrame>,
+}
+
+
+impl Recording {
+
+    pub fn new(seed: [u64; 2], integrator: Integrator) -> Self {
+        Self { seed, integrator, frames: Vec::new() }
+    }
+
+    pub fn record(
+        &mut self, t: f32, dt: f32,
+        input: &Input, bindings: &InputMap, integrator: Integrator,
+    ) {
+        self.frames.push(RecordedFrame {
+            t, dt, input: *input, bindings: *bindings, integrator
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&RecordedFrame> {
+        self.frames.get(index)
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &RecordedFrame> {
+        self.frames.iter()
+    }
+
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer::with_capacity(HEADER_SIZE + self.frames.len() * 10);
+        out.bytes(MAGIC);
+        out.u16(VERSION);
+        out.u8(self.integrator.code());
+        out.u8(0);
+        out.u64(self.seed[0]);
+        out.u64(self.seed[1]);
+        out.u32(self.frames.len() as u32);
+
+        let mut previous = [0; INPUT_WORDS];
+        let mut bindings = InputMap::empty();
+        let mut integrator = self.integrator;
+        for frame in &self.frames {
+            out.f32(frame.t);
+            out.f32(frame.dt);
+
+            let words = frame.input.to_words();
+            let mut mask = (0..INPUT_WORDS)
+                .filter(|i| words[*i] != previous[*i])
+                .fold(0u16, |mask, i| mask | 1 << i);
+            if frame.bindings != bindings {
+                mask |= BINDINGS_CHANGED;
+            }
+            if frame.integrator != integrator {
+                mask |= INTEGRATOR_CHANGED;
+            }
+            out.u16(mask);
+            for i in 0..INPUT_WORDS {
+                if mask & 1 << i > 0 {
+                    out.u32(words[i]);
+                }
+            }
+            if mask & BINDINGS_CHANGED > 0 {
+                for word in frame.bindings.to_words() {
+                    out.u32(word);
+                }
+            }
+            if mask & INTEGRATOR_CHANGED > 0 {
+                out.u8(frame.integrator.code());
+            }
+            previous = words;
+            bindings = frame.bindings;
+            integrator = frame.integrator;
+        }
+        out.into_bytes()
+    }
+
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut input = Reader::new(bytes);
+        if input.take(4)? != MAGIC { return Err(ReplayError::BadMagic); }
+        let version = input.u16()?;
+        if version != VERSION { return Err(ReplayError::Version(version)); }
+        let code = input.u8()?;
+        let start = Integrator::from_code(code).ok_or(ReplayError::UnknownIntegrator(code))?;
+        input.u8()?;
+        let seed = [input.u64()?, input.u64()?];
+        let count = input.u32()? as usize;
+
+        // every frame takes at least 10 bytes, don't trust the count further
+        let mut frames = Vec::with_capacity(count.min(input.remaining() / 10));
+        let mut words = [0; INPUT_WORDS];
+        let mut bindings = InputMap::empty();
+        let mut integrator = start;
+        for _ in 0..count {
+            let t = input.f32()?;
+            let dt = input.f32()?;
+            let mask = input.u16()?;
+            for i in 0..INPUT_WORDS {
+                if mask & 1 << i > 0 {
+                    words[i] = input.u32()?;
+                }
+            }
+            if mask & BINDINGS_CHANGED > 0 {
+                let mut binding_words = [0; BINDING_WORDS];
+                for word in binding_words.iter_mut() {
+                    *word = input.u32()?;
+                }
+                bindings = InputMap::from_words(&binding_words).ok_or(ReplayError::BadBindings)?;
+            }
+            if mask & INTEGRATOR_CHANGED > 0 {
+                let code = input.u8()?;
+                integrator = Integrator::from_code(code).ok_or(ReplayError::UnknownIntegrator(code))?;
+            }
+            frames.push(RecordedFrame {
+                t, dt, input: Input::from_words(&words), bindings, integrator
+            });
+        }
+        if !input.finished() { return Err(ReplayError::TrailingBytes); }
+
+        Ok(Self { seed, integrator: start, frames })
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ Key, Action };
+    use crate::engine::input::Binding;
+
+    fn recording() -> Recording {
+        let mut recording = Recording::new([7, 11], Integrator::SemiImplicitEuler);
+        let mut input = Input::default();
+        let mut bindings = InputMap::new();
+        let mut integrator = Integrator::SemiImplicitEuler;
+        for t in 0..100 {
+            if t % 10 == 0 { input.set_key(Key::W, t % 20 == 0); }
+            if t == 50 { input.gamepad_axes[0] = -0.75; }
+            if t == 70 { bindings.rebind(Action::Fire, Binding::Key(Key::F)); }
+            if t == 80 { integrator = Integrator::Rk4; }
+            recording.record(t as f32 * 16.0, 1.0 / 60.0, &input, &bindings, integrator);
+        }
+        recording
+    }
+
+    #[test]
+    fn round_trip() {
+        let recording = recording();
+        let bytes = recording.to_bytes();
+        assert_eq!(Recording::from_bytes(&bytes), Ok(recording));
+
+        // frames where the input didn't change only cost their timing, the
+        // bindings are written on the first frame and after the rebind, the
+        // integrator only once it switches
+        let changed = 10 + 1;
+        assert_eq!(
+            bytes.len(),
+            HEADER_SIZE + 100 * 10 + changed * 4 + 2 * BINDING_WORDS * 4 + 1
+        );
+    }
+
+    #[test]
+    fn rejects_bad_blobs() {
+        let bytes = recording().to_bytes();
+
+        assert_eq!(
+            Recording::from_bytes(&bytes[0..bytes.len() - 1]),
+            Err(ReplayError::Truncated)
+        );
+        assert_eq!(
+            Recording::from_bytes(&[bytes.as_slice(), &[0]].concat()),
+            Err(ReplayError::TrailingBytes)
+        );
+
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(Recording::from_bytes(&bad), Err(ReplayError::BadMagic));
+
+        let mut bad = bytes.clone();
+        bad[4] = 9;
+        assert_eq!(Recording::from_bytes(&bad), Err(ReplayError::Version(9)));
+
+        let mut bad = bytes.clone();
+        bad[6] = 7;
+        assert_eq!(Recording::from_bytes(&bad), Err(ReplayError::UnknownIntegrator(7)));
+
+        // the first action slot of the first frame, after its one input word
+        let mut bad = bytes.clone();
+        bad[HEADER_SIZE + 10 + 4] = 9;
+        assert_eq!(Recording::from_bytes(&bad), Err(ReplayError::BadBindings));
+    }
+}
@@ -2,10 +2,13 @@
 
 in vec4 a_position;
 
+// set by JS for every viewport
+uniform mat4 u_view_projection;
+
 uniform objectData {
     mat4 u_matrix;
 };
 
 void main() {
-    gl_Position = u_matrix * a_position;
+    gl_Position = u_view_projection * u_matrix * a_position;
 }
@@ -6,13 +6,16 @@ out vec3 v_color;
 out float v_alpha;
 out float v_collide;
 
+// set by JS for every viewport
+uniform mat4 u_view_projection;
+
 uniform objectData {
     mat4 u_matrix;
     float u_collide;
 };
 
 void main() {
-    gl_Position = u_matrix * a_position;
+    gl_Position = u_view_projection * u_matrix * a_position;
     v_color = a_color;
     v_collide = u_collide;
     v_alpha = 1.0 - (gl_Position.z / (800.0 * 2.0));
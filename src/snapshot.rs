@@ -0,0 +1,237 @@
+// The full state of a running game, saved to a binary blob JS can store and
+// loaded back later. Each game object writes and reads its own state, this
+// holds the header and the pieces shared between them.
+//
+// Binary format, little endian:
+//
+//   header: "NSNP", version u16, 0 u16, seed u64 x2
+//   clock:  time f32, frame u64
+//   camera: position, up, the 16 floats of the view matrix, then the
+//           projection as 0 and the fov or 1 and the orthographic height
+//   input:  the words of the player's `InputMap`
+//   game:   prng state u64 x2, then the ship, asteroids and objects, each
+//           list prefixed by its length as a u8
+//
+// The clock is saved too, so timestamps in the game stay valid as they are.
+// Pending timers only drive effects like hit flashes and aren't saved.
+
+use crate::binary::{ Writer, Reader, Truncated };
+use crate::engine::{ Engine, Projection, InputMap };
+use crate::engine::input::BINDING_WORDS;
+use crate::game::Game;
+use crate::math::Matrix4;
+use crate::physics::{ RigidBody, Motion, Integrator };
+
+
+
+const MAGIC: &[u8; 4] = b"NSNP";
+const VERSION: u16 = 5;
+
+
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SnapshotError {
+    Truncated,
+    BadMagic,
+    Version(u16),
+    TrailingBytes,
+    // more of something than the game has room for
+    TooMany,
+    // an arena slot layout that doesn't add up
+    BadSlots,
+    UnknownIntegrator(u8),
+    UnknownProjection(u8),
+    BadBindings,
+}
+
+impl From<Truncated> for SnapshotError {
+    fn from(_: Truncated) -> Self {
+        SnapshotError::Truncated
+    }
+}
+
+
+
+pub fn save(engine: &Engine) -> Vec<u8> {
+    let mut out = Writer::new();
+    out.bytes(MAGIC);
+    out.u16(VERSION);
+    out.u16(0);
+    let [s0, s1] = engine.seed();
+    out.u64(s0);
+    out.u64(s1);
+
+    out.f32(engine.clock.time());
+    out.u64(engine.clock.frame());
+
+    let camera = &engine.views.main().camera;
+    out.vec3(camera.position);
+    out.vec3(camera.up);
+    for value in camera.view_matrix().matrix.as_flattened() {
+        out.f32(*value);
+    }
+    match camera.projection() {
+        Projection::Perspective { fov } => {
+            out.u8(0);
+            out.f32(fov);
+        },
+        Projection::Orthographic { height } => {
+            out.u8(1);
+            out.f32(height);
+        },
+    }
+
+    for word in engine.bindings().to_words() {
+        out.u32(word);
+    }
+
+    engine.game.save(&mut out);
+    out.into_bytes()
+}
+
+
+// replaces the running game, which is left as it was if the snapshot can't
+// be loaded
+pub fn load(engine: &mut Engine, bytes: &[u8]) -> Result<(), SnapshotError> {
+    let mut input = Reader::new(bytes);
+    if input.take(4)? != MAGIC { return Err(SnapshotError::BadMagic); }
+    let version = input.u16()?;
+    if version != VERSION { return Err(SnapshotError::Version(version)); }
+    input.u16()?;
+    let seed = [input.u64()?, input.u64()?];
+    let (time, frame) = (input.f32()?, input.u64()?);
+
+    let position = input.vec3()?;
+    let up = input.vec3()?;
+    let mut view = Matrix4::identity();
+    for value in view.matrix.as_flattened_mut() {
+        *value = input.f32()?;
+    }
+    let projection = match input.u8()? {
+        0 => Projection::Perspective { fov: input.f32()? },
+        1 => Projection::Orthographic { height: input.f32()? },
+        other => return Err(SnapshotError::UnknownProjection(other)),
+    };
+    let mut words = [0; BINDING_WORDS];
+    for word in words.iter_mut() {
+        *word = input.u32()?;
+    }
+    let bindings = InputMap::from_words(&words).ok_or(SnapshotError::BadBindings)?;
+
+    let mut game = Game::load(engine.assets(), &mut input)?;
+    if !input.finished() {
+        game.world.clear();
+        return Err(SnapshotError::TrailingBytes);
+    }
+
+    engine.replace_game(game, seed);
+    *engine.bindings_mut() = bindings;
+    engine.clock.restore(time, frame);
+    let camera = &mut engine.views.main_mut().camera;
+    camera.position = position;
+    camera.up = up;
+    camera.set_view_matrix(view);
+    camera.set_projection(projection);
+    Ok(())
+}
+
+
+// --------------------------------------------------------
+
+
+// mass and limits are set when a body is created, only its motion and
+// integrator are saved
+pub fn save_rigidbody(out: &mut Writer, rigidbody: &RigidBody) {
+    out.u8(rigidbody.integrator.code());
+    let motion = rigidbody.motion();
+    out.vec3(motion.force);
+    out.vec3(motion.velocity);
+    out.vec3(motion.acceleration);
+    out.vec3(motion.torque);
+    out.vec3(motion.angular_velocity);
+    out.vec3(motion.angular_acceleration);
+}
+
+pub fn load_rigidbody(
+    input: &mut Reader, rigidbody: &mut RigidBody
+) -> Result<(), SnapshotError> {
+    let code = input.u8()?;
+    rigidbody.integrator = Integrator::from_code(code)
+        .ok_or(SnapshotError::UnknownIntegrator(code))?;
+    rigidbody.set_motion(Motion {
+        force: input.vec3()?,
+        velocity: input.vec3()?,
+        acceleration: input.vec3()?,
+        torque: input.vec3()?,
+        angular_velocity: input.vec3()?,
+        angular_acceleration: input.vec3()?,
+    });
+    Ok(())
+}
+
+// length of a list, which has to fit in `room`
+pub fn count(input: &mut Reader, room: usize) -> Result<usize, SnapshotError> {
+    let count = input.u8()? as usize;
+    if count > room { return Err(SnapshotError::TooMany); }
+    Ok(count)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{ Input, Key };
+    use crate::platform::HeadlessPlatform;
+
+    fn engine() -> Engine {
+        let mut engine = Engine::new(&crate::load_assets());
+        engine.input = Input::with_keys(&[Key::W, Key::D, Key::Space]);
+        for _ in 1..200 {
+            engine.render(1.0 / 60.0);
+        }
+        engine
+    }
+
+    #[test]
+    fn load_after_save_round_trips() {
+        let platform = HeadlessPlatform::install();
+        let engine = engine();
+        let bytes = save(&engine);
+        let live = platform.live_entities();
+        assert!(!engine.game.world.ship().unwrap().gun1.bullets.is_empty());
+
+        let mut loaded = Engine::with_seed(&crate::load_assets(), [1, 1]);
+        load(&mut loaded, &bytes).unwrap();
+        assert_eq!(save(&loaded), bytes);
+        assert_eq!(loaded.seed(), engine.seed());
+
+        // the game that was running before the load is gone
+        assert_eq!(platform.live_entities(), 2 * live);
+    }
+
+    #[test]
+    fn bad_snapshots_leave_the_game_alone() {
+        let platform = HeadlessPlatform::install();
+        let mut engine = engine();
+        let bytes = save(&engine);
+        let live = platform.live_entities();
+
+        for bad in [
+            &bytes[0..bytes.len() - 1],
+            &bytes[0..bytes.len() / 2],
+            &[bytes.as_slice(), &[0]].concat(),
+        ] {
+            assert!(load(&mut engine, bad).is_err());
+            assert_eq!(platform.live_entities(), live);
+            assert_eq!(save(&engine), bytes);
+        }
+
+        let mut bad = bytes.clone();
+        bad[4] = 9;
+        assert_eq!(load(&mut engine, &bad), Err(SnapshotError::Version(9)));
+        let mut bad = bytes.clone();
+        bad[0] = b'X';
+        assert_eq!(load(&mut engine, &bad), Err(SnapshotError::BadMagic));
+    }
+}
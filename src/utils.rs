@@ -1,5 +1,10 @@
 
 
+use crate::engine::RenderDescriptor;
+use crate::platform;
+
+
+#[cfg(feature = "std")]
 pub fn set_panic_hook() {
     std::panic::set_hook(Box::new(|panic_info| {
         let mut msg = "Panic occurred".to_string();
@@ -15,36 +20,47 @@ pub fn set_panic_hook() {
             msg.push_str(format!(": {}", e).as_str());
         }
 
-        unsafe {
-            crate::js_console_error_raw(msg.as_ptr(), msg.len());
-        }
+        console_error(&msg);
     }));
 }
 
 
-pub fn webgl_add_entity(meta: &'static str) -> usize {
-    unsafe {
-        crate::js_add_entity(meta.as_ptr(), meta.len())
+// Without std there is no hook to set, panics in the browser end up here
+// instead. The message is formatted on the stack and the module traps
+// afterwards. Other targets bring their own handler.
+#[cfg(all(not(feature = "std"), target_arch = "wasm32", not(test)))]
+#[panic_handler]
+fn panic(panic_info: &core::panic::PanicInfo) -> ! {
+    use core::fmt::Write;
+
+    let mut msg = crate::log::MessageBuffer::new();
+    let _ = write!(msg, "Panic occurred");
+    if let Some(location) = panic_info.location() {
+        let _ = write!(msg, " in file '{}' at line {}", location.file(), location.line());
     }
+    let _ = write!(msg, ": {}", panic_info.message());
+    console_error(msg.as_str());
+
+    core::arch::wasm32::unreachable()
 }
 
-// pub fn webgl_destroy_entity(id: usize) {
-//     unsafe {
-//         crate::js_destroy_entity(id);
-//     }
-// }
+
+pub fn webgl_add_entity(descriptor: &RenderDescriptor) -> usize {
+    let wire = descriptor.to_wire();
+    platform::with(|p| p.add_entity(wire.as_bytes()))
+}
+
+pub fn webgl_destroy_entity(id: usize) {
+    platform::with(|p| p.destroy_entity(id));
+}
 
 
 pub fn console_log(text: &str) {
-    unsafe {
-        crate::js_console_log_raw(text.as_ptr(), text.len());
-    }
+    platform::with(|p| p.console_log(text));
 }
 
 pub fn console_error(text: &str) {
-    unsafe {
-        crate::js_console_error_raw(text.as_ptr(), text.len());
-    }
+    platform::with(|p| p.console_error(text));
 }
 
 
@@ -59,13 +75,8 @@ macro_rules! add_shader {
             let frag = include_str!(
                 concat!("shaders/", stringify!($name), ".frag")
             );
-            unsafe {
-                crate::js_add_shader(
-                    name.as_ptr(), name.len(),
-                    vert.as_ptr(), vert.len(),
-                    frag.as_ptr(), frag.len(),
-                );
-            }
+            $crate::platform::with(|p| p.add_shader(name, vert, frag));
+            $crate::engine::Shader::new(name, vert, frag)
         }
     }
 }
@@ -79,13 +90,12 @@ macro_rules! add_buffer {
             let data = include_str!(
                 concat!("data/", stringify!($name), ".json")
             );
-            unsafe {
-                crate::js_add_buffer_float(
-                    name.as_ptr(), name.len(),
-                    data.as_ptr(), data.len(),
-                    $size, $normalize
-                );
-            }
+            let size: f32 = $size;
+            let normalize: bool = $normalize;
+            $crate::platform::with(|p| p.add_buffer(
+                $crate::platform::BufferKind::Float, name, data, size, normalize
+            ));
+            $crate::engine::Buffer::new(name, size as usize, data)
         }
     };
     (bytes, $name:expr, $size:expr, $normalize:expr) => {
@@ -94,13 +104,31 @@ macro_rules! add_buffer {
             let data = include_str!(
                 concat!("data/", stringify!($name), ".json")
             );
-            unsafe {
-                crate::js_add_buffer_bytes(
-                    name.as_ptr(), name.len(),
-                    data.as_ptr(), data.len(),
-                    $size, $normalize
-                );
-            }
+            let size: f32 = $size;
+            let normalize: bool = $normalize;
+            $crate::platform::with(|p| p.add_buffer(
+                $crate::platform::BufferKind::Bytes, name, data, size, normalize
+            ));
+            $crate::engine::Buffer::new(name, size as usize, data)
+        }
+    }
+}
+
+
+// Sends a mesh imported from `assets/` by the build script to JS, the name
+// is the file name without its extension
+#[macro_export]
+macro_rules! add_mesh {
+    ($name:expr) => {
+        {
+            let name = stringify!($name);
+            let data: &'static [u8] = include_bytes!(
+                concat!(env!("OUT_DIR"), "/", stringify!($name), ".mesh")
+            );
+            let mesh = $crate::engine::Mesh::parse(name, data)
+                .expect(concat!("mesh ", stringify!($name)));
+            $crate::platform::with(|p| p.add_mesh(name, data));
+            mesh
         }
     }
 }
@@ -0,0 +1,301 @@
+// Runs the game natively against the headless platform, the way `js/main.js`
+// drives it in the browser.
+
+use std::collections::HashSet;
+
+use neutrino_demo::engine::{ Engine, Input, Key };
+use neutrino_demo::game::{ self, GameEvent };
+use neutrino_demo::engine::viewport::ALL_LAYERS;
+use neutrino_demo::engine::{ Scope, Counter };
+use neutrino_demo::log::{ self, Level };
+use neutrino_demo::engine::memory_buffer::{ decode, Command };
+use neutrino_demo::platform::{ HeadlessPlatform, Call };
+
+
+
+fn engine() -> (HeadlessPlatform, Engine) {
+    let platform = HeadlessPlatform::install();
+    let engine = Engine::new(&neutrino_demo::load_assets());
+    (platform, engine)
+}
+
+
+fn run(engine: &mut Engine, frames: std::ops::Range<u32>, keys: &[Key]) {
+    engine.input = Input::with_keys(keys);
+    for _ in frames {
+        engine.render(1.0 / 60.0);
+    }
+}
+
+
+fn entity_ids(platform: &HeadlessPlatform) -> Vec<usize> {
+    platform.calls().iter().filter_map(|call| match call {
+        Call::AddEntity { id, .. } => Some(*id),
+        _ => None,
+    }).collect()
+}
+
+
+#[test]
+fn assets_are_sent_before_entities() {
+    let (platform, _) = engine();
+    let calls = platform.calls();
+
+    let shaders: Vec<&str> = calls.iter().filter_map(|call| match call {
+        Call::AddShader { name } => Some(name.as_str()),
+        _ => None,
+    }).collect();
+    assert_eq!(shaders, vec!["vertex_color", "test", "debug_line"]);
+
+    let meshes: Vec<&str> = calls.iter().filter_map(|call| match call {
+        Call::AddMesh { name, .. } => Some(name.as_str()),
+        _ => None,
+    }).collect();
+    assert_eq!(meshes, vec!["cube", "ship", "quad"]);
+
+    let first_entity = calls.iter()
+        .position(|call| matches!(call, Call::AddEntity { .. }))
+        .unwrap();
+    assert!(calls[first_entity..].iter().all(|call| {
+        !matches!(call, Call::AddShader { .. } | Call::AddBuffer { .. } | Call::AddMesh { .. })
+    }));
+
+    for call in &calls {
+        if let Call::AddEntity { meta, .. } = call {
+            assert!(meta.starts_with(r#"{"shader":""#), "{}", meta);
+        }
+    }
+}
+
+
+#[test]
+fn render_frame_sends_a_matrix_for_every_entity() {
+    let (platform, mut engine) = engine();
+    run(&mut engine, 1..2, &[]);
+
+    let (header, commands) = decode(engine.frame.buffer.words()).unwrap();
+    assert_eq!(header.flags, 0);
+
+    let mut matrices = HashSet::new();
+    for command in commands {
+        if let Command::SetUniform { entity, block: 0, uniform: 0, values } = command {
+            assert_eq!(values.len(), 16);
+            assert!(command.values().all(f32::is_finite));
+            assert!(matrices.insert(entity as usize), "entity {} sent twice", entity);
+        }
+    }
+
+    let live: HashSet<usize> = entity_ids(&platform).into_iter().collect();
+    assert_eq!(live.len(), platform.live_entities());
+    assert_eq!(matrices, live);
+}
+
+
+#[test]
+fn thrust_moves_the_ship_and_camera() {
+    let (_, mut engine) = engine();
+    let start = engine.game.world.ship().unwrap().position;
+    let start_camera = engine.views.main().camera.position;
+
+    run(&mut engine, 1..120, &[Key::W]);
+
+    let ship = engine.game.world.ship().unwrap().position;
+    assert!((ship - start).len() > 1.0);
+    assert!((engine.views.main().camera.position - start_camera).len() > 1.0);
+
+    // the camera trails and leads the ship, and settles over it once it stops
+    run(&mut engine, 120..900, &[]);
+    let ship = engine.game.world.ship().unwrap();
+    assert!(ship.rigidbody.velocity().len() < 0.1);
+    let offset = engine.views.main().camera.position - ship.position;
+    assert!(offset.x.abs() < 1.0 && (offset.z - 0.1).abs() < 1.0);
+}
+
+
+#[test]
+fn bullets_are_created_and_destroyed_through_the_platform() {
+    let (platform, mut engine) = engine();
+    run(&mut engine, 1..2, &[]);
+    let live = platform.live_entities();
+    platform.clear_calls();
+
+    run(&mut engine, 2..300, &[Key::Space]);
+    assert!(platform.live_entities() > live);
+
+    // stop shooting and let every bullet run out its lifetime
+    run(&mut engine, 300..600, &[]);
+    assert_eq!(platform.live_entities(), live);
+
+    let calls = platform.calls();
+    let created = entity_ids(&platform);
+    let destroyed: Vec<usize> = calls.iter().filter_map(|call| match call {
+        Call::DestroyEntity(id) => Some(*id),
+        _ => None,
+    }).collect();
+    assert!(!created.is_empty());
+    assert_eq!(created.len(), destroyed.len());
+
+    // ids of destroyed bullets are handed out again
+    let unique: HashSet<&usize> = created.iter().collect();
+    assert!(unique.len() < created.len());
+
+    let (header, _) = decode(engine.frame.buffer.words()).unwrap();
+    assert_eq!(header.flags, 0);
+}
+
+
+#[test]
+fn paused_game_only_moves_when_stepped() {
+    let (_, mut engine) = engine();
+    run(&mut engine, 1..30, &[Key::W, Key::Space]);
+    let position = |engine: &Engine| engine.game.world.ship().unwrap().position;
+
+    engine.clock.pause();
+    let paused = position(&engine);
+    let time = engine.clock.time();
+    run(&mut engine, 0..20, &[Key::W, Key::Space]);
+    assert_eq!(position(&engine), paused);
+    assert_eq!(engine.clock.time(), time);
+    assert!(engine.frame.buffer.words().len() > 5);
+
+    engine.clock.step();
+    run(&mut engine, 0..5, &[Key::W, Key::Space]);
+    assert_ne!(position(&engine), paused);
+    assert_eq!(engine.clock.time(), time + 1.0 / 60.0);
+
+    // slow motion, game time runs at a quarter of the real time
+    engine.clock.resume();
+    engine.clock.set_scale(0.25);
+    let time = engine.clock.time();
+    let frame = engine.clock.frame();
+    run(&mut engine, 0..30, &[Key::W]);
+    assert!((engine.clock.time() - time - 30.0 / 60.0 / 4.0).abs() < 1e-4);
+    assert_eq!(engine.clock.frame(), frame + 30);
+}
+
+
+#[test]
+fn playing_publishes_events() {
+    let (_, mut engine) = engine();
+    run(&mut engine, 1..2, &[Key::W, Key::Space]);
+    let events: Vec<GameEvent> = engine.game.world.events.iter().copied().collect();
+    let fired = events.iter()
+        .filter(|event| matches!(event, GameEvent::BulletFired { .. }))
+        .count();
+    assert_eq!(fired, 2);
+    assert!(events.contains(&GameEvent::ThrusterFired { thruster: 4, throttle: 1.0 }));
+
+    // thrusters fire from input, before the guns
+    let first_shot = events.iter()
+        .position(|event| matches!(event, GameEvent::BulletFired { .. }))
+        .unwrap();
+    assert!(matches!(events[first_shot - 1], GameEvent::ThrusterFired { .. }));
+
+    run(&mut engine, 2..3, &[]);
+    assert!(engine.game.world.events.is_empty());
+    assert_eq!(engine.game.world.events.previous().count(), events.len());
+
+    run(&mut engine, 3..900, &[Key::D, Key::W, Key::Space]);
+    assert_eq!(engine.game.world.events.dropped(), 0);
+}
+
+
+#[test]
+fn every_view_gets_a_viewport_and_bullets_stay_off_the_minimap() {
+    let (_, mut engine) = engine();
+    run(&mut engine, 1..30, &[Key::Space]);
+
+    let (_, commands) = decode(engine.frame.buffer.words()).unwrap();
+    let mut viewports = Vec::new();
+    for command in commands {
+        if let Command::SetViewport { view, mask, .. } = command {
+            let values: Vec<f32> = command.values().collect();
+            viewports.push((view, mask, values));
+        }
+    }
+    assert_eq!(viewports.len(), engine.views.len());
+    assert_eq!(viewports[0].1, ALL_LAYERS);
+    assert_eq!(&viewports[0].2[0..4], &[0.0, 0.0, 1.0, 1.0]);
+
+    let (_, minimap_mask, values) = &viewports[game::MINIMAP];
+    assert_eq!(minimap_mask & game::EFFECTS_LAYER, 0);
+    assert_ne!(minimap_mask & game::WORLD_LAYER, 0);
+    let matrix = engine.views.get(game::MINIMAP).unwrap().camera.view_projection_matrix();
+    assert_eq!(&values[4..], matrix.matrix.as_flattened());
+
+    // the camera projections follow the canvas
+    engine.resize(1200.0, 600.0);
+    assert_eq!(engine.views.main().camera.aspect(), 2.0);
+    run(&mut engine, 30..31, &[]);
+    let main: Vec<f32> = decode(engine.frame.buffer.words()).unwrap().1
+        .find(|command| matches!(command, Command::SetViewport { view: 0, .. }))
+        .unwrap().values().collect();
+    let matrix = engine.views.main().camera.view_projection_matrix();
+    assert_eq!(&main[4..], matrix.matrix.as_flattened());
+
+    engine.restart(engine.seed());
+    assert_eq!(engine.views.main().camera.aspect(), 2.0);
+}
+
+
+#[test]
+fn frames_are_profiled_into_the_ring() {
+    let (platform, mut engine) = engine();
+    platform.set_clock_step(0.25);
+    run(&mut engine, 1..60, &[Key::W, Key::Space]);
+
+    let profiler = &engine.frame.profiler;
+    assert_eq!(profiler.recorded(), 59);
+    let last = profiler.record(0).unwrap();
+    assert_eq!(last.frame() as u64, engine.clock.frame());
+
+    let stages = [
+        Scope::Input, Scope::Ai, Scope::Physics,
+        Scope::Collision, Scope::Events, Scope::RenderUpload,
+    ];
+    assert!(stages.iter().all(|scope| last.time(*scope) > 0.0));
+    let total: f32 = stages.iter().map(|scope| last.time(*scope)).sum();
+    assert!(last.time(Scope::Frame) > total);
+
+    // the ship, its bullets and every asteroid
+    let asteroids = engine.game.world.asteroids.len() as u32;
+    assert!(last.counter(Counter::BodiesIntegrated) > 1 + asteroids);
+    assert!(last.counter(Counter::CollisionTests) >= asteroids * (asteroids - 1) / 2);
+    let words = engine.frame.buffer.words().len() as u32;
+    assert_eq!(last.counter(Counter::BufferWords), words);
+    assert_eq!(last.counter(Counter::BufferCapacity), 4000);
+    assert_eq!(last.counter(Counter::DroppedCommands), 0);
+
+    // a paused frame only uploads
+    engine.clock.pause();
+    run(&mut engine, 0..1, &[]);
+    let paused = engine.frame.profiler.record(0).unwrap();
+    assert_eq!(paused.time(Scope::Physics), 0.0);
+    assert!(paused.time(Scope::RenderUpload) > 0.0);
+    assert_eq!(paused.counter(Counter::BodiesIntegrated), 0);
+}
+
+
+#[test]
+fn ship_logging_is_turned_on_per_module() {
+    let (platform, mut engine) = engine();
+    let ship_logs = |platform: &HeadlessPlatform| platform.calls().iter().filter(|call| {
+        matches!(call, Call::Log { level: Level::Trace, module, .. } if module == "game::ship")
+    }).count();
+
+    run(&mut engine, 1..3, &[]);
+    assert_eq!(ship_logs(&platform), 0);
+
+    log::set_level("game::ship", Level::Trace).unwrap();
+    run(&mut engine, 3..5, &[]);
+    assert_eq!(ship_logs(&platform), 4);
+    assert!(platform.calls().iter().any(|call| matches!(
+        call, Call::Log { text, .. } if text.starts_with("ship at Vec3")
+    )));
+
+    // the most specific filter wins
+    log::set_level("game", Level::Off).unwrap();
+    run(&mut engine, 5..7, &[]);
+    assert_eq!(ship_logs(&platform), 8);
+    log::reset_levels();
+}
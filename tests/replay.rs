@@ -0,0 +1,183 @@
+// Records a session natively and replays it in a fresh engine, the replay
+// has to end up in exactly the same state.
+
+use neutrino_demo::engine::{ Engine, Input, Key, Action, Axis };
+use neutrino_demo::engine::input::{ Binding, AxisBinding };
+use neutrino_demo::math::Vec3;
+use neutrino_demo::physics::Integrator;
+use neutrino_demo::platform::HeadlessPlatform;
+use neutrino_demo::replay::Recording;
+
+
+
+// position, rotation, velocity and angular velocity of the ship, then of
+// every asteroid, then the positions of all bullets in flight
+fn state(engine: &Engine) -> Vec<Vec3> {
+    let world = &engine.game.world;
+    let ship = world.ship().unwrap();
+    let mut state = vec![
+        ship.position, ship.rotation,
+        ship.rigidbody.velocity(), ship.rigidbody.angular_velocity(),
+    ];
+    for (_, asteroid) in world.asteroids.iter() {
+        state.extend([
+            asteroid.position, asteroid.rotation,
+            asteroid.rigidbody.velocity(), asteroid.rigidbody.angular_velocity(),
+        ]);
+    }
+    for gun in [&ship.gun1, &ship.gun2] {
+        state.extend(gun.bullets.iter().map(|bullet| bullet.position));
+    }
+    state
+}
+
+
+fn play_session(engine: &mut Engine) {
+    for frame in 0..900 {
+        let mut input = Input::with_keys(match (frame / 60) % 5 {
+            0 => &[Key::W, Key::Space][..],
+            1 => &[Key::A, Key::W],
+            2 => &[Key::E],
+            3 => &[Key::D, Key::Space, Key::S],
+            _ => &[],
+        });
+        input.gamepad_axes[0] = ((frame as f32) * 0.05).sin();
+
+        // uneven frame times, like a real browser
+        let dt = if frame % 7 == 0 { 0.02 } else { 0.016 };
+        engine.input = input;
+        engine.render(dt);
+    }
+}
+
+
+#[test]
+fn replay_reproduces_the_session() {
+    HeadlessPlatform::install();
+    let mut engine = Engine::with_seed(&neutrino_demo::load_assets(), [5, 9]);
+    engine.start_recording();
+    play_session(&mut engine);
+    let recording = engine.stop_recording().unwrap();
+    let recorded = state(&engine);
+    let frame = engine.frame.buffer.words().to_vec();
+    assert_eq!(recording.len(), 900);
+
+    let bytes = recording.to_bytes();
+
+    HeadlessPlatform::install();
+    let mut engine = Engine::new(&neutrino_demo::load_assets());
+    engine.input = Input::with_keys(&[Key::W, Key::Q]);
+    engine.play(Recording::from_bytes(&bytes).unwrap());
+    assert_eq!(engine.seed(), [5, 9]);
+
+    // live time and input are ignored while playing
+    for _ in 0..900 {
+        assert!(engine.is_playing());
+        engine.render(1.0);
+    }
+
+    assert_eq!(state(&engine), recorded);
+    assert_eq!(engine.frame.buffer.words(), frame.as_slice());
+}
+
+
+#[test]
+fn recording_restarts_the_game() {
+    let platform = HeadlessPlatform::install();
+    let mut engine = Engine::new(&neutrino_demo::load_assets());
+    let start = state(&engine);
+    let live = platform.live_entities();
+
+    engine.input = Input::with_keys(&[Key::W, Key::Space]);
+    for _ in 0..120 {
+        engine.render(1.0 / 60.0);
+    }
+    assert_ne!(state(&engine), start);
+
+    engine.start_recording();
+    assert_eq!(state(&engine), start);
+    assert_eq!(platform.live_entities(), live);
+}
+
+
+#[test]
+fn replay_follows_rebinding() {
+    HeadlessPlatform::install();
+    let mut engine = Engine::with_seed(&neutrino_demo::load_assets(), [3, 4]);
+    engine.start_recording();
+    for frame in 0..600 {
+        if frame == 200 {
+            engine.bindings_mut().rebind(Action::Fire, Binding::Key(Key::F));
+            engine.bindings_mut().rebind_axis(Axis::Thrust, AxisBinding::Keys {
+                negative: Key::K, positive: Key::I
+            });
+        }
+        engine.input = Input::with_keys(match frame / 100 {
+            0 | 1 => &[Key::W, Key::Space][..],
+            _ => &[Key::I, Key::F, Key::D],
+        });
+        engine.render(1.0 / 60.0);
+    }
+    let recording = engine.stop_recording().unwrap();
+    let recorded = state(&engine);
+    let bytes = recording.to_bytes();
+
+    // the player here has the default bindings and rebinds while watching
+    HeadlessPlatform::install();
+    let mut engine = Engine::new(&neutrino_demo::load_assets());
+    let defaults = engine.frame.bindings;
+    engine.play(Recording::from_bytes(&bytes).unwrap());
+    for frame in 0..600 {
+        if frame == 100 {
+            engine.bindings_mut().rebind(Action::Fire, Binding::Key(Key::G));
+        }
+        engine.render(1.0 / 60.0);
+    }
+    assert_eq!(state(&engine), recorded);
+
+    // once the recording runs out the player's bindings are back
+    engine.render(1.0 / 60.0);
+    assert!(!engine.is_playing());
+    let mut expected = defaults;
+    expected.rebind(Action::Fire, Binding::Key(Key::G));
+    assert_eq!(engine.frame.bindings, expected);
+}
+
+
+#[test]
+fn replay_follows_integrator_changes() {
+    HeadlessPlatform::install();
+    let mut engine = Engine::with_seed(&neutrino_demo::load_assets(), [6, 2]);
+    engine.set_integrator(Integrator::SemiImplicitEuler);
+    engine.start_recording();
+    assert_eq!(engine.game.integrator(), Integrator::SemiImplicitEuler);
+    for frame in 0..600 {
+        if frame == 300 {
+            engine.set_integrator(Integrator::Rk4);
+        }
+        engine.input = Input::with_keys(&[Key::W, Key::A, Key::Space]);
+        engine.render(1.0 / 60.0);
+    }
+    let recording = engine.stop_recording().unwrap();
+    let recorded = state(&engine);
+    let bytes = recording.to_bytes();
+
+    // the player here has the default integrator and switches while watching
+    HeadlessPlatform::install();
+    let mut engine = Engine::new(&neutrino_demo::load_assets());
+    engine.play(Recording::from_bytes(&bytes).unwrap());
+    assert_eq!(engine.game.integrator(), Integrator::SemiImplicitEuler);
+    for frame in 0..600 {
+        if frame == 100 {
+            engine.set_integrator(Integrator::SemiImplicitEuler);
+        }
+        engine.render(1.0 / 60.0);
+    }
+    assert_eq!(state(&engine), recorded);
+    assert_eq!(engine.game.integrator(), Integrator::Rk4);
+
+    // once the recording runs out the player's integrator is back
+    engine.render(1.0 / 60.0);
+    assert!(!engine.is_playing());
+    assert_eq!(engine.game.integrator(), Integrator::SemiImplicitEuler);
+}
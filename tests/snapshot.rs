@@ -0,0 +1,77 @@
+// Saves a game halfway through a session and loads it into another engine,
+// both have to carry on exactly the same from there.
+
+use neutrino_demo::engine::{ Engine, Input, Key, Action };
+use neutrino_demo::engine::input::Binding;
+use neutrino_demo::math::Vec3;
+use neutrino_demo::platform::HeadlessPlatform;
+use neutrino_demo::snapshot;
+
+
+
+fn state(engine: &Engine) -> Vec<Vec3> {
+    let world = &engine.game.world;
+    let ship = world.ship().unwrap();
+    let mut state = vec![ship.position, ship.rotation, ship.rigidbody.velocity()];
+    for (_, asteroid) in world.asteroids.iter() {
+        state.extend([asteroid.position, asteroid.rotation, asteroid.rigidbody.velocity()]);
+    }
+    for gun in [&ship.gun1, &ship.gun2] {
+        state.extend(gun.bullets.iter().map(|bullet| bullet.position));
+    }
+    state
+}
+
+
+fn play(engine: &mut Engine, frames: std::ops::Range<u32>) {
+    for frame in frames {
+        engine.input = Input::with_keys(match (frame / 45) % 4 {
+            0 => &[Key::W, Key::Space][..],
+            1 => &[Key::A, Key::Space],
+            2 => &[Key::Q, Key::S],
+            _ => &[Key::D, Key::W, Key::Space],
+        });
+        engine.render(0.016);
+    }
+}
+
+
+#[test]
+fn loaded_snapshot_continues_like_the_original() {
+    HeadlessPlatform::install();
+    let mut engine = Engine::with_seed(&neutrino_demo::load_assets(), [3, 8]);
+    play(&mut engine, 1..300);
+    let bytes = snapshot::save(&engine);
+
+    let mut loaded = Engine::new(&neutrino_demo::load_assets());
+    play(&mut loaded, 1..40);
+    snapshot::load(&mut loaded, &bytes).unwrap();
+    assert_eq!(state(&loaded), state(&engine));
+
+    play(&mut engine, 300..600);
+    play(&mut loaded, 300..600);
+    assert_eq!(state(&loaded), state(&engine));
+    assert_eq!(snapshot::save(&loaded), snapshot::save(&engine));
+}
+
+
+#[test]
+fn loaded_snapshot_keeps_the_bindings() {
+    HeadlessPlatform::install();
+    let mut engine = Engine::with_seed(&neutrino_demo::load_assets(), [3, 8]);
+    play(&mut engine, 1..100);
+    engine.bindings_mut().rebind(Action::Fire, Binding::Key(Key::F));
+    let bytes = snapshot::save(&engine);
+
+    let mut loaded = Engine::new(&neutrino_demo::load_assets());
+    snapshot::load(&mut loaded, &bytes).unwrap();
+    assert_eq!(loaded.bindings(), engine.bindings());
+
+    for engine in [&mut engine, &mut loaded] {
+        engine.input = Input::with_keys(&[Key::F, Key::W]);
+        for _ in 0..60 {
+            engine.render(0.016);
+        }
+    }
+    assert_eq!(state(&loaded), state(&engine));
+}
@@ -0,0 +1,355 @@
+// Basic glTF 2.0: `.gltf` files with their buffers in data URIs or in files
+// next to them, and `.glb`. Every triangle primitive of the first mesh is
+// merged into one mesh. Node transforms, materials, skins, morph targets and
+// sparse accessors are not supported.
+
+use crate::ImportError;
+use crate::json::{ self, Json };
+use crate::mesh::{ Mesh, Vertex };
+
+
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const CHUNK_JSON: u32 = 0x4e4f_534a;
+const CHUNK_BIN: u32 = 0x004e_4942;
+
+const TRIANGLES: usize = 4;
+
+
+// `load` reads buffers that aren't embedded, by their uri
+pub fn parse(
+    text: &str, load: impl Fn(&str) -> Result<Vec<u8>, ImportError>
+) -> Result<Mesh, ImportError> {
+    read(&json::parse(text)?, None, load)
+}
+
+pub fn parse_glb(
+    bytes: &[u8], load: impl Fn(&str) -> Result<Vec<u8>, ImportError>
+) -> Result<Mesh, ImportError> {
+    let word = |at: usize| -> Result<u32, ImportError> {
+        let word = bytes.get(at..at + 4).ok_or(error("glb is truncated"))?;
+        Ok(u32::from_le_bytes(word.try_into().expect("4 bytes")))
+    };
+    if bytes.get(0..4) != Some(GLB_MAGIC) { return Err(error("not a glb file")); }
+    if word(4)? != 2 { return Err(error("only glTF 2.0 is supported")); }
+
+    let mut json = None;
+    let mut bin = None;
+    let mut at = 12;
+    while at < bytes.len().min(word(8)? as usize) {
+        let (len, kind) = (word(at)? as usize, word(at + 4)?);
+        let chunk = bytes.get(at + 8..at + 8 + len).ok_or(error("glb is truncated"))?;
+        match kind {
+            CHUNK_JSON => json = Some(chunk),
+            CHUNK_BIN => bin = bin.or(Some(chunk)),
+            _ => {},
+        }
+        at += 8 + len;
+    }
+    let json = json.ok_or(error("glb has no JSON chunk"))?;
+    let text = std::str::from_utf8(json).map_err(|_| error("glb JSON is not UTF-8"))?;
+    read(&json::parse(text)?, bin, load)
+}
+
+
+fn error(reason: &'static str) -> ImportError {
+    ImportError::Gltf(reason)
+}
+
+
+fn read(
+    json: &Json, bin: Option<&[u8]>, load: impl Fn(&str) -> Result<Vec<u8>, ImportError>
+) -> Result<Mesh, ImportError> {
+    let version = json.get("asset").and_then(|a| a.get("version")).and_then(Json::as_str);
+    if !version.is_some_and(|v| v.starts_with("2.")) {
+        return Err(error("only glTF 2.0 is supported"));
+    }
+
+    let buffers = json.get("buffers").and_then(Json::as_array).unwrap_or(&[]).iter()
+        .enumerate()
+        .map(|(i, buffer)| match buffer.get("uri").and_then(Json::as_str) {
+            Some(uri) if uri.starts_with("data:") => {
+                let (_, data) = uri.split_once(";base64,").ok_or(error("data uri is not base64"))?;
+                base64(data).ok_or(error("bad base64 in data uri"))
+            },
+            Some(uri) => load(uri),
+            // only the first buffer of a glb can leave out its uri
+            None if i == 0 => bin.map(<[u8]>::to_vec).ok_or(error("buffer has no data")),
+            None => Err(error("buffer has no data")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let file = File { json, buffers };
+
+    let primitives = json.get("meshes").and_then(|m| m.at(0))
+        .and_then(|m| m.get("primitives")).and_then(Json::as_array)
+        .ok_or(error("file has no mesh"))?;
+
+    let mut corners = Vec::new();
+    for primitive in primitives {
+        let mode = primitive.get("mode").and_then(Json::as_usize).unwrap_or(TRIANGLES);
+        if mode != TRIANGLES { return Err(error("only triangle lists are supported")); }
+
+        let attributes = primitive.get("attributes").ok_or(error("primitive has no attributes"))?;
+        let attribute = |name| -> Result<Option<Accessor>, ImportError> {
+            attributes.get(name).map(|index| file.accessor(index)).transpose()
+        };
+        let positions = attribute("POSITION")?.ok_or(error("primitive has no positions"))?;
+        let normals = attribute("NORMAL")?;
+        let uvs = attribute("TEXCOORD_0")?;
+        let colors = attribute("COLOR_0")?;
+
+        if positions.components != 3
+            || normals.as_ref().is_some_and(|a| a.components != 3)
+            || uvs.as_ref().is_some_and(|a| a.components != 2)
+            || colors.as_ref().is_some_and(|a| a.components < 3) {
+            return Err(error("attribute has the wrong type"));
+        }
+        let count = positions.count();
+        for accessor in [&normals, &uvs, &colors].into_iter().flatten() {
+            if accessor.count() != count { return Err(error("attributes differ in length")); }
+        }
+
+        let indices = match primitive.get("indices") {
+            Some(index) => file.accessor(index)?.values.iter()
+                .map(|&i| i as usize)
+                .collect(),
+            None => (0..count).collect::<Vec<_>>(),
+        };
+        for i in indices {
+            if i >= count { return Err(error("index out of range")); }
+            let p = positions.get(i);
+            corners.push(Vertex {
+                position: [p[0] as f32, p[1] as f32, p[2] as f32],
+                normal: normals.as_ref().map(|a| {
+                    let n = a.get(i);
+                    [n[0] as f32, n[1] as f32, n[2] as f32]
+                }),
+                // glTF puts the origin of uvs at the top left, GL at the
+                // bottom left like OBJ
+                uv: uvs.as_ref().map(|a| {
+                    let uv = a.get(i);
+                    [uv[0] as f32, 1.0 - uv[1] as f32]
+                }),
+                color: colors.as_ref().map(|a| {
+                    let c = a.get(i);
+                    let alpha = c.get(3).copied().unwrap_or(1.0);
+                    [c[0], c[1], c[2], alpha].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+                }),
+            });
+        }
+    }
+
+    Ok(Mesh::from_corners(corners))
+}
+
+
+// --------------------------------------------------------
+
+
+struct File<'a> {
+    json: &'a Json,
+    buffers: Vec<Vec<u8>>,
+}
+
+
+// an accessor read into f64s, which hold every component type exactly.
+// Normalized integers are already scaled.
+struct Accessor {
+    components: usize,
+    values: Vec<f64>,
+}
+
+
+impl Accessor {
+
+    fn count(&self) -> usize {
+        self.values.len() / self.components
+    }
+
+    fn get(&self, index: usize) -> &[f64] {
+        &self.values[index * self.components..][..self.components]
+    }
+
+}
+
+
+impl File<'_> {
+
+    fn accessor(&self, index: &Json) -> Result<Accessor, ImportError> {
+        let index = index.as_usize().ok_or(error("bad accessor index"))?;
+        let accessor = self.json.get("accessors").and_then(|a| a.at(index))
+            .ok_or(error("accessor not found"))?;
+        if accessor.get("sparse").is_some() { return Err(error("sparse accessors are not supported")); }
+
+        let usize_of = |json: &Json, key| json.get(key).and_then(Json::as_usize);
+        let count = usize_of(accessor, "count").ok_or(error("accessor has no count"))?;
+        let components = match accessor.get("type").and_then(Json::as_str) {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            _ => return Err(error("unsupported accessor type")),
+        };
+        let kind = usize_of(accessor, "componentType").ok_or(error("accessor has no component type"))?;
+        let normalized = accessor.get("normalized") == Some(&Json::Bool(true));
+        let size = match kind {
+            5120 | 5121 => 1,
+            5122 | 5123 => 2,
+            5125 | 5126 => 4,
+            _ => return Err(error("unsupported component type")),
+        };
+
+        let view = usize_of(accessor, "bufferView")
+            .and_then(|view| self.json.get("bufferViews")?.at(view))
+            .ok_or(error("accessor has no buffer view"))?;
+        let buffer = usize_of(view, "buffer").and_then(|b| self.buffers.get(b))
+            .ok_or(error("buffer not found"))?;
+        let start = usize_of(view, "byteOffset").unwrap_or(0)
+            + usize_of(accessor, "byteOffset").unwrap_or(0);
+        let stride = usize_of(view, "byteStride").unwrap_or(size * components);
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let at = start + element * stride + component * size;
+                let bytes = buffer.get(at..at + size).ok_or(error("accessor out of bounds"))?;
+                let mut word = [0; 4];
+                word[0..size].copy_from_slice(bytes);
+                let value = match kind {
+                    5120 => f64::from(word[0] as i8),
+                    5121 => f64::from(word[0]),
+                    5122 => f64::from(i16::from_le_bytes([word[0], word[1]])),
+                    5123 => f64::from(u16::from_le_bytes([word[0], word[1]])),
+                    5125 => f64::from(u32::from_le_bytes(word)),
+                    _ => f64::from(f32::from_le_bytes(word)),
+                };
+                values.push(match (normalized, kind) {
+                    (true, 5120) => (value / 127.0).max(-1.0),
+                    (true, 5121) => value / 255.0,
+                    (true, 5122) => (value / 32767.0).max(-1.0),
+                    (true, 5123) => value / 65535.0,
+                    _ => value,
+                });
+            }
+        }
+        Ok(Accessor { components, values })
+    }
+
+}
+
+
+fn base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let (mut bits, mut nbits) = (0u32, 0);
+    for c in text.bytes().take_while(|c| *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | u32::from(value);
+        nbits += 6;
+        if nbits >= 8 {
+            nbits -= 8;
+            out.push((bits >> nbits) as u8);
+        }
+    }
+    Some(out)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{ HAS_NORMALS, HAS_UVS, HAS_COLORS };
+
+    // a triangle with normals, uvs and normalized byte colors, the colors
+    // interleaved with padding to check strides
+    fn buffer() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let floats = [
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0_f32,
+            0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0,
+            0.0, 0.0, 1.0, 0.0, 0.0, 0.25,
+        ];
+        for f in floats {
+            bytes.extend_from_slice(&f.to_le_bytes());
+        }
+        for c in [[255, 0, 0], [0, 255, 0], [0, 0, 255]] {
+            bytes.extend_from_slice(&c);
+            bytes.push(0);
+        }
+        for i in [0u16, 1, 2, 2, 1, 0] {
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+        bytes
+    }
+
+    const GLTF: &str = r#"{
+        "asset": { "version": "2.0" },
+        "buffers": [{ "uri": "triangle.bin", "byteLength": 120 }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 96 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 12, "byteStride": 4 },
+            { "buffer": 0, "byteOffset": 108, "byteLength": 12 }
+        ],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 0, "byteOffset": 72, "componentType": 5126, "count": 3, "type": "VEC2" },
+            { "bufferView": 1, "componentType": 5121, "normalized": true, "count": 3, "type": "VEC3" },
+            { "bufferView": 2, "componentType": 5123, "count": 6, "type": "SCALAR" }
+        ],
+        "meshes": [{ "primitives": [{
+            "attributes": { "POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2, "COLOR_0": 3 },
+            "indices": 4
+        }] }]
+    }"#;
+
+    #[test]
+    fn reads_attributes_and_indices() {
+        let mesh = parse(GLTF, |uri| {
+            assert_eq!(uri, "triangle.bin");
+            Ok(buffer())
+        }).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.indices, [0, 1, 2, 2, 1, 0]);
+        assert_eq!(mesh.flags(), HAS_NORMALS | HAS_UVS | HAS_COLORS);
+        let v = mesh.vertices[1];
+        assert_eq!(v.position, [1.0, 0.0, 0.0]);
+        assert_eq!(v.normal, Some([0.0, 0.0, 1.0]));
+        assert_eq!(v.uv, Some([1.0, 1.0]));
+        assert_eq!(v.color, Some([0, 255, 0, 255]));
+        assert_eq!(mesh.vertices[2].uv, Some([0.0, 0.75]));
+    }
+
+    #[test]
+    fn reads_glb_and_data_uris() {
+        let mut json = GLTF.replace(r#""uri": "triangle.bin", "#, "").into_bytes();
+        while !json.len().is_multiple_of(4) { json.push(b' '); }
+        let bin = buffer();
+        let mut glb = Vec::new();
+        glb.extend_from_slice(GLB_MAGIC);
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        glb.extend_from_slice(&bin);
+
+        let no_files = |_: &str| -> Result<Vec<u8>, ImportError> { panic!("nothing to load") };
+        let from_glb = parse_glb(&glb, no_files).unwrap();
+        assert_eq!(from_glb, parse(GLTF, |_| Ok(buffer())).unwrap());
+
+        assert_eq!(base64("TWFueQ==").unwrap(), b"Many");
+        assert_eq!(base64("TWE").unwrap(), b"Ma");
+        let embedded = GLTF.replace("triangle.bin", "data:application/octet-stream;base64,AAAA");
+        assert_eq!(parse(&embedded, no_files).unwrap_err(), error("accessor out of bounds"));
+    }
+}
@@ -0,0 +1,257 @@
+// Just enough JSON to read glTF files
+
+use crate::ImportError;
+
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // keys in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+
+impl Json {
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn at(&self, index: usize) -> Option<&Json> {
+        self.as_array()?.get(index)
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    // whole, non negative numbers only
+    pub fn as_usize(&self) -> Option<usize> {
+        let number = self.as_f64()?;
+        (number >= 0.0 && number.fract() == 0.0).then_some(number as usize)
+    }
+
+}
+
+
+pub fn parse(text: &str) -> Result<Json, ImportError> {
+    let mut parser = Parser { bytes: text.as_bytes(), at: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.at != parser.bytes.len() { return Err(parser.error()); }
+    Ok(value)
+}
+
+
+// --------------------------------------------------------
+
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+
+impl Parser<'_> {
+
+    fn error(&self) -> ImportError {
+        ImportError::Json { offset: self.at }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.bytes.get(self.at).is_some_and(|b| b.is_ascii_whitespace()) {
+            self.at += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.at).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ImportError> {
+        if self.peek() != Some(byte) { return Err(self.error()); }
+        self.at += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, ImportError> {
+        if !self.bytes[self.at..].starts_with(word.as_bytes()) { return Err(self.error()); }
+        self.at += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, ImportError> {
+        match self.peek().ok_or(self.error())? {
+            b'{' => self.object(),
+            b'[' => self.array(),
+            b'"' => Ok(Json::String(self.string()?)),
+            b't' => self.literal("true", Json::Bool(true)),
+            b'f' => self.literal("false", Json::Bool(false)),
+            b'n' => self.literal("null", Json::Null),
+            _ => self.number(),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, ImportError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.at += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+            match self.peek() {
+                Some(b',') => self.at += 1,
+                Some(b'}') => { self.at += 1; return Ok(Json::Object(members)); },
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, ImportError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.at += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.at += 1,
+                Some(b']') => { self.at += 1; return Ok(Json::Array(items)); },
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ImportError> {
+        self.expect(b'"')?;
+        let mut text = String::new();
+        loop {
+            let start = self.at;
+            while self.bytes.get(self.at).is_some_and(|b| *b != b'"' && *b != b'\\') {
+                self.at += 1;
+            }
+            // the input is a str and we stopped on ascii, so this is whole characters
+            text.push_str(std::str::from_utf8(&self.bytes[start..self.at]).unwrap_or(""));
+            match self.bytes.get(self.at) {
+                Some(b'"') => { self.at += 1; return Ok(text); },
+                Some(b'\\') => {
+                    let escaped = *self.bytes.get(self.at + 1).ok_or(self.error())?;
+                    self.at += 2;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode()?,
+                        _ => return Err(self.error()),
+                    };
+                    text.push(c);
+                },
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    // the 4 hex digits after `\u`, and the low half of a surrogate pair
+    fn unicode(&mut self) -> Result<char, ImportError> {
+        let high = self.hex()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or(self.error());
+        }
+        if !self.bytes[self.at..].starts_with(b"\\u") { return Err(self.error()); }
+        self.at += 2;
+        let low = self.hex()?;
+        if !(0xdc00..0xe000).contains(&low) { return Err(self.error()); }
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).ok_or(self.error())
+    }
+
+    fn hex(&mut self) -> Result<u32, ImportError> {
+        let digits = self.bytes.get(self.at..self.at + 4).ok_or(self.error())?;
+        let digits = std::str::from_utf8(digits).map_err(|_| self.error())?;
+        let value = u32::from_str_radix(digits, 16).map_err(|_| self.error())?;
+        self.at += 4;
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Json, ImportError> {
+        let start = self.at;
+        while self.bytes.get(self.at)
+            .is_some_and(|b| b.is_ascii_digit() || b"+-.eE".contains(b)) {
+            self.at += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.at]).ok()
+            .and_then(|number| number.parse().ok())
+            .map(Json::Number)
+            .ok_or(ImportError::Json { offset: start })
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_nested_values() {
+        let json = parse(r#" {
+            "asset": { "version": "2.0" },
+            "list": [1, -2.5e1, true, null, "a\"é😀"],
+            "empty": [{}, []]
+        } "#).unwrap();
+        assert_eq!(json.get("asset").and_then(|a| a.get("version")), Some(&Json::String("2.0".into())));
+        let list = json.get("list").unwrap();
+        assert_eq!(list.at(0).and_then(Json::as_usize), Some(1));
+        assert_eq!(list.at(1).and_then(Json::as_f64), Some(-25.0));
+        assert_eq!(list.at(1).and_then(Json::as_usize), None);
+        assert_eq!(list.at(3), Some(&Json::Null));
+        assert_eq!(list.at(4).and_then(Json::as_str), Some("a\"é😀"));
+        assert_eq!(json.get("empty").and_then(|e| e.as_array()).map(|e| e.len()), Some(2));
+    }
+
+    #[test]
+    fn reports_where_it_went_wrong() {
+        assert_eq!(parse(r#"{"a": [1, 2,]}"#), Err(ImportError::Json { offset: 12 }));
+        assert_eq!(parse(r#"{"a": tru}"#), Err(ImportError::Json { offset: 6 }));
+        assert_eq!(parse("[1] 2"), Err(ImportError::Json { offset: 4 }));
+    }
+}
@@ -0,0 +1,55 @@
+// Asset pipeline for neutrino. Meshes are imported from Wavefront OBJ and
+// glTF files at build time and written in a binary format the engine embeds
+// with `include_bytes!` and JS uploads to webgl without parsing any text.
+// The engine's build script runs it over everything in `assets/`.
+
+use std::fs;
+use std::path::Path;
+
+pub mod mesh;
+pub use mesh::{ Mesh, Vertex };
+
+pub mod obj;
+pub mod gltf;
+pub mod json;
+
+
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ImportError {
+    Io(String),
+    UnknownFormat(String),
+    Obj { line: usize, reason: &'static str },
+    Json { offset: usize },
+    Gltf(&'static str),
+    // the index buffer holds u32s at most
+    TooManyVertices,
+}
+
+
+// the extensions `import` understands
+pub const EXTENSIONS: [&str; 3] = ["obj", "gltf", "glb"];
+
+
+// imports a mesh by its extension, buffers of a glTF file are looked up next
+// to it
+pub fn import(path: &Path) -> Result<Mesh, ImportError> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| ImportError::Io(format!("{}: {}", path.display(), e)))
+    };
+    let text = |bytes: Vec<u8>| {
+        String::from_utf8(bytes).map_err(|_| ImportError::Io(format!("{} is not UTF-8", path.display())))
+    };
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let load = |uri: &str| read(&dir.join(uri));
+
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let mesh = match extension.to_ascii_lowercase().as_str() {
+        "obj" => obj::parse(&text(read(path)?)?)?,
+        "gltf" => gltf::parse(&text(read(path)?)?, load)?,
+        "glb" => gltf::parse_glb(&read(path)?, load)?,
+        _ => return Err(ImportError::UnknownFormat(path.display().to_string())),
+    };
+    if mesh.vertices.len() > u32::MAX as usize { return Err(ImportError::TooManyVertices); }
+    Ok(mesh)
+}
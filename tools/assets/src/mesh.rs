@@ -0,0 +1,193 @@
+// An imported triangle mesh and the binary format the engine embeds. The
+// format is read by `engine::mesh` in Rust and `Mesh` in `js/webgl.js`, keep
+// the three in step.
+//
+// Binary format, little endian:
+//
+//   header:  "NMSH", version u16, flags u16, vertices u32, indices u32
+//   streams: positions f32 x3, then normals f32 x3, uvs f32 x2 and colors
+//            u8 x4 for the ones set in the flags, one stream after the other
+//   indices: u16, or u32 with WIDE_INDICES
+//
+// Every stream starts on a multiple of 4 bytes, so JS can point attributes
+// straight at them.
+
+use std::collections::HashMap;
+
+
+
+pub const MAGIC: &[u8; 4] = b"NMSH";
+pub const VERSION: u16 = 1;
+
+pub const HAS_NORMALS: u16 = 1 << 0;
+pub const HAS_UVS: u16 = 1 << 1;
+pub const HAS_COLORS: u16 = 1 << 2;
+pub const WIDE_INDICES: u16 = 1 << 3;
+
+
+
+// One vertex with everything a mesh can carry, missing attributes are left
+// at their defaults and dropped from the output
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: Option<[f32; 3]>,
+    pub uv: Option<[f32; 2]>,
+    pub color: Option<[u8; 4]>,
+}
+
+
+impl Vertex {
+
+    // bit patterns, so equal vertices can be looked up in a map
+    fn key(&self) -> [u32; 13] {
+        let mut key = [0; 13];
+        for (k, v) in key.iter_mut().zip(self.position) {
+            *k = v.to_bits();
+        }
+        if let Some(normal) = self.normal {
+            for (k, v) in key[3..6].iter_mut().zip(normal) {
+                *k = v.to_bits();
+            }
+            key[12] |= 1;
+        }
+        if let Some(uv) = self.uv {
+            key[6] = uv[0].to_bits();
+            key[7] = uv[1].to_bits();
+            key[12] |= 2;
+        }
+        if let Some(color) = self.color {
+            key[8] = u32::from_le_bytes(color);
+            key[12] |= 4;
+        }
+        key
+    }
+
+}
+
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+
+impl Mesh {
+
+    // builds an indexed mesh from a list of triangle corners, corners that
+    // are the same in every attribute share one vertex
+    pub fn from_corners(corners: impl IntoIterator<Item = Vertex>) -> Self {
+        let mut mesh = Mesh::default();
+        let mut seen = HashMap::new();
+        for corner in corners {
+            let index = *seen.entry(corner.key()).or_insert_with(|| {
+                mesh.vertices.push(corner);
+                mesh.vertices.len() as u32 - 1
+            });
+            mesh.indices.push(index);
+        }
+        mesh
+    }
+
+    // attributes only make it into the output if every vertex has them
+    pub fn flags(&self) -> u16 {
+        let all = |has: fn(&Vertex) -> bool| {
+            !self.vertices.is_empty() && self.vertices.iter().all(has)
+        };
+        let mut flags = 0;
+        if all(|v| v.normal.is_some()) { flags |= HAS_NORMALS; }
+        if all(|v| v.uv.is_some()) { flags |= HAS_UVS; }
+        if all(|v| v.color.is_some()) { flags |= HAS_COLORS; }
+        if self.vertices.len() > u16::MAX as usize + 1 { flags |= WIDE_INDICES; }
+        flags
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let flags = self.flags();
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&flags.to_le_bytes());
+        out.extend_from_slice(&(self.vertices.len() as u32).to_le_bytes());
+        out.extend_from_slice(&(self.indices.len() as u32).to_le_bytes());
+
+        let floats = |out: &mut Vec<u8>, values: &[f32]| {
+            for value in values {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+        for v in &self.vertices {
+            floats(&mut out, &v.position);
+        }
+        if flags & HAS_NORMALS != 0 {
+            for v in &self.vertices {
+                floats(&mut out, &v.normal.unwrap_or_default());
+            }
+        }
+        if flags & HAS_UVS != 0 {
+            for v in &self.vertices {
+                floats(&mut out, &v.uv.unwrap_or_default());
+            }
+        }
+        if flags & HAS_COLORS != 0 {
+            for v in &self.vertices {
+                out.extend_from_slice(&v.color.unwrap_or_default());
+            }
+        }
+
+        for &index in &self.indices {
+            if flags & WIDE_INDICES != 0 {
+                out.extend_from_slice(&index.to_le_bytes());
+            } else {
+                out.extend_from_slice(&(index as u16).to_le_bytes());
+            }
+        }
+        out
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corner(x: f32, color: Option<[u8; 4]>) -> Vertex {
+        Vertex { position: [x, 0.0, 0.0], color, ..Vertex::default() }
+    }
+
+    #[test]
+    fn shared_corners_are_indexed_once() {
+        let red = Some([255, 0, 0, 255]);
+        let mesh = Mesh::from_corners([
+            corner(0.0, red), corner(1.0, red), corner(2.0, red),
+            corner(2.0, red), corner(1.0, red), corner(1.0, None),
+        ]);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 2, 1, 3]);
+        // one vertex has no color, so nobody gets one
+        assert_eq!(mesh.flags(), 0);
+    }
+
+    #[test]
+    fn encodes_header_streams_and_indices() {
+        let mesh = Mesh::from_corners((0..3).map(|i| Vertex {
+            position: [i as f32, 0.0, 0.0],
+            uv: Some([0.5, 0.25]),
+            color: Some([1, 2, 3, 4]),
+            ..Vertex::default()
+        }));
+        let bytes = mesh.encode();
+        assert_eq!(&bytes[0..4], MAGIC);
+        assert_eq!(bytes[4..6], VERSION.to_le_bytes());
+        assert_eq!(bytes[6..8], (HAS_UVS | HAS_COLORS).to_le_bytes());
+        assert_eq!(bytes[8..12], 3u32.to_le_bytes());
+        assert_eq!(bytes[12..16], 3u32.to_le_bytes());
+        assert_eq!(bytes.len(), 16 + 3 * 12 + 3 * 8 + 3 * 4 + 3 * 2);
+        assert_eq!(bytes[16 + 12..16 + 16], 1.0f32.to_le_bytes());
+        assert_eq!(bytes[16 + 36 + 24..16 + 36 + 28], [1, 2, 3, 4]);
+        assert_eq!(bytes[bytes.len() - 2..], 2u16.to_le_bytes());
+    }
+}
@@ -0,0 +1,146 @@
+// Wavefront OBJ: positions, uvs, normals and faces. Faces with more than
+// three corners are split into a fan. Colors can follow the position as
+// `v x y z r g b`, the way most exporters write vertex colors. Materials,
+// groups, lines and everything else are skipped.
+
+use crate::ImportError;
+use crate::mesh::{ Mesh, Vertex };
+
+
+
+pub fn parse(source: &str) -> Result<Mesh, ImportError> {
+    let mut positions: Vec<([f32; 3], Option<[u8; 4]>)> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut corners = Vec::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let error = |reason| ImportError::Obj { line: number + 1, reason };
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else { continue };
+        let rest: Vec<&str> = words.collect();
+
+        match keyword {
+            "v" => {
+                let values = floats(&rest).ok_or(error("bad number"))?;
+                match values[..] {
+                    [x, y, z] | [x, y, z, _] => positions.push(([x, y, z], None)),
+                    [x, y, z, r, g, b] => positions.push(([x, y, z], Some(color([r, g, b])))),
+                    _ => return Err(error("a position needs 3 values")),
+                }
+            },
+            "vt" => {
+                let values = floats(&rest).ok_or(error("bad number"))?;
+                match values[..] {
+                    [u] => uvs.push([u, 0.0]),
+                    [u, v] | [u, v, _] => uvs.push([u, v]),
+                    _ => return Err(error("a uv needs 1 to 3 values")),
+                }
+            },
+            "vn" => {
+                let values = floats(&rest).ok_or(error("bad number"))?;
+                match values[..] {
+                    [x, y, z] => normals.push([x, y, z]),
+                    _ => return Err(error("a normal needs 3 values")),
+                }
+            },
+            "f" => {
+                if rest.len() < 3 { return Err(error("a face needs 3 corners")); }
+                let face = rest.iter()
+                    .map(|corner| {
+                        let mut parts = corner.split('/');
+                        let position = parts.next().unwrap_or("");
+                        let (position, color) = *lookup(&positions, position)
+                            .ok_or(error("bad position index"))?;
+                        let uv = match parts.next() {
+                            None | Some("") => None,
+                            Some(uv) => Some(*lookup(&uvs, uv).ok_or(error("bad uv index"))?),
+                        };
+                        let normal = match parts.next() {
+                            None | Some("") => None,
+                            Some(normal) => Some(
+                                *lookup(&normals, normal).ok_or(error("bad normal index"))?
+                            ),
+                        };
+                        Ok(Vertex { position, normal, uv, color })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                for i in 1..face.len() - 1 {
+                    corners.extend([face[0], face[i], face[i + 1]]);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    Ok(Mesh::from_corners(corners))
+}
+
+
+fn floats(words: &[&str]) -> Option<Vec<f32>> {
+    words.iter().map(|word| word.parse().ok()).collect()
+}
+
+fn color(rgb: [f32; 3]) -> [u8; 4] {
+    let [r, g, b] = rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    [r, g, b, 255]
+}
+
+// indices start at 1, negative ones count back from the last element
+fn lookup<'a, T>(items: &'a [T], index: &str) -> Option<&'a T> {
+    let index: isize = index.parse().ok()?;
+    let index = match index {
+        0 => return None,
+        i if i < 0 => items.len().checked_sub(i.unsigned_abs())?,
+        i => i as usize - 1,
+    };
+    items.get(index)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{ HAS_NORMALS, HAS_UVS, HAS_COLORS };
+
+    #[test]
+    fn quads_are_split_and_corners_shared() {
+        let mesh = parse("
+            # a unit quad facing up
+            o quad
+            v 0 0 0 1 0 0
+            v 1 0 0 1 0 0
+            v 1 0 1 1 0 0
+            v 0 0 1 1 0 0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vt 0 1
+            vn 0 1 0
+            usemtl none
+            f 1/1/1 2/2/1 3/3/1 4/4/1
+            f -4/-4/-1 -2/-2/-1 -1/-1/-1
+        ").unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, [0, 1, 2, 0, 2, 3, 0, 2, 3]);
+        assert_eq!(mesh.flags(), HAS_NORMALS | HAS_UVS | HAS_COLORS);
+        assert_eq!(mesh.vertices[2].uv, Some([1.0, 1.0]));
+        assert_eq!(mesh.vertices[0].color, Some([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        assert_eq!(
+            parse("v 0 0 0\nv 1 0 0\nf 1 2 3").unwrap_err(),
+            ImportError::Obj { line: 3, reason: "bad position index" },
+        );
+        assert_eq!(
+            parse("v 0 zero 0").unwrap_err(),
+            ImportError::Obj { line: 1, reason: "bad number" },
+        );
+        let mesh = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1//1 2 3").unwrap_err();
+        assert_eq!(mesh, ImportError::Obj { line: 4, reason: "bad normal index" });
+    }
+}